@@ -0,0 +1,190 @@
+//! Change notifications over Redis pub/sub.
+//!
+//! When a notification channel is configured with
+//! [`ConfigBuilder::notification_channel`], [`RedisCache::update`] publishes
+//! the changes it makes on that channel, in the same pipeline as the changes
+//! themselves. Every update publishes a single message, holding one record per
+//! line such as `set MEMBER:{<guild_id>}:<user_id>` or `delete CHANNEL:{<guild_id>}:<id>`.
+//! Keys are written without namespace.
+//!
+//! Other processes read the changes with a [`ChangeSubscriber`].
+//!
+//! [`ConfigBuilder::notification_channel`]: crate::ConfigBuilder::notification_channel
+//! [`RedisCache::update`]: crate::RedisCache::update
+
+use futures_core::Stream;
+use futures_util::{stream, StreamExt};
+use redis::aio::PubSub;
+
+use super::RedisKey;
+use crate::Error;
+
+/// A change made to a value of the cache.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum CacheChange {
+    /// The value of the key was created or replaced.
+    Set(RedisKey),
+    /// The value of the key was deleted.
+    Delete(RedisKey),
+}
+
+impl CacheChange {
+    /// Returns the key of the changed value, which tells its resource type and
+    /// ids.
+    pub const fn key(&self) -> RedisKey {
+        match self {
+            Self::Set(key) | Self::Delete(key) => *key,
+        }
+    }
+
+    pub(crate) fn write(&self, out: &mut Vec<u8>) {
+        let (operation, key) = match self {
+            Self::Set(key) => (&b"set "[..], key),
+            Self::Delete(key) => (&b"delete "[..], key),
+        };
+
+        out.extend_from_slice(operation);
+        out.extend_from_slice(&key.to_namespaced_bytes(None));
+    }
+
+    fn parse(record: &[u8]) -> Result<Self, Error> {
+        let parse_error = || Error::Parse {
+            msg: "It is not a change record.".to_owned(),
+            response: String::from_utf8_lossy(record).into_owned(),
+        };
+
+        let index = record
+            .iter()
+            .position(|byte| *byte == b' ')
+            .ok_or_else(parse_error)?;
+        let key = RedisKey::parse(&record[index + 1..]).ok_or_else(parse_error)?;
+
+        match &record[..index] {
+            b"set" => Ok(Self::Set(key)),
+            b"delete" => Ok(Self::Delete(key)),
+            _ => Err(parse_error()),
+        }
+    }
+
+    /// Decode the records of a message published by [`RedisCache::update`].
+    ///
+    /// [`RedisCache::update`]: crate::RedisCache::update
+    pub fn parse_message(message: &[u8]) -> impl Iterator<Item = Result<Self, Error>> + '_ {
+        message
+            .split(|byte| *byte == b'\n')
+            .filter(|record| !record.is_empty())
+            .map(Self::parse)
+    }
+}
+
+/// Receiver of the changes published on a notification channel.
+pub struct ChangeSubscriber(PubSub);
+
+impl ChangeSubscriber {
+    /// Subscribe to the changes published on the channel.
+    ///
+    /// The pub/sub connection is created with
+    /// [`redis::Client::get_async_pubsub`], as a connection in subscriber
+    /// mode cannot run other commands.
+    pub async fn new(mut pubsub: PubSub, channel: &str) -> Result<Self, Error> {
+        pubsub.subscribe(channel).await?;
+
+        Ok(Self(pubsub))
+    }
+
+    /// Stream the changes in the order they were published.
+    ///
+    /// A record which cannot be decoded is returned as an error, without
+    /// ending the stream.
+    pub fn into_stream(self) -> impl Stream<Item = Result<CacheChange, Error>> {
+        self.0.into_on_message().flat_map(|message| {
+            let changes: Vec<_> = CacheChange::parse_message(message.get_payload_bytes()).collect();
+            stream::iter(changes)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+    use redis::Client;
+    use twilight_model::{
+        gateway::payload::incoming::{ChannelCreate, ChannelDelete, RoleCreate},
+        id::Id,
+    };
+
+    use super::{CacheChange, ChangeSubscriber};
+    use crate::{cache::RedisKey, test, ConfigBuilder};
+
+    #[test]
+    fn test_parse_message() {
+        let guild_id = Id::new(1);
+        let user_id = Id::new(2);
+
+        let changes: Vec<_> =
+            CacheChange::parse_message(b"set MEMBER:{1}:2\ndelete GUILD:{1}\nset FOO:1").collect();
+
+        assert_eq!(
+            changes[0].as_ref().unwrap(),
+            &CacheChange::Set(RedisKey::Member { guild_id, user_id })
+        );
+        assert_eq!(
+            changes[1].as_ref().unwrap(),
+            &CacheChange::Delete(RedisKey::Guild { id: guild_id })
+        );
+        assert!(changes[2].is_err());
+    }
+
+    #[test]
+    fn test_subscriber() {
+        test::block_on(async {
+            let channel = "test_subscriber";
+            let mut cache = test::redis_cache_with_config(
+                ConfigBuilder::default()
+                    .notification_channel(channel)
+                    .build(),
+            )
+            .await;
+            let guild_id = Id::new(911);
+            let channel_id = Id::new(912);
+            let role_id = Id::new(913);
+
+            let url = option_env!("TEST_REDIS_URL").unwrap_or("redis://127.0.0.1");
+            let pubsub = Client::open(url).unwrap().get_async_pubsub().await.unwrap();
+            let mut changes = ChangeSubscriber::new(pubsub, channel)
+                .await
+                .unwrap()
+                .into_stream();
+
+            let channel = test::model::channel(channel_id, Some(guild_id));
+            cache.update(ChannelCreate(channel.clone())).await.unwrap();
+            cache
+                .update(RoleCreate {
+                    guild_id,
+                    role: test::model::role(role_id),
+                })
+                .await
+                .unwrap();
+            cache.update(ChannelDelete(channel)).await.unwrap();
+
+            assert_eq!(
+                changes.next().await.unwrap().unwrap(),
+                CacheChange::Set(RedisKey::Channel {
+                    guild_id: Some(guild_id),
+                    channel_id
+                })
+            );
+            assert_eq!(
+                changes.next().await.unwrap().unwrap(),
+                CacheChange::Set(RedisKey::Role { guild_id, role_id })
+            );
+            assert_eq!(
+                changes.next().await.unwrap().unwrap(),
+                CacheChange::Delete(RedisKey::Channel {
+                    guild_id: Some(guild_id),
+                    channel_id
+                })
+            );
+        });
+    }
+}
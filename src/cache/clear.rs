@@ -0,0 +1,232 @@
+use super::{sweep::scan_nodes, RedisKey};
+use crate::{config::ResourceType, CacheStrategy, Connection, Error, RedisCache};
+
+/// Number of keys asked to each `SCAN`, and so deleted in a single round trip.
+const BATCH_SIZE: usize = 1000;
+
+/// Keys owned by each resource type, as the names of the keys with ids and the
+/// keys without.
+const KEYS: &[(ResourceType, &[&str], &[RedisKey])] = &[
+    (
+        ResourceType::CHANNEL,
+        &[
+            "CHANNEL",
+            "GUILD_CHANNELS",
+            "CHANNEL_THREADS",
+            "GUILD_ACTIVE_THREADS",
+        ],
+        &[],
+    ),
+    (ResourceType::EMOJI, &["EMOJI", "GUILD_EMOJIS"], &[]),
+    (
+        ResourceType::GUILD,
+        &["GUILD"],
+        &[RedisKey::Guilds, RedisKey::UnavailableGuilds],
+    ),
+    (
+        ResourceType::INTEGRATION,
+        &["INTEGRATION", "GUILD_INTEGRATIONS"],
+        &[],
+    ),
+    (ResourceType::MEMBER, &["MEMBER", "GUILD_MEMBERS"], &[]),
+    (
+        ResourceType::MESSAGE,
+        &["MESSAGE", "CHANNEL_MESSAGES", "CHANNEL_PINS"],
+        &[],
+    ),
+    (
+        ResourceType::PRESENCE,
+        &["PRESENCE", "GUILD_PRESENCES"],
+        &[],
+    ),
+    (ResourceType::ROLE, &["ROLE", "GUILD_ROLES"], &[]),
+    (ResourceType::USER_CURRENT, &[], &[RedisKey::CurrentUser]),
+    (
+        ResourceType::USER,
+        &["USER", "USER_GUILDS"],
+        &[RedisKey::Users],
+    ),
+    (
+        ResourceType::VOICE_STATE,
+        &["VOICE_STATE", "GUILD_VOICE_STATES", "CHANNEL_VOICE_STATES"],
+        &[],
+    ),
+    (
+        ResourceType::STAGE_INSTANCE,
+        &["STAGE_INSTANCE", "GUILD_STAGE_INSTANCES"],
+        &[],
+    ),
+    (ResourceType::STICKER, &["STICKER", "GUILD_STICKERS"], &[]),
+    (
+        ResourceType::THREAD_MEMBER,
+        &["THREAD_MEMBER", "THREAD_MEMBERS"],
+        &[],
+    ),
+    (
+        ResourceType::SHARD,
+        &["SHARD_SESSION"],
+        &[RedisKey::ShardCount, RedisKey::GuildShards],
+    ),
+];
+
+impl<S: CacheStrategy> RedisCache<S> {
+    /// Delete every key of the cache, including the event log, returning the
+    /// number of keys deleted.
+    ///
+    /// The keys are found with `SCAN` on the namespace of the cache, so other
+    /// data in the same database is kept as long as its keys do not look like
+    /// keys of the cache. Setting a [`namespace`] makes sure of it.
+    ///
+    /// [`namespace`]: crate::ConfigBuilder::namespace
+    pub async fn clear(&self) -> Result<usize, Error> {
+        let deleted = self.clear_resource(ResourceType::all()).await?;
+        let mut conn = self.get_connection().await?;
+
+        Ok(deleted + self.delete_keys(&mut conn, &[RedisKey::EventLog]).await?)
+    }
+
+    /// Delete every key of the given resource types, like [`clear`], returning
+    /// the number of keys deleted.
+    ///
+    /// The index sets of a resource type are deleted along with its values,
+    /// such as `GUILD_MEMBERS` for [`ResourceType::MEMBER`].
+    ///
+    /// [`clear`]: Self::clear
+    pub async fn clear_resource(&self, resource_type: ResourceType) -> Result<usize, Error> {
+        let mut conn = self.get_connection().await?;
+        let mut deleted = 0;
+
+        for (key_resource_type, names, keys) in KEYS {
+            if !resource_type.contains(*key_resource_type) {
+                continue;
+            }
+
+            for name in *names {
+                deleted += self.clear_keys(&mut conn, name).await?;
+            }
+
+            deleted += self.delete_keys(&mut conn, keys).await?;
+        }
+
+        Ok(deleted)
+    }
+
+    /// Delete every key named `name` with an id, a page of `SCAN` at a time.
+    async fn clear_keys(&self, conn: &mut Connection<'_>, name: &str) -> Result<usize, Error> {
+        let mut deleted = 0;
+
+        for node in scan_nodes(conn).await? {
+            let mut cursor = 0;
+
+            loop {
+                let (next, keys) = self
+                    .scan_page(conn, &node, name, cursor, BATCH_SIZE)
+                    .await?;
+
+                let keys: Vec<RedisKey> =
+                    keys.iter().filter_map(|key| self.parse_key(key)).collect();
+                deleted += self.delete_keys(conn, &keys).await?;
+
+                if next == 0 {
+                    break;
+                }
+                cursor = next;
+            }
+        }
+
+        Ok(deleted)
+    }
+
+    /// Delete the keys, publishing the deletion of the values among them.
+    async fn delete_keys(
+        &self,
+        conn: &mut Connection<'_>,
+        keys: &[RedisKey],
+    ) -> Result<usize, Error> {
+        if keys.is_empty() {
+            return Ok(0);
+        }
+
+        let mut pipe = self.pipe();
+        for key in keys {
+            if key.is_value() {
+                pipe.delete_resource(*key);
+            } else {
+                pipe.pipeline.del(pipe.key(*key));
+            }
+        }
+        pipe.publish_changes();
+
+        let deleted: Vec<redis::Value> = pipe.pipeline.query_async(conn).await?;
+
+        Ok(deleted
+            .iter()
+            .take(keys.len())
+            .filter(|deleted| matches!(deleted, redis::Value::Int(1)))
+            .count())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use redis::AsyncCommands;
+    use twilight_model::{gateway::payload::incoming::GuildCreate, id::Id};
+
+    use crate::{cache::RedisKey, config::ResourceType, test, ConfigBuilder};
+
+    #[test]
+    fn test_clear() {
+        test::block_on(async {
+            let mut cache =
+                test::redis_cache_with_config(ConfigBuilder::default().namespace("clear").build())
+                    .await;
+            let other = test::redis_cache_with_config(
+                ConfigBuilder::default().namespace("clear_other").build(),
+            )
+            .await;
+            let guild_id = Id::new(981);
+            let user_id = Id::new(982);
+            let role_id = Id::new(983);
+
+            let mut guild = test::model::guild(guild_id);
+            guild.members = vec![test::model::member(user_id)];
+            guild.roles = vec![test::model::role(role_id)];
+            cache.update(GuildCreate(guild)).await.unwrap();
+
+            let mut conn = other.get_connection().await.unwrap();
+            let other_key = other.key(RedisKey::Role { guild_id, role_id });
+            let _: () = conn.set(&other_key, "other").await.unwrap();
+
+            assert!(cache.clear_resource(ResourceType::ROLE).await.unwrap() >= 2);
+
+            let mut conn = cache.get_connection().await.unwrap();
+            assert!(cache
+                .get_role(&mut conn, guild_id, role_id)
+                .await
+                .unwrap()
+                .is_none());
+            assert!(cache
+                .get_member(&mut conn, guild_id, user_id)
+                .await
+                .unwrap()
+                .is_some());
+
+            cache.clear().await.unwrap();
+
+            let exists: bool = conn
+                .exists(cache.key(RedisKey::Member { guild_id, user_id }))
+                .await
+                .unwrap();
+            assert!(!exists);
+            let exists: bool = conn
+                .exists(cache.key(RedisKey::GuildMembers { guild_id }))
+                .await
+                .unwrap();
+            assert!(!exists);
+            let exists: bool = conn.exists(cache.key(RedisKey::Guilds)).await.unwrap();
+            assert!(!exists);
+            let exists: bool = conn.exists(&other_key).await.unwrap();
+            assert!(exists);
+        });
+    }
+}
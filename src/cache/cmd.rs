@@ -1,7 +1,7 @@
 use redis::AsyncCommands;
 
 use super::{helper::AsyncIter, FromCachedRedisValue, Pipe, ToBytes};
-use crate::{cache::RedisKey, CacheStrategy, Connection, Error};
+use crate::{cache::RedisKey, codec::Codec, CacheStrategy, Connection, Error};
 
 /// Re-exported items for use in generated code by macros.
 #[allow(unused_imports)]
@@ -16,22 +16,22 @@ pub(crate) mod __export {
     };
 }
 
-pub async fn scan<'a, 'stmt, V: super::FromBytes>(
+pub async fn scan<'a, 'stmt, C: Codec, V: super::FromBytes>(
     conn: &'stmt mut Connection<'a>,
-    key: RedisKey,
+    key: Vec<u8>,
 ) -> Result<AsyncIter<'stmt, V>, Error> {
-    Ok(AsyncIter::new(conn.sscan(key).await?))
+    Ok(AsyncIter::new::<C>(conn.sscan(key).await?))
 }
 
-pub async fn contains<'a, VM: ToBytes>(
+pub async fn contains<'a, C: Codec, VM: ToBytes>(
     conn: &mut Connection<'a>,
-    key: RedisKey,
+    key: Vec<u8>,
     value: VM,
 ) -> Result<bool, Error> {
-    Ok(conn.sismember(key, value.to_bytes()?).await?)
+    Ok(conn.sismember(key, value.to_bytes::<C>()?).await?)
 }
 
-pub async fn len<'a>(conn: &mut Connection<'a>, key: RedisKey) -> Result<usize, Error> {
+pub async fn len<'a>(conn: &mut Connection<'a>, key: Vec<u8>) -> Result<usize, Error> {
     Ok(conn.scard(key).await?)
 }
 
@@ -40,12 +40,13 @@ where
     S: CacheStrategy,
     V: ToBytes,
 {
-    pipe.0.sismember(key, value.to_bytes()?);
+    pipe.pipeline
+        .sismember(pipe.key(key), value.to_bytes::<S::Codec>()?);
     Ok(())
 }
 
 pub fn len_with_pipe<S: CacheStrategy>(pipe: &mut Pipe<S>, key: RedisKey) {
-    pipe.0.scard(key);
+    pipe.pipeline.scard(pipe.key(key));
 }
 
 /// Implement a getter for a set of values.
@@ -54,7 +55,7 @@ macro_rules! impl_set_wrapper_methods {
         $set_name:ident,
         key: {
             RedisKey::$redis_key:ident: {
-                $key_name:ident: $key_id_type:ty
+                $($key_name:ident: $key_id_type:ty),+
             }
         },
         value: { $value_name:ident: $value_id_type:ty }
@@ -68,26 +69,26 @@ macro_rules! impl_set_wrapper_methods {
                 pub async fn [<scan_ $set_name>]<'a, 'stmt>(
                     &'a self,
                     conn: &'stmt mut Connection<'a>,
-                    $key_name: $key_id_type,
+                    $($key_name: $key_id_type,)+
                 ) -> Result<
                     AsyncIter<'stmt, $value_id_type>,
                     Error
                 > {
-                    scan(
+                    scan::<S::Codec, _>(
                         conn,
-                        RedisKey::$redis_key { $key_name }
+                        self.key(RedisKey::$redis_key { $($key_name),+ })
                     ).await
                 }
 
                 pub async fn [<$set_name _contains>](
                     &self,
                     conn: &mut Connection<'_>,
-                    $key_name: $key_id_type,
+                    $($key_name: $key_id_type,)+
                     $value_name: $value_id_type,
                 ) -> Result<bool, Error> {
-                    contains(
+                    contains::<S::Codec, _>(
                         conn,
-                        RedisKey::$redis_key { $key_name },
+                        self.key(RedisKey::$redis_key { $($key_name),+ }),
                         $value_name
                     ).await
                 }
@@ -95,11 +96,11 @@ macro_rules! impl_set_wrapper_methods {
                 pub async fn [<len_ $set_name>](
                     &self,
                     conn: &mut Connection<'_>,
-                    $key_name: $key_id_type,
+                    $($key_name: $key_id_type,)+
                 ) -> Result<usize, Error> {
                     len(
                         conn,
-                        RedisKey::$redis_key { $key_name }
+                        self.key(RedisKey::$redis_key { $($key_name),+ })
                     ).await
                 }
             }
@@ -107,12 +108,12 @@ macro_rules! impl_set_wrapper_methods {
             impl<S: CacheStrategy> $crate::cache::Pipe<S> {
                 pub fn [<$set_name _contains>](
                     &mut self,
-                    $key_name: $key_id_type,
+                    $($key_name: $key_id_type,)+
                     $value_name: $value_id_type,
                 ) -> Result<&mut Self, Error> {
                     contains_with_pipe(
                         self,
-                        RedisKey::$redis_key { $key_name },
+                        RedisKey::$redis_key { $($key_name),+ },
                         $value_name
                     )?;
                     Ok(self)
@@ -120,11 +121,11 @@ macro_rules! impl_set_wrapper_methods {
 
                 pub fn [<len_ $set_name>](
                     &mut self,
-                    $key_name: $key_id_type,
+                    $($key_name: $key_id_type,)+
                 ) -> &mut Self {
                     len_with_pipe(
                         self,
-                        RedisKey::$redis_key { $key_name }
+                        RedisKey::$redis_key { $($key_name),+ }
                     );
                     self
                 }
@@ -150,9 +151,9 @@ macro_rules! impl_global_set_wrapper_methods {
                     &'a mut self,
                     conn: &'stmt mut Connection<'a>,
                 ) -> Result<AsyncIter<'stmt, $value_id_marker>, Error> {
-                    scan(
+                    scan::<S::Codec, _>(
                         conn,
-                        RedisKey::$redis_key
+                        self.key(RedisKey::$redis_key)
                     ).await
                 }
 
@@ -161,9 +162,9 @@ macro_rules! impl_global_set_wrapper_methods {
                     conn: &mut Connection<'_>,
                     $value_name: $value_id_marker,
                 ) -> Result<bool, Error> {
-                    contains(
+                    contains::<S::Codec, _>(
                         conn,
-                        RedisKey::$redis_key,
+                        self.key(RedisKey::$redis_key),
                         $value_name
                     ).await
                 }
@@ -174,7 +175,7 @@ macro_rules! impl_global_set_wrapper_methods {
                 ) -> Result<usize, Error> {
                     len(
                         conn,
-                        RedisKey::$redis_key
+                        self.key(RedisKey::$redis_key)
                     ).await
                 }
             }
@@ -207,16 +208,44 @@ macro_rules! impl_global_set_wrapper_methods {
     };
 }
 
-pub async fn get<'a, T: FromCachedRedisValue>(
+pub async fn get<'a, C: Codec, T: FromCachedRedisValue>(
     conn: &mut Connection<'a>,
-    key: impl Into<RedisKey>,
+    key: Vec<u8>,
 ) -> Result<Option<T>, Error> {
-    let data: redis::Value = conn.get(key.into()).await?;
-    T::from_cached_redis_value(&data).map(Some)
+    let data: redis::Value = conn.get(key).await?;
+    Option::from_cached_redis_value::<C>(&data)
 }
 
 pub fn get_with_pipe<S: CacheStrategy>(pipe: &mut Pipe<S>, key: impl Into<RedisKey>) {
-    pipe.0.get(key.into());
+    pipe.pipeline.get(pipe.key(key));
+}
+
+pub async fn get_many<'a, C: Codec, T: FromCachedRedisValue>(
+    conn: &mut Connection<'a>,
+    keys: Vec<Vec<u8>>,
+) -> Result<Vec<Option<T>>, Error> {
+    if keys.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // `AsyncCommands::mget` sends `GET` for a single key, whose reply is not an array.
+    let data: redis::Value = redis::cmd("MGET").arg(keys).query_async(conn).await?;
+    Vec::from_cached_redis_value::<C>(&data)
+}
+
+pub fn get_many_with_pipe<S: CacheStrategy>(
+    pipe: &mut Pipe<S>,
+    keys: impl Iterator<Item = RedisKey>,
+) {
+    let keys: Vec<Vec<u8>> = keys.map(|key| pipe.key(key)).collect();
+
+    if keys.is_empty() {
+        // `MGET` needs a key, but the pipe still needs a reply in place of it
+        // so that the replies of the next commands are not shifted.
+        pipe.pipeline.cmd("EVAL").arg("return {}").arg(0);
+    } else {
+        pipe.pipeline.cmd("MGET").arg(keys);
+    }
 }
 
 macro_rules! impl_str_wrapper_methods {
@@ -236,7 +265,20 @@ macro_rules! impl_str_wrapper_methods {
                     conn: &mut Connection<'_>,
                     $key_name: $key_id_marker,
                 ) -> Result<Option<$value_name>, Error> {
-                    get(conn, $key_name).await
+                    get::<S::Codec, _>(conn, self.key($key_name)).await
+                }
+
+                /// Get the values of many ids with a single `MGET`, in the
+                /// order of the ids.
+                pub async fn [<get_ $get_name s>](
+                    &self,
+                    conn: &mut Connection<'_>,
+                    [<$key_name s>]: &[$key_id_marker],
+                ) -> Result<Vec<Option<$value_name>>, Error> {
+                    get_many::<S::Codec, _>(
+                        conn,
+                        [<$key_name s>].iter().map(|id| self.key(*id)).collect()
+                    ).await
                 }
             }
 
@@ -248,6 +290,21 @@ macro_rules! impl_str_wrapper_methods {
                     get_with_pipe(self, $key_name);
                     self
                 }
+
+                /// Get the values of many ids with a single `MGET`.
+                ///
+                /// With no ids, a command replying an empty array is added in
+                /// place of `MGET`, so the pipe still gets one reply.
+                pub fn [<get_ $get_name s>](
+                    &mut self,
+                    [<$key_name s>]: &[$key_id_marker],
+                ) -> &mut Self {
+                    get_many_with_pipe(
+                        self,
+                        [<$key_name s>].iter().map(|id| RedisKey::from(*id))
+                    );
+                    self
+                }
             }
         } }
     };
@@ -276,12 +333,32 @@ macro_rules! impl_str_wrapper_methods_with_two_id {
                     $id_name: $id_type,
                     $id2_name: $id2_type
                 ) -> Result<Option<$value_type>, Error> {
-                    get(
+                    get::<S::Codec, _>(
                         conn,
-                        RedisKey::$redis_key {
+                        self.key(RedisKey::$redis_key {
                             $id_name,
                             $id2_name
-                        }
+                        })
+                    ).await
+                }
+
+                /// Get the values of many ids with a single `MGET`, in the
+                /// order of the ids.
+                pub async fn [<get_ $get_name s>](
+                    &self,
+                    conn: &mut Connection<'_>,
+                    $id_name: $id_type,
+                    [<$id2_name s>]: &[$id2_type]
+                ) -> Result<Vec<Option<$value_type>>, Error> {
+                    get_many::<S::Codec, _>(
+                        conn,
+                        [<$id2_name s>]
+                            .iter()
+                            .map(|$id2_name| self.key(RedisKey::$redis_key {
+                                $id_name,
+                                $id2_name: *$id2_name
+                            }))
+                            .collect()
                     ).await
                 }
             }
@@ -301,6 +378,27 @@ macro_rules! impl_str_wrapper_methods_with_two_id {
                     );
                     self
                 }
+
+                /// Get the values of many ids with a single `MGET`.
+                ///
+                /// With no ids, a command replying an empty array is added in
+                /// place of `MGET`, so the pipe still gets one reply.
+                pub fn [<get_ $get_name s>](
+                    &mut self,
+                    $id_name: $id_type,
+                    [<$id2_name s>]: &[$id2_type]
+                ) -> &mut Self {
+                    get_many_with_pipe(
+                        self,
+                        [<$id2_name s>]
+                            .iter()
+                            .map(|$id2_name| RedisKey::$redis_key {
+                                $id_name,
+                                $id2_name: *$id2_name
+                            })
+                    );
+                    self
+                }
             }
         } }
     };
@@ -310,3 +408,82 @@ pub(crate) use impl_global_set_wrapper_methods;
 pub(crate) use impl_set_wrapper_methods;
 pub(crate) use impl_str_wrapper_methods;
 pub(crate) use impl_str_wrapper_methods_with_two_id;
+
+#[cfg(test)]
+mod tests {
+    use twilight_model::{
+        channel::Channel,
+        gateway::payload::incoming::{ChannelCreate, MemberAdd},
+        id::Id,
+    };
+
+    use crate::{model::CachedMember, test};
+
+    #[test]
+    fn test_get_many() {
+        test::block_on(async {
+            let mut cache = test::redis_cache().await;
+            let guild_id = Id::new(801);
+            let channel_ids = [Id::new(802), Id::new(803)];
+            let user_ids = [Id::new(804), Id::new(805)];
+
+            for channel_id in channel_ids {
+                cache
+                    .update(ChannelCreate(test::model::channel(
+                        channel_id,
+                        Some(guild_id),
+                    )))
+                    .await
+                    .unwrap();
+            }
+            for user_id in user_ids {
+                cache
+                    .update(MemberAdd {
+                        guild_id,
+                        member: test::model::member(user_id),
+                    })
+                    .await
+                    .unwrap();
+            }
+
+            let mut conn = cache.get_connection().await.unwrap();
+
+            let channels = cache
+                .get_channels(
+                    &mut conn,
+                    Some(guild_id),
+                    &[channel_ids[1], Id::new(806), channel_ids[0]],
+                )
+                .await
+                .unwrap();
+            let channels: Vec<_> = channels.iter().map(|c| c.as_ref().map(|c| c.id)).collect();
+            assert_eq!(channels, [Some(channel_ids[1]), None, Some(channel_ids[0])]);
+
+            let members = cache
+                .get_members(&mut conn, guild_id, &[user_ids[1]])
+                .await
+                .unwrap();
+            assert_eq!(members[0].as_ref().unwrap().user_id(), user_ids[1]);
+
+            assert!(cache.get_users(&mut conn, &[]).await.unwrap().is_empty());
+
+            let mut pipe = cache.pipe();
+            pipe.get_channels(Some(guild_id), &channel_ids)
+                .get_members(guild_id, &user_ids);
+            let (channels, members): (Vec<Option<Channel>>, Vec<Option<CachedMember>>) =
+                pipe.query(&mut conn).await.unwrap();
+
+            assert!(channels.iter().all(Option::is_some));
+            assert!(members.iter().all(Option::is_some));
+
+            let mut pipe = cache.pipe();
+            pipe.get_channels(Some(guild_id), &[])
+                .get_members(guild_id, &user_ids);
+            let (channels, members): (Vec<Option<Channel>>, Vec<Option<CachedMember>>) =
+                pipe.query(&mut conn).await.unwrap();
+
+            assert!(channels.is_empty());
+            assert_eq!(members.len(), user_ids.len());
+        });
+    }
+}
@@ -0,0 +1,55 @@
+-- Run the commands of a pipe only if every guarded key still holds the value
+-- it had when the pipe was built.
+--
+-- ARGV starts with the number of guarded keys, which come first in KEYS,
+-- followed by the expected value of each of them, an empty string standing
+-- for a missing key. The value of a set is its members joined with commas.
+--
+-- The commands follow in ARGV, each as a string with a letter per argument:
+-- `k` for a key, taken from the rest of KEYS in order, and `a` for any other
+-- argument, taken from the rest of ARGV.
+local guards = tonumber(ARGV[1])
+
+local function holds(key, expected)
+    if redis.call('TYPE', key).ok ~= 'set' then
+        return (redis.call('GET', key) or '') == expected
+    end
+
+    local count = 0
+    for member in string.gmatch(expected, '[^,]+') do
+        if redis.call('SISMEMBER', key, member) == 0 then
+            return false
+        end
+        count = count + 1
+    end
+
+    return redis.call('SCARD', key) == count
+end
+
+for i = 1, guards do
+    if not holds(KEYS[i], ARGV[i + 1]) then
+        return 0
+    end
+end
+
+local k = guards + 1
+local i = guards + 2
+while i <= #ARGV do
+    local kinds = ARGV[i]
+    local args = {}
+    i = i + 1
+
+    for j = 1, #kinds do
+        if string.sub(kinds, j, j) == 'k' then
+            args[j] = KEYS[k]
+            k = k + 1
+        else
+            args[j] = ARGV[i]
+            i = i + 1
+        end
+    end
+
+    redis.call(unpack(args))
+end
+
+return 1
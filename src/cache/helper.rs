@@ -1,30 +1,30 @@
-use std::{collections::HashSet, iter::Map, marker::PhantomData};
+use std::{
+    collections::HashSet,
+    iter::Map,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use futures_core::Stream;
 
 use redis::Value;
 use twilight_model::id::Id;
 
-use super::{FromCachedRedisValue, RedisKey, ToBytes};
-use crate::Error;
+use super::{FromCachedRedisValue, RedisKey};
+use crate::{codec::Codec, Error};
 
-pub(crate) fn serialize_with_keys<I, T>(
-    iter: impl Iterator<Item = (I, T)>,
-) -> Result<Vec<(RedisKey, Vec<u8>)>, Error>
-where
-    I: Into<RedisKey>,
-    T: ToBytes,
-{
-    iter.map(|(id, e)| Ok::<(RedisKey, Vec<u8>), Error>((id.into(), e.to_bytes()?)))
-        .collect()
-}
+/// Pairs of serialized keys and values, ready to be passed to `MSET`.
+pub(crate) type SerializedPairs = Vec<(Vec<u8>, Vec<u8>)>;
 
 // RedisKey Helper:
 pub trait MapRedisKey {
-    fn map_redis_key(self) -> Vec<RedisKey>;
+    fn map_redis_key(self, namespace: Option<&str>) -> Vec<Vec<u8>>;
 }
 
 impl<I: Into<RedisKey>, T: Iterator<Item = I>> MapRedisKey for T {
-    fn map_redis_key(self) -> Vec<RedisKey> {
-        self.map(|id| id.into()).collect()
+    fn map_redis_key(self, namespace: Option<&str>) -> Vec<Vec<u8>> {
+        self.map(|id| id.into().to_namespaced_bytes(namespace))
+            .collect()
     }
 }
 
@@ -63,22 +63,47 @@ where
 // AsyncIter Helper:
 pub struct AsyncIter<'a, V> {
     iter: redis::AsyncIter<'a, Value>,
-    _resource: PhantomData<V>,
+    decode: fn(&Value) -> Result<Option<V>, Error>,
 }
 
 impl<'a, V: FromCachedRedisValue> AsyncIter<'a, V> {
-    pub fn new(iter: redis::AsyncIter<'a, Value>) -> Self {
+    pub fn new<C: Codec>(iter: redis::AsyncIter<'a, Value>) -> Self {
         Self {
             iter,
-            _resource: PhantomData,
+            decode: Option::from_cached_redis_value::<C>,
         }
     }
 
+    /// Returns the next decoded value, skipping values that are missing, such
+    /// as keys that expired after being listed.
     pub async fn next_item(&mut self) -> Result<Option<V>, Error> {
-        if let Some(v) = self.iter.next_item().await {
-            Option::from_cached_redis_value(&v)
-        } else {
-            Ok(None)
+        while let Some(v) = self.iter.next_item().await {
+            if let Some(value) = (self.decode)(&v)? {
+                return Ok(Some(value));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// Yields the decoded values, like [`AsyncIter::next_item`].
+impl<'a, V: FromCachedRedisValue> Stream for AsyncIter<'a, V> {
+    type Item = Result<V, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            let Some(value) = ready!(Pin::new(&mut this.iter).poll_next(cx)) else {
+                return Poll::Ready(None);
+            };
+
+            match (this.decode)(&value) {
+                Ok(Some(value)) => return Poll::Ready(Some(Ok(value))),
+                Ok(None) => continue,
+                Err(error) => return Poll::Ready(Some(Err(error))),
+            }
         }
     }
 }
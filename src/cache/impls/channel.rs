@@ -5,6 +5,7 @@ use twilight_model::id::{
 
 use crate::{
     cache::{cmd, helper::*, RedisKey, ToBytes},
+    config::ResourceType,
     CacheStrategy, Connection, Error,
 };
 
@@ -19,9 +20,37 @@ cmd::impl_set_wrapper_methods!(
         channel_id: Id<ChannelMarker>
     }
 );
-cmd::impl_str_wrapper_methods!(
+cmd::impl_set_wrapper_methods!(
+    channel_threads,
+    key: {
+        RedisKey::ChannelThreads: {
+            guild_id: Id<GuildMarker>,
+            channel_id: Id<ChannelMarker>
+        }
+    },
+    value: {
+        thread_id: Id<ChannelMarker>
+    }
+);
+cmd::impl_set_wrapper_methods!(
+    guild_active_threads,
+    key: {
+        RedisKey::GuildActiveThreads: {
+            guild_id: Id<GuildMarker>
+        }
+    },
+    value: {
+        thread_id: Id<ChannelMarker>
+    }
+);
+cmd::impl_str_wrapper_methods_with_two_id!(
     channel,
-    key: { channel_id: Id<ChannelMarker> },
+    key: {
+        RedisKey::Channel: {
+            guild_id: Option<Id<GuildMarker>>,
+            channel_id: Id<ChannelMarker>
+        }
+    },
     value: S::Channel
 );
 
@@ -31,8 +60,10 @@ impl<S: CacheStrategy> crate::cache::Pipe<S> {
         guild_id: Id<GuildMarker>,
         channel_id: Id<ChannelMarker>,
     ) -> &mut Self {
-        self.0
-            .sadd(RedisKey::GuildChannels { guild_id }, channel_id.get());
+        self.pipeline.sadd(
+            self.key(RedisKey::GuildChannels { guild_id }),
+            channel_id.get(),
+        );
         self
     }
 
@@ -41,22 +72,101 @@ impl<S: CacheStrategy> crate::cache::Pipe<S> {
         guild_id: Id<GuildMarker>,
         channel_id: Id<ChannelMarker>,
     ) -> &mut Self {
-        self.0
-            .srem(RedisKey::GuildChannels { guild_id }, channel_id.get());
+        self.pipeline.srem(
+            self.key(RedisKey::GuildChannels { guild_id }),
+            channel_id.get(),
+        );
+        self
+    }
+
+    pub(crate) fn add_channel_thread(
+        &mut self,
+        guild_id: Id<GuildMarker>,
+        channel_id: Id<ChannelMarker>,
+        thread_id: Id<ChannelMarker>,
+    ) -> &mut Self {
+        self.pipeline.sadd(
+            self.key(RedisKey::ChannelThreads {
+                guild_id,
+                channel_id,
+            }),
+            thread_id.get(),
+        );
+        self
+    }
+
+    pub(crate) fn remove_channel_thread(
+        &mut self,
+        guild_id: Id<GuildMarker>,
+        channel_id: Id<ChannelMarker>,
+        thread_id: Id<ChannelMarker>,
+    ) -> &mut Self {
+        self.pipeline.srem(
+            self.key(RedisKey::ChannelThreads {
+                guild_id,
+                channel_id,
+            }),
+            thread_id.get(),
+        );
+        self
+    }
+
+    pub(crate) fn delete_channel_threads(
+        &mut self,
+        guild_id: Id<GuildMarker>,
+        channel_id: Id<ChannelMarker>,
+    ) -> &mut Self {
+        self.pipeline.del(self.key(RedisKey::ChannelThreads {
+            guild_id,
+            channel_id,
+        }));
+        self
+    }
+
+    pub(crate) fn add_guild_active_thread(
+        &mut self,
+        guild_id: Id<GuildMarker>,
+        thread_id: Id<ChannelMarker>,
+    ) -> &mut Self {
+        self.pipeline.sadd(
+            self.key(RedisKey::GuildActiveThreads { guild_id }),
+            thread_id.get(),
+        );
+        self
+    }
+
+    pub(crate) fn remove_guild_active_thread(
+        &mut self,
+        guild_id: Id<GuildMarker>,
+        thread_id: Id<ChannelMarker>,
+    ) -> &mut Self {
+        self.pipeline.srem(
+            self.key(RedisKey::GuildActiveThreads { guild_id }),
+            thread_id.get(),
+        );
         self
     }
 
     pub(crate) fn set_channel(
         &mut self,
-        id: Id<ChannelMarker>,
+        guild_id: Option<Id<GuildMarker>>,
+        channel_id: Id<ChannelMarker>,
         channel: &S::Channel,
     ) -> Result<&mut Self, Error> {
-        self.0.set(RedisKey::from(id), channel.to_bytes()?);
+        self.set_resource(
+            ResourceType::CHANNEL,
+            (guild_id, channel_id),
+            channel.to_bytes::<S::Codec>()?,
+        );
         Ok(self)
     }
 
-    pub(crate) fn delete_channel(&mut self, id: Id<ChannelMarker>) -> &mut Self {
-        self.0.del(RedisKey::from(id));
+    pub(crate) fn delete_channel(
+        &mut self,
+        guild_id: Option<Id<GuildMarker>>,
+        channel_id: Id<ChannelMarker>,
+    ) -> &mut Self {
+        self.delete_resource((guild_id, channel_id));
         self
     }
 }
@@ -4,7 +4,8 @@ use twilight_model::id::{
 };
 
 use crate::{
-    cache::{cmd, helper::*, Pipe, RedisKey, WithGuildId},
+    cache::{cmd, helper::*, CacheChange, Pipe, RedisKey, WithGuildId},
+    config::ResourceType,
     traits::CacheStrategy,
     Error,
 };
@@ -20,9 +21,14 @@ cmd::impl_set_wrapper_methods!(
         emoji_id: Id<EmojiMarker>
     }
 );
-cmd::impl_str_wrapper_methods!(
+cmd::impl_str_wrapper_methods_with_two_id!(
     emoji,
-    key: { emoji_id: Id<EmojiMarker> },
+    key: {
+        RedisKey::Emoji: {
+            guild_id: Id<GuildMarker>,
+            emoji_id: Id<EmojiMarker>
+        }
+    },
     value: WithGuildId<S::Emoji>
 );
 
@@ -32,8 +38,8 @@ impl<S: CacheStrategy> Pipe<S> {
         guild_id: Id<GuildMarker>,
         addition_emoji_ids: impl Iterator<Item = Id<EmojiMarker>>,
     ) -> &mut Self {
-        self.0.sadd(
-            RedisKey::GuildEmojis { guild_id },
+        self.pipeline.sadd(
+            self.key(RedisKey::GuildEmojis { guild_id }),
             addition_emoji_ids.collect_as_u64(),
         );
         self
@@ -44,8 +50,10 @@ impl<S: CacheStrategy> Pipe<S> {
         guild_id: Id<GuildMarker>,
         removal_emoji_id: Id<EmojiMarker>,
     ) -> &mut Self {
-        self.0
-            .srem(RedisKey::GuildEmojis { guild_id }, removal_emoji_id.get());
+        self.pipeline.srem(
+            self.key(RedisKey::GuildEmojis { guild_id }),
+            removal_emoji_id.get(),
+        );
         self
     }
 
@@ -54,8 +62,8 @@ impl<S: CacheStrategy> Pipe<S> {
         guild_id: Id<GuildMarker>,
         removal_emoji_ids: &[Id<EmojiMarker>],
     ) -> &mut Self {
-        self.0.srem(
-            RedisKey::GuildEmojis { guild_id },
+        self.pipeline.srem(
+            self.key(RedisKey::GuildEmojis { guild_id }),
             removal_emoji_ids.iter().copied().collect_as_u64(),
         );
         self
@@ -63,22 +71,61 @@ impl<S: CacheStrategy> Pipe<S> {
 
     pub(crate) fn set_emojis(
         &mut self,
+        guild_id: Id<GuildMarker>,
         emojis: impl Iterator<Item = (Id<EmojiMarker>, S::Emoji)>,
     ) -> Result<&mut Self, Error> {
-        self.0.mset(&serialize_with_keys(emojis)?);
+        let emojis = emojis
+            .map(|(emoji_id, emoji)| {
+                Ok((
+                    emoji_id,
+                    WithGuildId::to_bytes::<S::Codec>(guild_id, &emoji)?,
+                ))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        for (emoji_id, _) in &emojis {
+            self.notify(CacheChange::Set(RedisKey::from((guild_id, *emoji_id))));
+        }
+        let emojis: SerializedPairs = emojis
+            .into_iter()
+            .map(|(emoji_id, emoji)| (self.key((guild_id, emoji_id)), emoji))
+            .collect();
+
+        if let Some(ttl) = self.ttl(ResourceType::EMOJI) {
+            let ttl = (ttl.as_millis() as u64).max(1);
+            for (key, emoji) in emojis {
+                self.pipeline.pset_ex(key, emoji, ttl);
+            }
+        } else {
+            self.pipeline.mset(&emojis);
+        }
+
         Ok(self)
     }
 
-    pub(crate) fn delete_emoji(&mut self, emoji_id: Id<EmojiMarker>) -> &mut Self {
-        self.0.del(emoji_id.get());
+    pub(crate) fn delete_emoji(
+        &mut self,
+        guild_id: Id<GuildMarker>,
+        emoji_id: Id<EmojiMarker>,
+    ) -> &mut Self {
+        self.delete_resource((guild_id, emoji_id));
         self
     }
 
     pub(crate) fn delete_emojis(
         &mut self,
+        guild_id: Id<GuildMarker>,
         emoji_ids: impl Iterator<Item = Id<EmojiMarker>>,
     ) -> &mut Self {
-        self.0.del(emoji_ids.map_redis_key());
+        let keys: Vec<_> = emoji_ids
+            .map(|emoji_id| RedisKey::from((guild_id, emoji_id)))
+            .collect();
+        for key in &keys {
+            self.notify(CacheChange::Delete(*key));
+        }
+
+        self.pipeline
+            .del(keys.into_iter().map_redis_key(self.namespace()));
         self
     }
 }
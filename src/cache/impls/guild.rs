@@ -2,6 +2,7 @@ use twilight_model::id::{marker::GuildMarker, Id};
 
 use crate::{
     cache::{cmd, Pipe, RedisKey, ToBytes},
+    config::ResourceType,
     traits::CacheStrategy,
     Error,
 };
@@ -24,22 +25,26 @@ cmd::impl_str_wrapper_methods!(
 
 impl<S: CacheStrategy> Pipe<S> {
     pub(crate) fn add_unavailable_guild(&mut self, guild_id: Id<GuildMarker>) -> &mut Self {
-        self.0.sadd(RedisKey::UnavailableGuilds, guild_id.get());
+        self.pipeline
+            .sadd(self.key(RedisKey::UnavailableGuilds), guild_id.get());
         self
     }
 
     pub(crate) fn remove_unavailable_guild(&mut self, guild_id: Id<GuildMarker>) -> &mut Self {
-        self.0.srem(RedisKey::UnavailableGuilds, guild_id.get());
+        self.pipeline
+            .srem(self.key(RedisKey::UnavailableGuilds), guild_id.get());
         self
     }
 
     pub(crate) fn add_guild(&mut self, guild_id: Id<GuildMarker>) -> &mut Self {
-        self.0.sadd(RedisKey::Guilds, guild_id.get());
+        self.pipeline
+            .sadd(self.key(RedisKey::Guilds), guild_id.get());
         self
     }
 
     pub(crate) fn remove_guild(&mut self, guild_id: Id<GuildMarker>) -> &mut Self {
-        self.0.srem(RedisKey::Guilds, guild_id.get());
+        self.pipeline
+            .srem(self.key(RedisKey::Guilds), guild_id.get());
         self
     }
 
@@ -48,13 +53,13 @@ impl<S: CacheStrategy> Pipe<S> {
         guild_id: Id<GuildMarker>,
         guild: &S::Guild,
     ) -> Result<&mut Self, Error> {
-        self.0.set(RedisKey::from(guild_id), guild.to_bytes()?);
+        self.set_resource(ResourceType::GUILD, guild_id, guild.to_bytes::<S::Codec>()?);
 
         Ok(self)
     }
 
     pub(crate) fn delete_guild(&mut self, guild_id: Id<GuildMarker>) -> &mut Self {
-        self.0.del(RedisKey::from(guild_id));
+        self.delete_resource(guild_id);
 
         self
     }
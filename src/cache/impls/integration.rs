@@ -5,6 +5,7 @@ use twilight_model::id::{
 
 use crate::{
     cache::{cmd, Pipe, RedisKey, ToBytes},
+    config::ResourceType,
     traits::CacheStrategy,
     Connection, Error,
 };
@@ -37,8 +38,8 @@ impl<S: CacheStrategy> Pipe<S> {
         guild_id: Id<GuildMarker>,
         integration_id: Id<IntegrationMarker>,
     ) -> &mut Self {
-        self.0.sadd(
-            RedisKey::GuildIntegrations { guild_id },
+        self.pipeline.sadd(
+            self.key(RedisKey::GuildIntegrations { guild_id }),
             integration_id.get(),
         );
         self
@@ -49,8 +50,8 @@ impl<S: CacheStrategy> Pipe<S> {
         guild_id: Id<GuildMarker>,
         integration_id: Id<IntegrationMarker>,
     ) -> &mut Self {
-        self.0.srem(
-            RedisKey::GuildIntegrations { guild_id },
+        self.pipeline.srem(
+            self.key(RedisKey::GuildIntegrations { guild_id }),
             integration_id.get(),
         );
 
@@ -63,12 +64,13 @@ impl<S: CacheStrategy> Pipe<S> {
         integration_id: Id<IntegrationMarker>,
         integration: &S::GuildIntegration,
     ) -> Result<&mut Self, Error> {
-        self.0.set(
+        self.set_resource(
+            ResourceType::INTEGRATION,
             RedisKey::Integration {
                 guild_id,
                 integration_id,
             },
-            integration.to_bytes()?,
+            integration.to_bytes::<S::Codec>()?,
         );
 
         Ok(self)
@@ -79,7 +81,7 @@ impl<S: CacheStrategy> Pipe<S> {
         guild_id: Id<GuildMarker>,
         integration_id: Id<IntegrationMarker>,
     ) -> &mut Self {
-        self.0.del(RedisKey::Integration {
+        self.delete_resource(RedisKey::Integration {
             guild_id,
             integration_id,
         });
@@ -8,6 +8,7 @@ use twilight_model::id::{
 
 use crate::{
     cache::{cmd, FromCachedRedisValue, Pipe, RedisKey, ToBytes},
+    config::ResourceType,
     CacheStrategy, Connection, Error, RedisCache,
 };
 
@@ -17,7 +18,9 @@ impl<S: CacheStrategy> RedisCache<S> {
         conn: &mut Connection<'_>,
         channel_id: Id<ChannelMarker>,
     ) -> Result<usize, Error> {
-        Ok(conn.llen(RedisKey::ChannelMessages { channel_id }).await?)
+        Ok(conn
+            .llen(self.key(RedisKey::ChannelMessages { channel_id }))
+            .await?)
     }
 
     pub async fn index_channel_messages(
@@ -27,9 +30,9 @@ impl<S: CacheStrategy> RedisCache<S> {
         index: isize,
     ) -> Result<Option<S::Message>, Error> {
         let raw: redis::Value = conn
-            .lindex(RedisKey::ChannelMessages { channel_id }, index)
+            .lindex(self.key(RedisKey::ChannelMessages { channel_id }), index)
             .await?;
-        Option::from_cached_redis_value(&raw)
+        Option::from_cached_redis_value::<S::Codec>(&raw)
     }
 
     pub async fn range_channel_messages(
@@ -40,16 +43,21 @@ impl<S: CacheStrategy> RedisCache<S> {
         stop: isize,
     ) -> Result<VecDeque<S::Message>, Error> {
         let raw: redis::Value = conn
-            .lrange(RedisKey::ChannelMessages { channel_id }, start, stop)
+            .lrange(
+                self.key(RedisKey::ChannelMessages { channel_id }),
+                start,
+                stop,
+            )
             .await?;
 
-        VecDeque::from_cached_redis_value(&raw)
+        VecDeque::from_cached_redis_value::<S::Codec>(&raw)
     }
 }
 
 impl<S: CacheStrategy> Pipe<S> {
     pub fn len_channel_messages(&mut self, channel_id: Id<ChannelMarker>) -> &mut Self {
-        self.0.llen(RedisKey::ChannelMessages { channel_id });
+        self.pipeline
+            .llen(self.key(RedisKey::ChannelMessages { channel_id }));
         self
     }
 
@@ -58,12 +66,21 @@ impl<S: CacheStrategy> Pipe<S> {
         channel_id: Id<ChannelMarker>,
         index: isize,
     ) -> &mut Self {
-        self.0
-            .lindex(RedisKey::ChannelMessages { channel_id }, index);
+        self.pipeline
+            .lindex(self.key(RedisKey::ChannelMessages { channel_id }), index);
         self
     }
 }
 
+cmd::impl_set_wrapper_methods!(
+    channel_pins,
+    key: {
+        RedisKey::ChannelPins: {
+            channel_id: Id<ChannelMarker>
+        }
+    },
+    value: { message_id: Id<MessageMarker> }
+);
 cmd::impl_str_wrapper_methods!(
     message,
     key: { message_id: Id<MessageMarker> },
@@ -76,13 +93,16 @@ impl<S: CacheStrategy> Pipe<S> {
         channel_id: Id<ChannelMarker>,
         message_id: Id<MessageMarker>,
     ) -> &mut Self {
-        self.0
-            .rpush(RedisKey::ChannelMessages { channel_id }, message_id.get());
+        self.pipeline.rpush(
+            self.key(RedisKey::ChannelMessages { channel_id }),
+            message_id.get(),
+        );
         self
     }
 
     pub(crate) fn pop_channel_message(&mut self, channel_id: Id<ChannelMarker>) -> &mut Self {
-        self.0.lpop(RedisKey::ChannelMessages { channel_id }, None);
+        self.pipeline
+            .lpop(self.key(RedisKey::ChannelMessages { channel_id }), None);
         self
     }
 
@@ -91,26 +111,66 @@ impl<S: CacheStrategy> Pipe<S> {
         channel_id: Id<ChannelMarker>,
         message_id: Id<MessageMarker>,
     ) -> &mut Self {
-        self.0.lrem(
-            RedisKey::ChannelMessages { channel_id },
+        self.pipeline.lrem(
+            self.key(RedisKey::ChannelMessages { channel_id }),
             0,
             message_id.get(),
         );
         self
     }
 
+    pub(crate) fn delete_channel_messages(&mut self, channel_id: Id<ChannelMarker>) -> &mut Self {
+        self.pipeline
+            .del(self.key(RedisKey::ChannelMessages { channel_id }));
+        self
+    }
+
+    pub(crate) fn add_channel_pin(
+        &mut self,
+        channel_id: Id<ChannelMarker>,
+        message_id: Id<MessageMarker>,
+    ) -> &mut Self {
+        self.pipeline.sadd(
+            self.key(RedisKey::ChannelPins { channel_id }),
+            message_id.get(),
+        );
+        self
+    }
+
+    pub(crate) fn remove_channel_pin(
+        &mut self,
+        channel_id: Id<ChannelMarker>,
+        message_id: Id<MessageMarker>,
+    ) -> &mut Self {
+        self.pipeline.srem(
+            self.key(RedisKey::ChannelPins { channel_id }),
+            message_id.get(),
+        );
+        self
+    }
+
+    pub(crate) fn delete_channel_pins(&mut self, channel_id: Id<ChannelMarker>) -> &mut Self {
+        self.pipeline
+            .del(self.key(RedisKey::ChannelPins { channel_id }));
+        self
+    }
+
     pub(crate) fn set_message(
         &mut self,
         message_id: Id<MessageMarker>,
         message: &S::Message,
     ) -> Result<&mut Self, Error> {
-        self.0.set(RedisKey::from(message_id), message.to_bytes()?);
+        self.set_resource(
+            ResourceType::MESSAGE,
+            message_id,
+            message.to_bytes::<S::Codec>()?,
+        );
 
         Ok(self)
     }
 
     pub(crate) fn delete_message(&mut self, message_id: Id<MessageMarker>) -> &mut Self {
-        self.0.del(RedisKey::from(message_id));
+        self.delete_resource(message_id);
         self
     }
 }
@@ -1,8 +1,8 @@
 use redis::AsyncCommands;
 
-use crate::{CacheStrategy, Connection, Error, RedisCache};
+use crate::{CacheStrategy, Connection, Error, RedisCache, ResourceType};
 
-use super::{FromBytes, FromCachedRedisValue, Pipe, RedisKey, ToBytes};
+use super::{FromCachedRedisValue, Pipe, RedisKey, ToBytes};
 
 mod channel;
 mod emoji;
@@ -13,6 +13,7 @@ mod presence;
 mod role;
 mod stage_instance;
 mod sticker;
+mod thread_member;
 mod user;
 mod voice_state;
 
@@ -21,14 +22,48 @@ impl<S: CacheStrategy> RedisCache<S> {
         &self,
         conn: &mut Connection<'_>,
     ) -> Result<Option<S::CurrentUser>, Error> {
-        let raw: redis::Value = conn.get(RedisKey::CurrentUser).await?;
-        Option::from_cached_redis_value(&raw)
+        let raw: redis::Value = conn.get(self.key(RedisKey::CurrentUser)).await?;
+        Option::from_cached_redis_value::<S::Codec>(&raw)
+    }
+
+    /// Get a value to change it, making the commands of the pipe conditional
+    /// on the value not being changed by anyone else before they run.
+    pub(crate) async fn get_guarded<T: FromCachedRedisValue>(
+        &self,
+        pipe: &mut Pipe<S>,
+        key: impl Into<RedisKey>,
+    ) -> Result<Option<T>, Error> {
+        self.get_guarded_on(&mut self.get_connection().await?, pipe, key)
+            .await
+    }
+
+    /// Same as [`get_guarded`](Self::get_guarded), reading the value on a
+    /// connection the caller already holds.
+    pub(crate) async fn get_guarded_on<T: FromCachedRedisValue>(
+        &self,
+        conn: &mut Connection<'_>,
+        pipe: &mut Pipe<S>,
+        key: impl Into<RedisKey>,
+    ) -> Result<Option<T>, Error> {
+        let key = self.key(key);
+        let raw: redis::Value = conn.get(&key).await?;
+        let value = Option::from_cached_redis_value::<S::Codec>(&raw)?;
+
+        pipe.guard(
+            key,
+            match raw {
+                redis::Value::BulkString(bytes) => Some(bytes),
+                _ => None,
+            },
+        );
+
+        Ok(value)
     }
 }
 
 impl<S: CacheStrategy> Pipe<S> {
     pub fn get_current_user(&mut self) -> &mut Self {
-        self.0.get(RedisKey::CurrentUser);
+        self.pipeline.get(self.key(RedisKey::CurrentUser));
         self
     }
 
@@ -36,7 +71,11 @@ impl<S: CacheStrategy> Pipe<S> {
         &mut self,
         current_user: &S::CurrentUser,
     ) -> Result<&mut Self, Error> {
-        self.0.set(RedisKey::CurrentUser, current_user.to_bytes()?);
+        self.set_resource(
+            ResourceType::USER_CURRENT,
+            RedisKey::CurrentUser,
+            current_user.to_bytes::<S::Codec>()?,
+        );
         Ok(self)
     }
 }
@@ -5,6 +5,7 @@ use twilight_model::id::{
 
 use crate::{
     cache::{cmd, Pipe, RedisKey, ToBytes},
+    config::ResourceType,
     CacheStrategy, Error,
 };
 
@@ -34,8 +35,10 @@ impl<S: CacheStrategy> Pipe<S> {
         guild_id: Id<GuildMarker>,
         user_id: Id<UserMarker>,
     ) -> &mut Self {
-        self.0
-            .sadd(RedisKey::GuildPresences { guild_id }, user_id.get());
+        self.pipeline.sadd(
+            self.key(RedisKey::GuildPresences { guild_id }),
+            user_id.get(),
+        );
         self
     }
 
@@ -44,8 +47,10 @@ impl<S: CacheStrategy> Pipe<S> {
         guild_id: Id<GuildMarker>,
         user_id: Id<UserMarker>,
     ) -> &mut Self {
-        self.0
-            .srem(RedisKey::GuildPresences { guild_id }, user_id.get());
+        self.pipeline.srem(
+            self.key(RedisKey::GuildPresences { guild_id }),
+            user_id.get(),
+        );
         self
     }
 
@@ -55,9 +60,10 @@ impl<S: CacheStrategy> Pipe<S> {
         user_id: Id<UserMarker>,
         presence: &S::Presence,
     ) -> Result<&mut Self, Error> {
-        self.0.set(
+        self.set_resource(
+            ResourceType::PRESENCE,
             RedisKey::Presence { guild_id, user_id },
-            presence.to_bytes()?,
+            presence.to_bytes::<S::Codec>()?,
         );
         Ok(self)
     }
@@ -67,7 +73,7 @@ impl<S: CacheStrategy> Pipe<S> {
         guild_id: Id<GuildMarker>,
         user_id: Id<UserMarker>,
     ) -> &mut Self {
-        self.0.del(RedisKey::Presence { guild_id, user_id });
+        self.delete_resource(RedisKey::Presence { guild_id, user_id });
         self
     }
 }
@@ -5,6 +5,7 @@ use twilight_model::id::{
 
 use crate::{
     cache::{cmd, Pipe, RedisKey, WithGuildId},
+    config::ResourceType,
     CacheStrategy, Error,
 };
 
@@ -17,9 +18,14 @@ cmd::impl_set_wrapper_methods!(
     },
     value: { role_id: Id<RoleMarker> }
 );
-cmd::impl_str_wrapper_methods!(
-    guild_role_ids,
-    key: { guild_id: Id<GuildMarker> },
+cmd::impl_str_wrapper_methods_with_two_id!(
+    role,
+    key: {
+        RedisKey::Role: {
+            guild_id: Id<GuildMarker>,
+            role_id: Id<RoleMarker>
+        }
+    },
     value: WithGuildId<S::Role>
 );
 
@@ -29,8 +35,8 @@ impl<S: CacheStrategy> Pipe<S> {
         guild_id: Id<GuildMarker>,
         role_id: Id<RoleMarker>,
     ) -> &mut Self {
-        self.0
-            .sadd(RedisKey::GuildRoles { guild_id }, role_id.get());
+        self.pipeline
+            .sadd(self.key(RedisKey::GuildRoles { guild_id }), role_id.get());
         self
     }
 
@@ -39,8 +45,8 @@ impl<S: CacheStrategy> Pipe<S> {
         guild_id: Id<GuildMarker>,
         role_id: Id<RoleMarker>,
     ) -> &mut Self {
-        self.0
-            .srem(RedisKey::GuildRoles { guild_id }, role_id.get());
+        self.pipeline
+            .srem(self.key(RedisKey::GuildRoles { guild_id }), role_id.get());
         self
     }
 
@@ -50,15 +56,46 @@ impl<S: CacheStrategy> Pipe<S> {
         role_id: Id<RoleMarker>,
         role: &S::Role,
     ) -> Result<&mut Self, Error> {
-        self.0.set(
-            RedisKey::from(role_id),
-            WithGuildId::to_bytes(guild_id, role)?,
+        self.set_resource(
+            ResourceType::ROLE,
+            (guild_id, role_id),
+            WithGuildId::to_bytes::<S::Codec>(guild_id, role)?,
         );
         Ok(self)
     }
 
-    pub(crate) fn delete_role(&mut self, role_id: Id<RoleMarker>) -> &mut Self {
-        self.0.del(RedisKey::from(role_id));
+    /// Get every role of a guild in a single command, as a list of role ids
+    /// each followed by its role, nil if it is missing.
+    #[cfg(feature = "permission-calculator")]
+    pub(crate) fn get_guild_roles(&mut self, guild_id: Id<GuildMarker>) -> &mut Self {
+        let mut pattern = Vec::new();
+        if let Some(namespace) = self.namespace() {
+            pattern.extend_from_slice(namespace.as_bytes());
+            pattern.push(b':');
+        }
+        // The hash tag keeps the pattern in the slot of the set, as a Redis
+        // Cluster requires.
+        pattern.extend_from_slice(format!("ROLE:{{{guild_id}}}:*").as_bytes());
+        let key = self.key(RedisKey::GuildRoles { guild_id });
+
+        self.pipeline
+            .cmd("SORT")
+            .arg(key)
+            .arg("BY")
+            .arg("nosort")
+            .arg("GET")
+            .arg("#")
+            .arg("GET")
+            .arg(pattern);
+        self
+    }
+
+    pub(crate) fn delete_role(
+        &mut self,
+        guild_id: Id<GuildMarker>,
+        role_id: Id<RoleMarker>,
+    ) -> &mut Self {
+        self.delete_resource((guild_id, role_id));
         self
     }
 }
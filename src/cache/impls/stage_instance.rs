@@ -5,6 +5,7 @@ use twilight_model::id::{
 
 use crate::{
     cache::{cmd, Pipe, RedisKey, WithGuildId},
+    config::ResourceType,
     CacheStrategy, Connection, Error,
 };
 
@@ -17,9 +18,14 @@ cmd::impl_set_wrapper_methods!(
     },
     value: { stage_id: Id<StageMarker> }
 );
-cmd::impl_str_wrapper_methods!(
+cmd::impl_str_wrapper_methods_with_two_id!(
     stage_instance,
-    key: { stage_id: Id<StageMarker> },
+    key: {
+        RedisKey::StageInstance: {
+            guild_id: Id<GuildMarker>,
+            stage_id: Id<StageMarker>
+        }
+    },
     value: WithGuildId<S::StageInstance>
 );
 
@@ -29,8 +35,10 @@ impl<S: CacheStrategy> Pipe<S> {
         guild_id: Id<GuildMarker>,
         stage_id: Id<StageMarker>,
     ) -> &mut Self {
-        self.0
-            .sadd(RedisKey::GuildStageInstances { guild_id }, stage_id.get());
+        self.pipeline.sadd(
+            self.key(RedisKey::GuildStageInstances { guild_id }),
+            stage_id.get(),
+        );
         self
     }
 
@@ -39,8 +47,10 @@ impl<S: CacheStrategy> Pipe<S> {
         guild_id: Id<GuildMarker>,
         stage_id: Id<StageMarker>,
     ) -> &mut Self {
-        self.0
-            .srem(RedisKey::GuildStageInstances { guild_id }, stage_id.get());
+        self.pipeline.srem(
+            self.key(RedisKey::GuildStageInstances { guild_id }),
+            stage_id.get(),
+        );
         self
     }
 
@@ -50,15 +60,20 @@ impl<S: CacheStrategy> Pipe<S> {
         stage_id: Id<StageMarker>,
         stage_instance: &S::StageInstance,
     ) -> Result<&mut Self, Error> {
-        self.0.set(
-            RedisKey::from(stage_id),
-            WithGuildId::to_bytes(guild_id, stage_instance)?,
+        self.set_resource(
+            ResourceType::STAGE_INSTANCE,
+            (guild_id, stage_id),
+            WithGuildId::to_bytes::<S::Codec>(guild_id, stage_instance)?,
         );
         Ok(self)
     }
 
-    pub(crate) fn delete_stage_instance(&mut self, stage_id: Id<StageMarker>) -> &mut Self {
-        self.0.del(RedisKey::from(stage_id));
+    pub(crate) fn delete_stage_instance(
+        &mut self,
+        guild_id: Id<GuildMarker>,
+        stage_id: Id<StageMarker>,
+    ) -> &mut Self {
+        self.delete_resource((guild_id, stage_id));
         self
     }
 }
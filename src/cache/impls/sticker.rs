@@ -5,6 +5,7 @@ use twilight_model::id::{
 
 use crate::{
     cache::{cmd, Pipe, RedisKey, WithGuildId},
+    config::ResourceType,
     CacheStrategy, Error,
 };
 
@@ -17,9 +18,14 @@ cmd::impl_set_wrapper_methods!(
     },
     value: { sticker_id: Id<StickerMarker> }
 );
-cmd::impl_str_wrapper_methods!(
+cmd::impl_str_wrapper_methods_with_two_id!(
     sticker,
-    key: { sticker_id: Id<StickerMarker> },
+    key: {
+        RedisKey::Sticker: {
+            guild_id: Id<GuildMarker>,
+            sticker_id: Id<StickerMarker>
+        }
+    },
     value: WithGuildId<S::Sticker>
 );
 
@@ -29,8 +35,10 @@ impl<S: CacheStrategy> Pipe<S> {
         guild_id: Id<GuildMarker>,
         sticker_id: Id<StickerMarker>,
     ) -> &mut Self {
-        self.0
-            .sadd(RedisKey::GuildStickers { guild_id }, sticker_id.get());
+        self.pipeline.sadd(
+            self.key(RedisKey::GuildStickers { guild_id }),
+            sticker_id.get(),
+        );
         self
     }
 
@@ -39,8 +47,10 @@ impl<S: CacheStrategy> Pipe<S> {
         guild_id: Id<GuildMarker>,
         sticker_id: Id<StickerMarker>,
     ) -> &mut Self {
-        self.0
-            .srem(RedisKey::GuildStickers { guild_id }, sticker_id.get());
+        self.pipeline.srem(
+            self.key(RedisKey::GuildStickers { guild_id }),
+            sticker_id.get(),
+        );
         self
     }
 
@@ -50,15 +60,20 @@ impl<S: CacheStrategy> Pipe<S> {
         sticker_id: Id<StickerMarker>,
         sticker: &S::Sticker,
     ) -> Result<&mut Self, Error> {
-        self.0.set(
-            RedisKey::from(sticker_id),
-            WithGuildId::to_bytes(guild_id, sticker)?,
+        self.set_resource(
+            ResourceType::STICKER,
+            (guild_id, sticker_id),
+            WithGuildId::to_bytes::<S::Codec>(guild_id, sticker)?,
         );
         Ok(self)
     }
 
-    pub(crate) fn delete_sticker(&mut self, sticker_id: Id<StickerMarker>) -> &mut Self {
-        self.0.del(RedisKey::from(sticker_id));
+    pub(crate) fn delete_sticker(
+        &mut self,
+        guild_id: Id<GuildMarker>,
+        sticker_id: Id<StickerMarker>,
+    ) -> &mut Self {
+        self.delete_resource((guild_id, sticker_id));
         self
     }
 }
@@ -0,0 +1,88 @@
+use twilight_model::id::{
+    marker::{ChannelMarker, UserMarker},
+    Id,
+};
+
+use crate::{
+    cache::{cmd, Pipe, RedisKey, ToBytes},
+    config::ResourceType,
+    CacheStrategy, Error,
+};
+
+cmd::impl_set_wrapper_methods!(
+    thread_members,
+    key: {
+        RedisKey::ThreadMembers: {
+            thread_id: Id<ChannelMarker>
+        }
+    },
+    value: {
+        user_id: Id<UserMarker>
+    }
+);
+cmd::impl_str_wrapper_methods_with_two_id!(
+    thread_member,
+    key: {
+        RedisKey::ThreadMember: {
+            thread_id: Id<ChannelMarker>,
+            user_id: Id<UserMarker>
+        }
+    },
+    value: S::ThreadMember
+);
+
+impl<S: CacheStrategy> Pipe<S> {
+    pub(crate) fn add_thread_member(
+        &mut self,
+        thread_id: Id<ChannelMarker>,
+        user_id: Id<UserMarker>,
+    ) -> &mut Self {
+        self.pipeline.sadd(
+            self.key(RedisKey::ThreadMembers { thread_id }),
+            user_id.get(),
+        );
+        self
+    }
+
+    pub(crate) fn remove_thread_member(
+        &mut self,
+        thread_id: Id<ChannelMarker>,
+        user_id: Id<UserMarker>,
+    ) -> &mut Self {
+        self.pipeline.srem(
+            self.key(RedisKey::ThreadMembers { thread_id }),
+            user_id.get(),
+        );
+        self
+    }
+
+    pub(crate) fn delete_thread_members(&mut self, thread_id: Id<ChannelMarker>) -> &mut Self {
+        self.pipeline
+            .del(self.key(RedisKey::ThreadMembers { thread_id }));
+        self
+    }
+
+    pub(crate) fn set_thread_member(
+        &mut self,
+        thread_id: Id<ChannelMarker>,
+        user_id: Id<UserMarker>,
+        member: &S::ThreadMember,
+    ) -> Result<&mut Self, Error> {
+        self.set_resource(
+            ResourceType::THREAD_MEMBER,
+            RedisKey::ThreadMember { thread_id, user_id },
+            member.to_bytes::<S::Codec>()?,
+        );
+
+        Ok(self)
+    }
+
+    pub(crate) fn delete_thread_member(
+        &mut self,
+        thread_id: Id<ChannelMarker>,
+        user_id: Id<UserMarker>,
+    ) -> &mut Self {
+        self.delete_resource(RedisKey::ThreadMember { thread_id, user_id });
+        self
+    }
+}
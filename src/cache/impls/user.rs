@@ -5,6 +5,7 @@ use twilight_model::id::{
 
 use crate::{
     cache::{cmd, Pipe, RedisKey, ToBytes},
+    config::ResourceType,
     traits::CacheStrategy,
     Error,
 };
@@ -55,8 +56,8 @@ impl<S: CacheStrategy> Pipe<S> {
         user_id: Id<UserMarker>,
         guild_id: Id<GuildMarker>,
     ) -> &mut Self {
-        self.0
-            .sadd(RedisKey::UserGuilds { user_id }, guild_id.get());
+        self.pipeline
+            .sadd(self.key(RedisKey::UserGuilds { user_id }), guild_id.get());
         self
     }
 
@@ -65,21 +66,19 @@ impl<S: CacheStrategy> Pipe<S> {
         user_id: Id<UserMarker>,
         guild_id: Id<GuildMarker>,
     ) -> &mut Self {
-        self.0
-            .srem(RedisKey::UserGuilds { user_id }, guild_id.get());
+        self.pipeline
+            .srem(self.key(RedisKey::UserGuilds { user_id }), guild_id.get());
         self
     }
 
     pub(crate) fn add_user(&mut self, user_id: Id<UserMarker>) -> &mut Self {
-        self.0.sadd(RedisKey::Users, user_id.get());
+        self.pipeline.sadd(self.key(RedisKey::Users), user_id.get());
         self
     }
 
     pub(crate) fn remove_user(&mut self, user_id: Id<UserMarker>) -> &mut Self {
-        self.0
-            .srem(RedisKey::Users, user_id.get())
-            .del(RedisKey::from(user_id));
-        self
+        self.pipeline.srem(self.key(RedisKey::Users), user_id.get());
+        self.delete_resource(user_id)
     }
 
     pub(crate) fn set_user(
@@ -87,12 +86,12 @@ impl<S: CacheStrategy> Pipe<S> {
         user_id: Id<UserMarker>,
         user: &S::User,
     ) -> Result<&mut Self, Error> {
-        self.0.set(RedisKey::from(user_id), user.to_bytes()?);
+        self.set_resource(ResourceType::USER, user_id, user.to_bytes::<S::Codec>()?);
         Ok(self)
     }
 
     pub(crate) fn delete_user(&mut self, user_id: Id<UserMarker>) -> &mut Self {
-        self.0.del(RedisKey::from(user_id));
+        self.delete_resource(user_id);
         self
     }
 
@@ -101,8 +100,8 @@ impl<S: CacheStrategy> Pipe<S> {
         guild_id: Id<GuildMarker>,
         user_id: Id<UserMarker>,
     ) -> &mut Self {
-        self.0
-            .sadd(RedisKey::GuildMembers { guild_id }, user_id.get());
+        self.pipeline
+            .sadd(self.key(RedisKey::GuildMembers { guild_id }), user_id.get());
         self
     }
 
@@ -111,8 +110,8 @@ impl<S: CacheStrategy> Pipe<S> {
         guild_id: Id<GuildMarker>,
         user_id: Id<UserMarker>,
     ) -> &mut Self {
-        self.0
-            .srem(RedisKey::GuildMembers { guild_id }, user_id.get());
+        self.pipeline
+            .srem(self.key(RedisKey::GuildMembers { guild_id }), user_id.get());
         self
     }
 
@@ -122,8 +121,11 @@ impl<S: CacheStrategy> Pipe<S> {
         user_id: Id<UserMarker>,
         member: &S::Member,
     ) -> Result<&mut Self, Error> {
-        self.0
-            .set(RedisKey::from((guild_id, user_id)), member.to_bytes()?);
+        self.set_resource(
+            ResourceType::MEMBER,
+            (guild_id, user_id),
+            member.to_bytes::<S::Codec>()?,
+        );
         Ok(self)
     }
 
@@ -132,7 +134,7 @@ impl<S: CacheStrategy> Pipe<S> {
         guild_id: Id<GuildMarker>,
         user_id: Id<UserMarker>,
     ) -> &mut Self {
-        self.0.del(RedisKey::from((guild_id, user_id)));
+        self.delete_resource((guild_id, user_id));
         self
     }
 }
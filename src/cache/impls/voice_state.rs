@@ -5,6 +5,7 @@ use twilight_model::id::{
 
 use crate::{
     cache::{cmd, Pipe, RedisKey, ToBytes},
+    config::ResourceType,
     CacheStrategy, Error,
 };
 
@@ -43,9 +44,9 @@ impl<S: CacheStrategy> Pipe<S> {
         channel_id: Id<ChannelMarker>,
         user: &S::ChannelVoiceState,
     ) -> Result<&mut Self, Error> {
-        self.0.sadd(
-            RedisKey::ChannelVoiceStates { channel_id },
-            user.to_bytes()?,
+        self.pipeline.sadd(
+            self.key(RedisKey::ChannelVoiceStates { channel_id }),
+            user.to_bytes::<S::Codec>()?,
         );
 
         Ok(self)
@@ -56,21 +57,32 @@ impl<S: CacheStrategy> Pipe<S> {
         channel_id: Id<ChannelMarker>,
         user: &S::ChannelVoiceState,
     ) -> Result<&mut Self, Error> {
-        self.0.srem(
-            RedisKey::ChannelVoiceStates { channel_id },
-            user.to_bytes()?,
+        self.pipeline.srem(
+            self.key(RedisKey::ChannelVoiceStates { channel_id }),
+            user.to_bytes::<S::Codec>()?,
         );
 
         Ok(self)
     }
 
+    pub(crate) fn delete_channel_voice_states(
+        &mut self,
+        channel_id: Id<ChannelMarker>,
+    ) -> &mut Self {
+        self.pipeline
+            .del(self.key(RedisKey::ChannelVoiceStates { channel_id }));
+        self
+    }
+
     pub(crate) fn add_guild_voice_state(
         &mut self,
         guild_id: Id<GuildMarker>,
         user_id: Id<UserMarker>,
     ) -> &mut Self {
-        self.0
-            .sadd(RedisKey::GuildVoiceStates { guild_id }, user_id.get());
+        self.pipeline.sadd(
+            self.key(RedisKey::GuildVoiceStates { guild_id }),
+            user_id.get(),
+        );
 
         self
     }
@@ -80,8 +92,10 @@ impl<S: CacheStrategy> Pipe<S> {
         guild_id: Id<GuildMarker>,
         user_id: Id<UserMarker>,
     ) -> &mut Self {
-        self.0
-            .srem(RedisKey::GuildVoiceStates { guild_id }, user_id.get());
+        self.pipeline.srem(
+            self.key(RedisKey::GuildVoiceStates { guild_id }),
+            user_id.get(),
+        );
 
         self
     }
@@ -92,9 +106,10 @@ impl<S: CacheStrategy> Pipe<S> {
         user_id: Id<UserMarker>,
         voice_state: &S::VoiceState,
     ) -> Result<&mut Self, Error> {
-        self.0.set(
+        self.set_resource(
+            ResourceType::VOICE_STATE,
             RedisKey::VoiceState { guild_id, user_id },
-            voice_state.to_bytes()?,
+            voice_state.to_bytes::<S::Codec>()?,
         );
 
         Ok(self)
@@ -105,8 +120,7 @@ impl<S: CacheStrategy> Pipe<S> {
         guild_id: Id<GuildMarker>,
         user_id: Id<UserMarker>,
     ) -> &mut Self {
-        self.0.del(RedisKey::VoiceState { guild_id, user_id });
-
+        self.delete_resource(RedisKey::VoiceState { guild_id, user_id });
         self
     }
 }
@@ -1,3 +1,4 @@
+use atoi::FromRadix10Checked;
 use twilight_model::id::{
     marker::{
         self, ChannelMarker, EmojiMarker, GuildMarker, IntegrationMarker, MessageMarker,
@@ -6,17 +7,37 @@ use twilight_model::id::{
     Id,
 };
 
-#[derive(Debug, Clone, Copy)]
+/// A key of the cache.
+///
+/// Keys holding data of a guild wrap the guild id in a hash tag, for example
+/// `MEMBER:{<guild_id>}:<user_id>` and `GUILD_MEMBERS:{<guild_id>}`, so that
+/// they are stored in the same slot of a Redis Cluster. The members of a
+/// thread are tagged with the id of the thread instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum RedisKey {
     CurrentUser,
+    /// Channel, tagged with the id of its guild unless it is a private
+    /// channel.
     Channel {
-        id: Id<ChannelMarker>,
+        guild_id: Option<Id<GuildMarker>>,
+        channel_id: Id<ChannelMarker>,
     },
     GuildChannels {
         guild_id: Id<GuildMarker>,
     },
+    /// Set of the ids of the threads of a channel, whose guild id is a hash
+    /// tag like the threads themselves.
+    ChannelThreads {
+        guild_id: Id<GuildMarker>,
+        channel_id: Id<ChannelMarker>,
+    },
+    /// Set of the ids of the threads of a guild which are not archived.
+    GuildActiveThreads {
+        guild_id: Id<GuildMarker>,
+    },
     Emoji {
-        id: Id<EmojiMarker>,
+        guild_id: Id<GuildMarker>,
+        emoji_id: Id<EmojiMarker>,
     },
     GuildEmojis {
         guild_id: Id<GuildMarker>,
@@ -53,6 +74,10 @@ pub enum RedisKey {
     Message {
         id: Id<MessageMarker>,
     },
+    /// Set of the ids of the pinned messages of a channel.
+    ChannelPins {
+        channel_id: Id<ChannelMarker>,
+    },
     GuildPresences {
         guild_id: Id<GuildMarker>,
     },
@@ -64,19 +89,22 @@ pub enum RedisKey {
         guild_id: Id<GuildMarker>,
     },
     Role {
-        id: Id<RoleMarker>,
+        guild_id: Id<GuildMarker>,
+        role_id: Id<RoleMarker>,
     },
     GuildStageInstances {
         guild_id: Id<GuildMarker>,
     },
     StageInstance {
-        id: Id<StageMarker>,
+        guild_id: Id<GuildMarker>,
+        stage_id: Id<StageMarker>,
     },
     GuildStickers {
         guild_id: Id<GuildMarker>,
     },
     Sticker {
-        id: Id<StickerMarker>,
+        guild_id: Id<GuildMarker>,
+        sticker_id: Id<StickerMarker>,
     },
     ChannelVoiceStates {
         channel_id: Id<ChannelMarker>,
@@ -88,6 +116,27 @@ pub enum RedisKey {
         guild_id: Id<GuildMarker>,
         user_id: Id<UserMarker>,
     },
+    /// Set of the ids of the members of a thread, whose thread id is a hash tag.
+    ThreadMembers {
+        thread_id: Id<ChannelMarker>,
+    },
+    /// Member of a thread, whose thread id is a hash tag.
+    ThreadMember {
+        thread_id: Id<ChannelMarker>,
+        user_id: Id<UserMarker>,
+    },
+    /// Session of a shard, written on `Ready`.
+    ShardSession {
+        shard_id: u64,
+    },
+    /// Total number of shards, written on `Ready`.
+    ShardCount,
+    /// Hash mapping the id of each guild to the id of its shard.
+    GuildShards,
+    /// Stream of the events applied to the cache, see [`Config::event_log`].
+    ///
+    /// [`Config::event_log`]: crate::Config::event_log
+    EventLog,
 }
 
 macro_rules! impl_from_id {
@@ -103,14 +152,9 @@ macro_rules! impl_from_id {
 }
 
 impl_from_id!(
-    (Channel, ChannelMarker),
-    (Emoji, EmojiMarker),
     (Guild, GuildMarker),
     (User, UserMarker),
-    (Message, MessageMarker),
-    (Role, RoleMarker),
-    (StageInstance, StageMarker),
-    (Sticker, StickerMarker)
+    (Message, MessageMarker)
 );
 
 macro_rules! impl_from_two_id {
@@ -141,11 +185,38 @@ impl_from_two_id!(
         guild_id: GuildMarker,
         user_id: UserMarker
     }),
+    (Emoji, {
+        guild_id: GuildMarker,
+        emoji_id: EmojiMarker
+    }),
+    (Role, {
+        guild_id: GuildMarker,
+        role_id: RoleMarker
+    }),
+    (StageInstance, {
+        guild_id: GuildMarker,
+        stage_id: StageMarker
+    }),
+    (Sticker, {
+        guild_id: GuildMarker,
+        sticker_id: StickerMarker
+    }),
 );
 
+impl From<(Option<Id<GuildMarker>>, Id<ChannelMarker>)> for RedisKey {
+    fn from((guild_id, channel_id): (Option<Id<GuildMarker>>, Id<ChannelMarker>)) -> Self {
+        RedisKey::Channel {
+            guild_id,
+            channel_id,
+        }
+    }
+}
+
 enum KeyKind {
     Simple(&'static str),
     WithId((&'static str, u64)),
+    /// A key holding data of a guild, whose guild id is a hash tag.
+    WithGuildTag((&'static str, u64)),
     WithGuildId((&'static str, u64, u64)),
 }
 
@@ -167,6 +238,12 @@ impl<T> From<(&'static str, Id<GuildMarker>, Id<T>)> for KeyKind {
     }
 }
 
+impl KeyKind {
+    fn guild(name: &'static str, guild_id: Id<GuildMarker>) -> Self {
+        KeyKind::WithGuildTag((name, guild_id.get()))
+    }
+}
+
 impl From<KeyKind> for Vec<u8> {
     fn from(key: KeyKind) -> Vec<u8> {
         match key {
@@ -184,6 +261,20 @@ impl From<KeyKind> for Vec<u8> {
 
                 bytes
             }
+            KeyKind::WithGuildTag((base, guild_id)) => {
+                let base = base.as_bytes();
+                let mut buf = itoa::Buffer::new();
+                let guild_id = buf.format(guild_id).as_bytes();
+
+                let mut bytes = Vec::with_capacity(base.len() + 3 + guild_id.len());
+
+                bytes.extend_from_slice(base);
+                bytes.extend_from_slice(b":{");
+                bytes.extend_from_slice(guild_id);
+                bytes.push(b'}');
+
+                bytes
+            }
             KeyKind::WithGuildId((base, guild_id, id)) => {
                 let base = base.as_bytes();
                 let mut buf = itoa::Buffer::new();
@@ -191,12 +282,12 @@ impl From<KeyKind> for Vec<u8> {
                 let mut guild_buf = itoa::Buffer::new();
                 let id = guild_buf.format(id).as_bytes();
 
-                let mut bytes = Vec::with_capacity(base.len() + 1 + guild_id.len() + 1 + id.len());
+                let mut bytes = Vec::with_capacity(base.len() + 4 + guild_id.len() + id.len());
 
                 bytes.extend_from_slice(base);
-                bytes.push(b':');
+                bytes.extend_from_slice(b":{");
                 bytes.extend_from_slice(guild_id);
-                bytes.push(b':');
+                bytes.extend_from_slice(b"}:");
                 bytes.extend_from_slice(id);
 
                 bytes
@@ -205,46 +296,420 @@ impl From<KeyKind> for Vec<u8> {
     }
 }
 
-impl redis::ToRedisArgs for RedisKey {
-    fn write_redis_args<W>(&self, out: &mut W)
-    where
-        W: ?Sized + redis::RedisWrite,
-    {
-        let key: KeyKind = match self {
+impl RedisKey {
+    /// Returns whether the key holds a value of a resource, as opposed to an
+    /// index or other bookkeeping of the cache.
+    pub(crate) const fn is_value(&self) -> bool {
+        matches!(
+            self,
+            Self::CurrentUser
+                | Self::Channel { .. }
+                | Self::Emoji { .. }
+                | Self::Integration { .. }
+                | Self::User { .. }
+                | Self::Member { .. }
+                | Self::Guild { .. }
+                | Self::Message { .. }
+                | Self::Presence { .. }
+                | Self::Role { .. }
+                | Self::StageInstance { .. }
+                | Self::Sticker { .. }
+                | Self::VoiceState { .. }
+                | Self::ThreadMember { .. }
+                | Self::ShardSession { .. }
+        )
+    }
+
+    fn kind(&self) -> KeyKind {
+        match self {
             Self::CurrentUser => "CURRENT_USER".into(),
-            Self::Channel { id } => ("CHANNEL", *id).into(),
-            Self::GuildChannels { guild_id } => ("GUILD_CHANNELS", *guild_id).into(),
-            Self::Emoji { id } => ("EMOJI", *id).into(),
-            Self::GuildEmojis { guild_id } => ("GUILD_EMOJIS", *guild_id).into(),
+            Self::Channel {
+                guild_id: Some(guild_id),
+                channel_id,
+            } => ("CHANNEL", *guild_id, *channel_id).into(),
+            Self::Channel {
+                guild_id: None,
+                channel_id,
+            } => ("CHANNEL", *channel_id).into(),
+            Self::GuildChannels { guild_id } => KeyKind::guild("GUILD_CHANNELS", *guild_id),
+            Self::ChannelThreads {
+                guild_id,
+                channel_id,
+            } => ("CHANNEL_THREADS", *guild_id, *channel_id).into(),
+            Self::GuildActiveThreads { guild_id } => {
+                KeyKind::guild("GUILD_ACTIVE_THREADS", *guild_id)
+            }
+            Self::Emoji { guild_id, emoji_id } => ("EMOJI", *guild_id, *emoji_id).into(),
+            Self::GuildEmojis { guild_id } => KeyKind::guild("GUILD_EMOJIS", *guild_id),
             Self::Integration {
                 guild_id,
                 integration_id,
             } => ("INTEGRATION", *guild_id, *integration_id).into(),
-            Self::GuildIntegrations { guild_id } => ("GUILD_INTEGRATIONS", *guild_id).into(),
+            Self::GuildIntegrations { guild_id } => KeyKind::guild("GUILD_INTEGRATIONS", *guild_id),
             Self::User { id } => ("USER", *id).into(),
             Self::Users => "USERS".into(),
             Self::UserGuilds { user_id } => ("USER_GUILDS", *user_id).into(),
             Self::Member { guild_id, user_id } => ("MEMBER", *guild_id, *user_id).into(),
-            Self::GuildMembers { guild_id } => ("GUILD_MEMBERS", *guild_id).into(),
+            Self::GuildMembers { guild_id } => KeyKind::guild("GUILD_MEMBERS", *guild_id),
             Self::UnavailableGuilds => "UNAVAILABLE_GUILDS".into(),
-            Self::Guild { id } => ("GUILD", *id).into(),
+            Self::Guild { id } => KeyKind::guild("GUILD", *id),
             Self::Guilds => "GUILDS".into(),
             Self::ChannelMessages { channel_id } => ("CHANNEL_MESSAGES", *channel_id).into(),
             Self::Message { id } => ("MESSAGE", *id).into(),
-            Self::GuildPresences { guild_id } => ("GUILD_PRESENCES", *guild_id).into(),
+            Self::ChannelPins { channel_id } => ("CHANNEL_PINS", *channel_id).into(),
+            Self::GuildPresences { guild_id } => KeyKind::guild("GUILD_PRESENCES", *guild_id),
             Self::Presence { guild_id, user_id } => ("PRESENCE", *guild_id, *user_id).into(),
-            Self::GuildRoles { guild_id } => ("GUILD_ROLES", *guild_id).into(),
-            Self::Role { id } => ("ROLE", *id).into(),
-            Self::GuildStageInstances { guild_id } => ("GUILD_STAGE_INSTANCES", *guild_id).into(),
-            Self::StageInstance { id } => ("STAGE_INSTANCE", *id).into(),
-            Self::GuildStickers { guild_id } => ("GUILD_STICKERS", *guild_id).into(),
-            Self::Sticker { id } => ("STICKER", *id).into(),
+            Self::GuildRoles { guild_id } => KeyKind::guild("GUILD_ROLES", *guild_id),
+            Self::Role { guild_id, role_id } => ("ROLE", *guild_id, *role_id).into(),
+            Self::GuildStageInstances { guild_id } => {
+                KeyKind::guild("GUILD_STAGE_INSTANCES", *guild_id)
+            }
+            Self::StageInstance { guild_id, stage_id } => {
+                ("STAGE_INSTANCE", *guild_id, *stage_id).into()
+            }
+            Self::GuildStickers { guild_id } => KeyKind::guild("GUILD_STICKERS", *guild_id),
+            Self::Sticker {
+                guild_id,
+                sticker_id,
+            } => ("STICKER", *guild_id, *sticker_id).into(),
             Self::ChannelVoiceStates { channel_id } => ("CHANNEL_VOICE_STATES", *channel_id).into(),
-            Self::GuildVoiceStates { guild_id } => ("GUILD_VOICE_STATES", *guild_id).into(),
+            Self::GuildVoiceStates { guild_id } => KeyKind::guild("GUILD_VOICE_STATES", *guild_id),
             Self::VoiceState { guild_id, user_id } => ("VOICE_STATE", *guild_id, *user_id).into(),
+            Self::ThreadMembers { thread_id } => {
+                KeyKind::WithGuildTag(("THREAD_MEMBERS", thread_id.get()))
+            }
+            Self::ThreadMember { thread_id, user_id } => {
+                KeyKind::WithGuildId(("THREAD_MEMBER", thread_id.get(), user_id.get()))
+            }
+            Self::ShardSession { shard_id } => KeyKind::WithId(("SHARD_SESSION", *shard_id)),
+            Self::ShardCount => "SHARD_COUNT".into(),
+            Self::GuildShards => "GUILD_SHARDS".into(),
+            Self::EventLog => "EVENT_LOG".into(),
+        }
+    }
+
+    /// Serialize the key, putting the namespace in front of it if one is given.
+    ///
+    /// For example, [`RedisKey::Guild`] in the namespace `bot` becomes `bot:GUILD:<id>`.
+    pub fn to_namespaced_bytes(&self, namespace: Option<&str>) -> Vec<u8> {
+        let key: Vec<u8> = self.kind().into();
+
+        let Some(namespace) = namespace else {
+            return key;
         };
 
-        let bytes: Vec<u8> = key.into();
-        out.write_arg(&bytes);
+        let mut bytes = Vec::with_capacity(namespace.len() + 1 + key.len());
+
+        bytes.extend_from_slice(namespace.as_bytes());
+        bytes.push(b':');
+        bytes.extend_from_slice(&key);
+
+        bytes
+    }
+
+    /// Parse a key serialized without namespace, `None` if it is not a key of
+    /// the cache.
+    pub fn parse(key: &[u8]) -> Option<Self> {
+        fn id(bytes: &[u8]) -> Option<u64> {
+            match u64::from_radix_10_checked(bytes) {
+                (Some(id), used) if used == bytes.len() && id != 0 => Some(id),
+                _ => None,
+            }
+        }
+
+        // Shard ids start at 0, unlike the other ids.
+        if let Some(bytes) = key.strip_prefix(b"SHARD_SESSION:") {
+            return match u64::from_radix_10_checked(bytes) {
+                (Some(shard_id), used) if used == bytes.len() => {
+                    Some(Self::ShardSession { shard_id })
+                }
+                _ => None,
+            };
+        }
+
+        let (name, rest) = match key.iter().position(|byte| *byte == b':') {
+            Some(index) => (&key[..index], Some(&key[index + 1..])),
+            None => (key, None),
+        };
+
+        let (guild_id, id) = match rest {
+            None => (None, None),
+            Some([b'{', rest @ ..]) => {
+                let end = rest.iter().position(|byte| *byte == b'}')?;
+                let guild_id = id(&rest[..end])?;
+
+                match &rest[end + 1..] {
+                    [] => (Some(Id::new(guild_id)), None),
+                    [b':', rest @ ..] => (Some(Id::new(guild_id)), Some(id(rest)?)),
+                    _ => return None,
+                }
+            }
+            Some(rest) => (None, Some(id(rest)?)),
+        };
+
+        Some(match (name, guild_id, id) {
+            (b"CURRENT_USER", None, None) => Self::CurrentUser,
+            (b"CHANNEL", guild_id, Some(id)) => Self::Channel {
+                guild_id,
+                channel_id: Id::new(id),
+            },
+            (b"GUILD_CHANNELS", Some(guild_id), None) => Self::GuildChannels { guild_id },
+            (b"CHANNEL_THREADS", Some(guild_id), Some(id)) => Self::ChannelThreads {
+                guild_id,
+                channel_id: Id::new(id),
+            },
+            (b"GUILD_ACTIVE_THREADS", Some(guild_id), None) => {
+                Self::GuildActiveThreads { guild_id }
+            }
+            (b"EMOJI", Some(guild_id), Some(id)) => Self::Emoji {
+                guild_id,
+                emoji_id: Id::new(id),
+            },
+            (b"GUILD_EMOJIS", Some(guild_id), None) => Self::GuildEmojis { guild_id },
+            (b"INTEGRATION", Some(guild_id), Some(id)) => Self::Integration {
+                guild_id,
+                integration_id: Id::new(id),
+            },
+            (b"GUILD_INTEGRATIONS", Some(guild_id), None) => Self::GuildIntegrations { guild_id },
+            (b"USER", None, Some(id)) => Self::User { id: Id::new(id) },
+            (b"USERS", None, None) => Self::Users,
+            (b"USER_GUILDS", None, Some(id)) => Self::UserGuilds {
+                user_id: Id::new(id),
+            },
+            (b"MEMBER", Some(guild_id), Some(id)) => Self::Member {
+                guild_id,
+                user_id: Id::new(id),
+            },
+            (b"GUILD_MEMBERS", Some(guild_id), None) => Self::GuildMembers { guild_id },
+            (b"UNAVAILABLE_GUILDS", None, None) => Self::UnavailableGuilds,
+            (b"GUILD", Some(id), None) => Self::Guild { id },
+            (b"GUILDS", None, None) => Self::Guilds,
+            (b"CHANNEL_MESSAGES", None, Some(id)) => Self::ChannelMessages {
+                channel_id: Id::new(id),
+            },
+            (b"MESSAGE", None, Some(id)) => Self::Message { id: Id::new(id) },
+            (b"CHANNEL_PINS", None, Some(id)) => Self::ChannelPins {
+                channel_id: Id::new(id),
+            },
+            (b"GUILD_PRESENCES", Some(guild_id), None) => Self::GuildPresences { guild_id },
+            (b"PRESENCE", Some(guild_id), Some(id)) => Self::Presence {
+                guild_id,
+                user_id: Id::new(id),
+            },
+            (b"GUILD_ROLES", Some(guild_id), None) => Self::GuildRoles { guild_id },
+            (b"ROLE", Some(guild_id), Some(id)) => Self::Role {
+                guild_id,
+                role_id: Id::new(id),
+            },
+            (b"GUILD_STAGE_INSTANCES", Some(guild_id), None) => {
+                Self::GuildStageInstances { guild_id }
+            }
+            (b"STAGE_INSTANCE", Some(guild_id), Some(id)) => Self::StageInstance {
+                guild_id,
+                stage_id: Id::new(id),
+            },
+            (b"GUILD_STICKERS", Some(guild_id), None) => Self::GuildStickers { guild_id },
+            (b"STICKER", Some(guild_id), Some(id)) => Self::Sticker {
+                guild_id,
+                sticker_id: Id::new(id),
+            },
+            (b"CHANNEL_VOICE_STATES", None, Some(id)) => Self::ChannelVoiceStates {
+                channel_id: Id::new(id),
+            },
+            (b"GUILD_VOICE_STATES", Some(guild_id), None) => Self::GuildVoiceStates { guild_id },
+            (b"VOICE_STATE", Some(guild_id), Some(id)) => Self::VoiceState {
+                guild_id,
+                user_id: Id::new(id),
+            },
+            (b"THREAD_MEMBERS", Some(thread_id), None) => Self::ThreadMembers {
+                thread_id: thread_id.cast(),
+            },
+            (b"THREAD_MEMBER", Some(thread_id), Some(id)) => Self::ThreadMember {
+                thread_id: thread_id.cast(),
+                user_id: Id::new(id),
+            },
+            (b"EVENT_LOG", None, None) => Self::EventLog,
+            (b"SHARD_COUNT", None, None) => Self::ShardCount,
+            (b"GUILD_SHARDS", None, None) => Self::GuildShards,
+            _ => return None,
+        })
+    }
+}
+
+impl redis::ToRedisArgs for RedisKey {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + redis::RedisWrite,
+    {
+        out.write_arg(&self.to_namespaced_bytes(None));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use twilight_model::{
+        gateway::payload::incoming::{ChannelCreate, ChannelDelete},
+        id::{marker::ChannelMarker, Id},
+    };
+
+    use super::RedisKey;
+    use crate::{test, ConfigBuilder};
+
+    #[test]
+    fn test_namespaced_bytes() {
+        let key = RedisKey::Member {
+            guild_id: Id::new(1),
+            user_id: Id::new(2),
+        };
+
+        assert_eq!(key.to_namespaced_bytes(None), b"MEMBER:{1}:2");
+        assert_eq!(key.to_namespaced_bytes(Some("bot")), b"bot:MEMBER:{1}:2");
+        assert_eq!(
+            RedisKey::GuildMembers {
+                guild_id: Id::new(1)
+            }
+            .to_namespaced_bytes(None),
+            b"GUILD_MEMBERS:{1}"
+        );
+        assert_eq!(
+            RedisKey::Role {
+                guild_id: Id::new(1),
+                role_id: Id::new(3)
+            }
+            .to_namespaced_bytes(None),
+            b"ROLE:{1}:3"
+        );
+        assert_eq!(
+            RedisKey::Channel {
+                guild_id: None,
+                channel_id: Id::new(4)
+            }
+            .to_namespaced_bytes(None),
+            b"CHANNEL:4"
+        );
+        assert_eq!(
+            RedisKey::ThreadMembers {
+                thread_id: Id::new(5)
+            }
+            .to_namespaced_bytes(None),
+            b"THREAD_MEMBERS:{5}"
+        );
+        assert_eq!(
+            RedisKey::Users.to_namespaced_bytes(Some("staging")),
+            b"staging:USERS"
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let guild_id = Id::new(1);
+        let keys = [
+            RedisKey::CurrentUser,
+            RedisKey::Channel {
+                guild_id: None,
+                channel_id: Id::new(2),
+            },
+            RedisKey::Channel {
+                guild_id: Some(guild_id),
+                channel_id: Id::new(2),
+            },
+            RedisKey::Guild { id: guild_id },
+            RedisKey::Role {
+                guild_id,
+                role_id: Id::new(5),
+            },
+            RedisKey::GuildMembers { guild_id },
+            RedisKey::Member {
+                guild_id,
+                user_id: Id::new(3),
+            },
+            RedisKey::ThreadMembers {
+                thread_id: Id::new(4),
+            },
+            RedisKey::ThreadMember {
+                thread_id: Id::new(4),
+                user_id: Id::new(3),
+            },
+            RedisKey::ShardSession { shard_id: 0 },
+        ];
+
+        for key in keys {
+            assert_eq!(RedisKey::parse(&key.to_namespaced_bytes(None)), Some(key));
+        }
+
+        assert_eq!(RedisKey::parse(b"CHANNEL:0"), None);
+        assert_eq!(RedisKey::parse(b"CHANNEL:{1}"), None);
+        assert_eq!(RedisKey::parse(b"MEMBER:{1}:2x"), None);
+        assert_eq!(RedisKey::parse(b"ROLE:2"), None);
+        assert_eq!(RedisKey::parse(b"bot:USERS"), None);
+    }
+
+    #[test]
+    fn test_namespace_isolation() {
+        test::block_on(async {
+            let mut production = test::redis_cache_with_config(
+                ConfigBuilder::default().namespace("production").build(),
+            )
+            .await;
+            let staging = test::redis_cache_with_config(
+                ConfigBuilder::default().namespace("staging").build(),
+            )
+            .await;
+
+            let guild_id = Id::new(201);
+            let channel_id: Id<ChannelMarker> = Id::new(202);
+
+            production
+                .update(ChannelCreate(test::model::channel(
+                    channel_id,
+                    Some(guild_id),
+                )))
+                .await
+                .unwrap();
+
+            {
+                let mut conn = production.get_connection().await.unwrap();
+                assert!(production
+                    .get_channel(&mut conn, Some(guild_id), channel_id)
+                    .await
+                    .unwrap()
+                    .is_some());
+                assert!(production
+                    .guild_channels_contains(&mut conn, guild_id, channel_id)
+                    .await
+                    .unwrap());
+            }
+
+            {
+                let mut conn = staging.get_connection().await.unwrap();
+                assert!(staging
+                    .get_channel(&mut conn, Some(guild_id), channel_id)
+                    .await
+                    .unwrap()
+                    .is_none());
+                assert!(!staging
+                    .guild_channels_contains(&mut conn, guild_id, channel_id)
+                    .await
+                    .unwrap());
+            }
+
+            {
+                let cache = test::redis_cache().await;
+                let mut conn = cache.get_connection().await.unwrap();
+                assert!(cache
+                    .get_channel(&mut conn, Some(guild_id), channel_id)
+                    .await
+                    .unwrap()
+                    .is_none());
+            }
+
+            production
+                .update(ChannelDelete(test::model::channel(
+                    channel_id,
+                    Some(guild_id),
+                )))
+                .await
+                .unwrap();
+        });
     }
 }
@@ -1,8 +1,16 @@
+mod change;
+mod clear;
 mod cmd;
 pub mod helper;
 mod impls;
 mod key;
+mod shard;
+mod snapshot;
+mod stats;
+mod stream;
+mod sweep;
 pub mod value;
+mod verify;
 
 use std::fmt::Debug;
 
@@ -11,48 +19,410 @@ use twilight_model::id::{marker::GuildMarker, Id};
 
 pub(crate) use self::pipe::Pipe;
 pub use self::{
+    change::{CacheChange, ChangeSubscriber},
     key::RedisKey,
+    shard::{guild_shard_id, ShardSession},
+    stats::{CacheStats, GuildStats},
     value::{FromBytes, FromCachedRedisValue, ToBytes},
+    verify::VerifyReport,
 };
-use crate::Error;
+use crate::{codec::Codec, Error};
 
 pub mod pipe {
-    use std::marker::PhantomData;
+    use std::{
+        marker::PhantomData,
+        sync::{Arc, OnceLock},
+        time::Duration,
+    };
 
-    use redis::{Pipeline, Value};
+    use redis::{Arg, Cmd, Pipeline, Script, Value};
 
-    use crate::{CacheStrategy, Error};
+    use crate::{config::ResourceType, CacheStrategy, Config, Connection, Error};
 
-    use super::FromCachedRedisValue;
+    use super::{CacheChange, FromCachedRedisValue, RedisKey};
 
-    pub struct Pipe<S: CacheStrategy>(pub Pipeline, PhantomData<S>);
+    pub struct Pipe<S: CacheStrategy> {
+        pub pipeline: Pipeline,
+        config: Arc<Config>,
+        /// Keys read to build the pipe, along with the value read from each
+        /// of them, see [`Pipe::guard`].
+        guards: Vec<(Vec<u8>, Vec<u8>)>,
+        /// Changes published when the pipe runs, see [`Pipe::publish_changes`].
+        changes: Vec<CacheChange>,
+        _strategy: PhantomData<S>,
+    }
 
     impl<S: CacheStrategy> Pipe<S> {
+        /// Create a pipe with the default configuration, whose keys have no namespace.
+        ///
+        /// Use [`RedisCache::pipe`] to create a pipe for the configuration of a cache.
+        ///
+        /// [`RedisCache::pipe`]: crate::RedisCache::pipe
         pub fn new() -> Self {
             Self::default()
         }
 
+        pub(crate) fn with_config(config: Arc<Config>) -> Self {
+            Self {
+                pipeline: Pipeline::new(),
+                config,
+                guards: Vec::new(),
+                changes: Vec::new(),
+                _strategy: PhantomData,
+            }
+        }
+
+        pub(crate) fn namespace(&self) -> Option<&str> {
+            self.config.namespace()
+        }
+
+        pub(crate) fn key(&self, key: impl Into<RedisKey>) -> Vec<u8> {
+            key.into().to_namespaced_bytes(self.namespace())
+        }
+
+        pub(crate) fn ttl(&self, resource_type: ResourceType) -> Option<Duration> {
+            self.config.ttl(resource_type)
+        }
+
+        /// Set a value, expiring it after the TTL configured for the resource type.
+        pub(crate) fn set_resource(
+            &mut self,
+            resource_type: ResourceType,
+            key: impl Into<RedisKey>,
+            value: Vec<u8>,
+        ) -> &mut Self {
+            let key = key.into();
+            self.notify(CacheChange::Set(key));
+            let key = self.key(key);
+
+            match self.ttl(resource_type) {
+                // Redis rejects an expiry of 0, so shorter ones are rounded up.
+                Some(ttl) => self
+                    .pipeline
+                    .pset_ex(key, value, (ttl.as_millis() as u64).max(1)),
+                None => self.pipeline.set(key, value),
+            };
+
+            self
+        }
+
+        /// Delete a value.
+        pub(crate) fn delete_resource(&mut self, key: impl Into<RedisKey>) -> &mut Self {
+            let key = key.into();
+            self.notify(CacheChange::Delete(key));
+            self.pipeline.del(self.key(key));
+            self
+        }
+
+        /// Append an event to the event log, if it is enabled.
+        pub(crate) fn log_event(&mut self, kind: &str, payload: &[u8]) -> &mut Self {
+            let Some(max_len) = self.config.event_log() else {
+                return self;
+            };
+            let key = self.key(RedisKey::EventLog);
+
+            self.pipeline
+                .cmd("XADD")
+                .arg(key)
+                .arg("MAXLEN")
+                .arg("~")
+                .arg(max_len)
+                .arg("*")
+                .arg("type")
+                .arg(kind)
+                .arg("payload")
+                .arg(payload);
+            self
+        }
+
+        /// Record a change to publish, if a notification channel is configured.
+        pub(crate) fn notify(&mut self, change: CacheChange) -> &mut Self {
+            if self.config.notification_channel().is_some() {
+                self.changes.push(change);
+            }
+            self
+        }
+
+        /// Publish the changes recorded so far on the notification channel, as
+        /// the last command of the pipe.
+        pub(crate) fn publish_changes(&mut self) -> &mut Self {
+            let Some(channel) = self.config.notification_channel() else {
+                return self;
+            };
+            if self.changes.is_empty() {
+                return self;
+            }
+
+            let mut message = Vec::new();
+            for (index, change) in self.changes.iter().enumerate() {
+                if index != 0 {
+                    message.push(b'\n');
+                }
+                change.write(&mut message);
+            }
+
+            self.pipeline.publish(channel, message);
+            self
+        }
+
         pub fn is_empty(&self) -> bool {
-            self.0.cmd_iter().next().is_none()
+            self.pipeline.cmd_iter().next().is_none()
         }
 
         pub fn atomic(&mut self) -> &mut Self {
-            self.0.atomic();
+            self.pipeline.atomic();
             self
         }
 
-        pub async fn query<'a, T: FromCachedRedisValue>(
+        pub async fn query<T: FromCachedRedisValue>(
             &self,
             conn: &mut impl redis::aio::ConnectionLike,
         ) -> Result<T, Error> {
-            let value: Value = self.0.query_async(conn).await?;
-            T::from_cached_redis_value(&value)
+            let value: Value = self.pipeline.query_async(conn).await?;
+            T::from_cached_redis_value::<S::Codec>(&value)
         }
+
+        /// Make the commands of this pipe run only if the key still holds the
+        /// value read from it, `None` if it was missing.
+        pub(crate) fn guard(&mut self, key: Vec<u8>, value: Option<Vec<u8>>) -> &mut Self {
+            self.guards.push((key, value.unwrap_or_default()));
+            self
+        }
+
+        /// Make the commands of this pipe run only if the set still holds
+        /// exactly the members read from it, none if it was missing.
+        pub(crate) fn guard_set(&mut self, key: Vec<u8>, members: &[Vec<u8>]) -> &mut Self {
+            self.guards.push((key, members.join(&b","[..])));
+            self
+        }
+
+        /// Run the commands of this pipe, returning `false` without running
+        /// them if a guarded key was changed in the meantime.
+        ///
+        /// A guarded pipe runs as a single Lua script, so that the check and
+        /// the commands are atomic even when other clients write to the same
+        /// keys.
+        pub(crate) async fn commit(&self, conn: &mut Connection<'_>) -> Result<bool, Error> {
+            if self.guards.is_empty() {
+                let _: Value = self.pipeline.query_async(conn).await?;
+                return Ok(true);
+            }
+
+            #[cfg(feature = "cluster")]
+            if let Connection::ClusterConnection(_) = conn {
+                return self.commit_cluster(conn).await;
+            }
+
+            Self::invoke(conn, self.guards.iter(), self.pipeline.cmd_iter()).await
+        }
+
+        /// Commit on a Redis Cluster, where a script may only use keys of a
+        /// single slot.
+        ///
+        /// The guards are checked by one script per slot, together with the
+        /// commands of that slot, and the other commands run afterwards. The
+        /// update is then atomic per slot only, which is enough for a value
+        /// written in the slot of its own guard.
+        #[cfg(feature = "cluster")]
+        async fn commit_cluster(&self, conn: &mut Connection<'_>) -> Result<bool, Error> {
+            use redis::cluster_routing::get_slot;
+
+            let cmds: Vec<&Cmd> = self.pipeline.cmd_iter().collect();
+            let groups = crate::cluster::groups(cmds.iter().copied());
+            let guard_slots: Vec<u16> = self.guards.iter().map(|(key, _)| get_slot(key)).collect();
+            let guards = |slot: u16| {
+                self.guards
+                    .iter()
+                    .zip(&guard_slots)
+                    .filter_map(move |(guard, guard_slot)| (*guard_slot == slot).then_some(guard))
+            };
+
+            // Check the guards without any command first, so that nothing is
+            // written if they fail.
+            let mut lone_slots = guard_slots.clone();
+            lone_slots.sort_unstable();
+            lone_slots.dedup();
+            lone_slots.retain(|slot| {
+                groups
+                    .iter()
+                    .all(|(group_slot, _)| *group_slot != Some(*slot))
+            });
+            for slot in lone_slots {
+                if !Self::invoke(conn, guards(slot), std::iter::empty()).await? {
+                    return Ok(false);
+                }
+            }
+
+            let mut rest = Pipeline::new();
+            rest.atomic();
+            for (slot, indices) in groups {
+                match slot.filter(|slot| guard_slots.contains(slot)) {
+                    Some(slot) => {
+                        let cmds = indices.iter().map(|index| cmds[*index]);
+                        if !Self::invoke(conn, guards(slot), cmds).await? {
+                            return Ok(false);
+                        }
+                    }
+                    None => {
+                        for index in indices {
+                            rest.add_command(cmds[index].clone());
+                        }
+                    }
+                }
+            }
+
+            if rest.cmd_iter().next().is_some() {
+                let _: Value = rest.query_async(conn).await?;
+            }
+
+            Ok(true)
+        }
+
+        async fn invoke<'a>(
+            conn: &mut Connection<'_>,
+            guards: impl Iterator<Item = &'a (Vec<u8>, Vec<u8>)>,
+            cmds: impl Iterator<Item = &'a Cmd>,
+        ) -> Result<bool, Error> {
+            static SCRIPT: OnceLock<Script> = OnceLock::new();
+            let script = SCRIPT.get_or_init(|| Script::new(include_str!("commit.lua")));
+
+            let guards: Vec<_> = guards.collect();
+            let mut invocation = script.prepare_invoke();
+            invocation.arg(guards.len());
+            for (key, value) in guards {
+                invocation.key(key).arg(value);
+            }
+            for cmd in cmds {
+                let args: Vec<&[u8]> = cmd
+                    .args_iter()
+                    .filter_map(|arg| match arg {
+                        Arg::Simple(arg) => Some(arg),
+                        Arg::Cursor => None,
+                    })
+                    .collect();
+
+                for args in split_command(&args) {
+                    let kinds: String = args
+                        .iter()
+                        .map(|(_, key)| if *key { 'k' } else { 'a' })
+                        .collect();
+                    invocation.arg(kinds);
+
+                    for (arg, key) in args {
+                        if key {
+                            invocation.key(arg);
+                        } else {
+                            invocation.arg(arg);
+                        }
+                    }
+                }
+            }
+
+            Ok(invocation.invoke_async(conn).await?)
+        }
+    }
+
+    /// Most arguments of a command run by the commit script, which unpacks them
+    /// onto the limited stack of Lua.
+    const MAX_SCRIPT_ARGS: usize = 1000;
+
+    /// Tell which arguments of a command are keys, splitting the commands
+    /// taking many keys or members into several ones of up to
+    /// [`MAX_SCRIPT_ARGS`] arguments.
+    fn split_command<'a>(args: &[&'a [u8]]) -> Vec<Vec<(&'a [u8], bool)>> {
+        let Some((name, rest)) = args.split_first() else {
+            return Vec::new();
+        };
+        let upper = name.to_ascii_uppercase();
+
+        // The arguments in front of every part, and the size of the groups
+        // of arguments which may not be split.
+        let (head, group) = match upper.as_slice() {
+            b"DEL" | b"UNLINK" | b"EXISTS" => (0, 1),
+            b"MSET" => (0, 2),
+            b"SADD" | b"SREM" | b"HDEL" | b"LPUSH" | b"RPUSH" => (1, 1),
+            _ => {
+                let key = upper.as_slice() != b"PUBLISH";
+                let mut command = vec![(*name, false)];
+                command.extend(
+                    rest.iter()
+                        .enumerate()
+                        .map(|(index, arg)| (*arg, key && index == 0)),
+                );
+                return vec![command];
+            }
+        };
+
+        let (head, rest) = rest.split_at(head.min(rest.len()));
+        let chunk = (MAX_SCRIPT_ARGS - 1 - head.len()) / group * group;
+
+        rest.chunks(chunk)
+            .map(|chunk| {
+                let mut command = vec![(*name, false)];
+                // The head is the key of a set or list, whose members are not
+                // keys. Otherwise, every argument is a key for DEL, and every
+                // other one for MSET.
+                command.extend(head.iter().map(|arg| (*arg, true)));
+                command.extend(
+                    chunk
+                        .iter()
+                        .enumerate()
+                        .map(|(index, arg)| (*arg, head.is_empty() && index % group == 0)),
+                );
+                command
+            })
+            .collect()
     }
 
     impl<S: CacheStrategy> Default for Pipe<S> {
         fn default() -> Self {
-            Self(Pipeline::new(), PhantomData)
+            Self::with_config(Arc::default())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::{split_command, MAX_SCRIPT_ARGS};
+
+        #[test]
+        fn test_split_command() {
+            let kinds = |commands: Vec<Vec<(&[u8], bool)>>| -> Vec<Vec<bool>> {
+                commands
+                    .into_iter()
+                    .map(|command| command.into_iter().map(|(_, key)| key).collect())
+                    .collect()
+            };
+
+            assert_eq!(
+                kinds(split_command(&[b"PSETEX", b"key", b"1", b"value"])),
+                [[false, true, false, false]]
+            );
+            assert_eq!(
+                kinds(split_command(&[b"MSET", b"a", b"1", b"b", b"2"])),
+                [[false, true, false, true, false]]
+            );
+            assert_eq!(
+                kinds(split_command(&[b"PUBLISH", b"channel", b"message"])),
+                [[false, false, false]]
+            );
+
+            let members = vec![b"member".as_slice(); 2 * MAX_SCRIPT_ARGS];
+            let mut args = vec![b"SREM".as_slice(), b"key"];
+            args.extend(&members);
+            let commands = split_command(&args);
+            assert_eq!(commands.len(), 3);
+            for command in &commands {
+                assert!(command.len() <= MAX_SCRIPT_ARGS);
+                assert_eq!(command[1], (b"key".as_slice(), true));
+                assert!(command[2..].iter().all(|(_, key)| !key));
+            }
+            assert_eq!(
+                commands
+                    .iter()
+                    .map(|command| command.len() - 2)
+                    .sum::<usize>(),
+                members.len()
+            );
         }
     }
 }
@@ -68,11 +438,11 @@ impl<T: ToBytes + FromBytes> WithGuildId<T> {
     }
 
     /// Make serialized `WithGuildId`.
-    pub fn to_bytes(guild_id: Id<GuildMarker>, resource: &T) -> Result<Vec<u8>, Error> {
+    pub fn to_bytes<C: Codec>(guild_id: Id<GuildMarker>, resource: &T) -> Result<Vec<u8>, Error> {
         let mut bytes = Vec::new();
 
         bytes.extend_from_slice(&guild_id.get().to_be_bytes());
-        bytes.extend_from_slice(&resource.to_bytes()?);
+        bytes.extend_from_slice(&resource.to_bytes::<C>()?);
 
         Ok(bytes)
     }
@@ -97,12 +467,12 @@ impl<T: Debug> Debug for WithGuildId<T> {
 }
 
 impl<T: FromBytes> FromCachedRedisValue for WithGuildId<T> {
-    fn from_cached_redis_value(value: &redis::Value) -> Result<Self, Error> {
+    fn from_cached_redis_value<C: Codec>(value: &redis::Value) -> Result<Self, Error> {
         if let Value::BulkString(bytes) = value {
             let mut guild_id: [u8; size_of::<u64>()] = [0; size_of::<u64>()];
             guild_id.copy_from_slice(&bytes[..8]);
             let guild_id = Id::new(u64::from_be_bytes(guild_id));
-            let resource = T::from_bytes(&bytes[8..])?;
+            let resource = T::from_bytes::<C>(&bytes[8..])?;
 
             Ok(Self { guild_id, resource })
         } else {
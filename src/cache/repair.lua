@@ -0,0 +1,32 @@
+-- Delete the values which no index points at, checking their indexes again
+-- at once so that a value indexed meanwhile is kept.
+--
+-- KEYS holds each value followed by its indexes. ARGV holds, for each value,
+-- its number of indexes followed by its id in each of them. Returns the
+-- position of each deleted value, starting at 1.
+local deleted = {}
+local k = 1
+local a = 1
+local position = 1
+
+while a <= #ARGV do
+    local n = tonumber(ARGV[a])
+    local indexed = false
+
+    for j = 1, n do
+        if redis.call('SISMEMBER', KEYS[k + j], ARGV[a + j]) == 1 then
+            indexed = true
+        end
+    end
+
+    if not indexed then
+        redis.call('DEL', KEYS[k])
+        deleted[#deleted + 1] = position
+    end
+
+    k = k + n + 1
+    a = a + n + 1
+    position = position + 1
+end
+
+return deleted
@@ -0,0 +1,359 @@
+//! Bookkeeping of the shards, so that another process can find the shard
+//! responsible for a guild.
+
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use twilight_model::id::{marker::GuildMarker, Id};
+
+use super::{FromCachedRedisValue, Pipe, RedisKey, ToBytes};
+use crate::{
+    config::ResourceType,
+    event::guild::{uncache_guild_data, uncache_guild_users},
+    CacheStrategy, Connection, Error, RedisCache,
+};
+
+/// Number of guilds removed in a single pipe by [`RedisCache::clear_shard`].
+const BATCH_SIZE: usize = 100;
+
+/// Session of a shard, as received in its `Ready` event.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ShardSession {
+    /// Id of the shard, starting at 0.
+    pub shard_id: u64,
+    /// Total number of shards.
+    pub shard_total: u64,
+    /// Id of the session, used to resume it.
+    pub session_id: String,
+    /// URL of the gateway to resume the session on.
+    pub resume_gateway_url: String,
+    /// Version of the gateway.
+    pub version: u64,
+}
+
+crate::cache::value::impl_to_bytes_for_model!(ShardSession);
+crate::cache::value::impl_from_bytes_for_model!(ShardSession);
+
+/// Returns the id of the shard receiving the events of the guild, following
+/// the formula of Discord.
+///
+/// # Panics
+///
+/// Panics if `shard_total` is 0.
+pub const fn guild_shard_id(guild_id: Id<GuildMarker>, shard_total: u64) -> u64 {
+    (guild_id.get() >> 22) % shard_total
+}
+
+impl<S: CacheStrategy> RedisCache<S> {
+    pub async fn get_shard_session(
+        &self,
+        conn: &mut Connection<'_>,
+        shard_id: u64,
+    ) -> Result<Option<ShardSession>, Error> {
+        let raw: redis::Value = conn
+            .get(self.key(RedisKey::ShardSession { shard_id }))
+            .await?;
+        Option::from_cached_redis_value::<S::Codec>(&raw)
+    }
+
+    /// Get the total number of shards, as received in the last `Ready` event.
+    pub async fn get_shard_count(&self, conn: &mut Connection<'_>) -> Result<Option<u64>, Error> {
+        Ok(conn.get(self.key(RedisKey::ShardCount)).await?)
+    }
+
+    /// Get the id of the shard responsible for the guild.
+    ///
+    /// Guilds are mapped to their shard by the `Ready` event of the shard, and
+    /// by their `GuildCreate` event once the number of shards is known.
+    pub async fn get_guild_shard(
+        &self,
+        conn: &mut Connection<'_>,
+        guild_id: Id<GuildMarker>,
+    ) -> Result<Option<u64>, Error> {
+        Ok(conn
+            .hget(self.key(RedisKey::GuildShards), guild_id.get())
+            .await?)
+    }
+
+    /// Remove every cached guild of a shard, along with its data, for example
+    /// after the session of the shard was invalidated.
+    ///
+    /// The guilds of the shard are found in the guild to shard mapping, or
+    /// among the available and unavailable guilds with [`guild_shard_id`] if
+    /// shards are not cached. They are removed in pipes of up to 100 guilds on
+    /// a single connection, each retried like [`update`] if a user of its
+    /// guilds changed in the meantime. Returns the number of guilds removed,
+    /// [`Error::InvalidShard`] if the shard is not one of `shard_total` shards,
+    /// or [`Error::Conflict`] if a pipe ran out of attempts.
+    ///
+    /// [`update`]: Self::update
+    pub async fn clear_shard(&mut self, shard_id: u64, shard_total: u64) -> Result<usize, Error> {
+        if shard_id >= shard_total {
+            return Err(Error::InvalidShard {
+                shard_id,
+                shard_total,
+            });
+        }
+
+        let mut conn = self.get_connection().await?;
+        let guild_ids = self
+            .shard_guild_ids(&mut conn, shard_id, shard_total)
+            .await?;
+
+        for guild_ids in guild_ids.chunks(BATCH_SIZE) {
+            self.clear_guilds(&mut conn, guild_ids).await?;
+        }
+
+        Ok(guild_ids.len())
+    }
+
+    /// Find the cached guilds of a shard.
+    async fn shard_guild_ids(
+        &self,
+        conn: &mut Connection<'_>,
+        shard_id: u64,
+        shard_total: u64,
+    ) -> Result<Vec<Id<GuildMarker>>, Error> {
+        let mut guild_ids = Vec::new();
+
+        if self.wants(ResourceType::SHARD) {
+            let mut iter = conn
+                .hscan::<_, (u64, u64)>(self.key(RedisKey::GuildShards))
+                .await?;
+            while let Some((guild_id, guild_shard_id)) = iter.next_item().await {
+                if guild_shard_id == shard_id {
+                    guild_ids.extend(Id::new_checked(guild_id));
+                }
+            }
+        } else {
+            guild_ids = self.scan_ids(conn, RedisKey::Guilds).await?;
+            guild_ids.extend(
+                self.scan_ids::<GuildMarker>(conn, RedisKey::UnavailableGuilds)
+                    .await?,
+            );
+            guild_ids.retain(|guild_id| guild_shard_id(*guild_id, shard_total) == shard_id);
+        }
+
+        guild_ids.sort_unstable();
+        guild_ids.dedup();
+
+        Ok(guild_ids)
+    }
+
+    /// Remove guilds along with their data in a single pipe.
+    ///
+    /// The users of the guilds are handled once for all of them, as the pipe
+    /// does not see its own removals: a user of two of the guilds is removed
+    /// rather than left without guilds.
+    async fn clear_guilds(
+        &self,
+        conn: &mut Connection<'_>,
+        guild_ids: &[Id<GuildMarker>],
+    ) -> Result<(), Error> {
+        for attempt in 0..self.config.max_update_attempts().get() {
+            if attempt > 0 {
+                tokio::time::sleep(crate::conflict_delay(attempt)).await;
+            }
+
+            let mut pipe = self.pipe();
+            if self.config.atomic() {
+                pipe.atomic();
+            }
+
+            let mut user_ids = Vec::new();
+            for guild_id in guild_ids {
+                user_ids.extend(uncache_guild_data(self, conn, &mut pipe, *guild_id, false).await?);
+                if self.wants(ResourceType::GUILD) {
+                    pipe.remove_unavailable_guild(*guild_id);
+                }
+            }
+            if self.wants(ResourceType::USER) {
+                uncache_guild_users(self, conn, &mut pipe, guild_ids, user_ids).await?;
+            }
+            pipe.publish_changes();
+
+            if pipe.is_empty() || pipe.commit(conn).await? {
+                return Ok(());
+            }
+        }
+
+        Err(Error::Conflict)
+    }
+
+    /// Map a guild to its shard, computed from the number of shards.
+    pub(crate) async fn cache_guild_shard(
+        &self,
+        pipe: &mut Pipe<S>,
+        guild_id: Id<GuildMarker>,
+    ) -> Result<(), Error> {
+        let shard_total = self
+            .get_shard_count(&mut self.get_connection().await?)
+            .await?;
+
+        if let Some(shard_total) = shard_total.filter(|shard_total| *shard_total != 0) {
+            pipe.set_guild_shard(guild_id, guild_shard_id(guild_id, shard_total));
+        }
+
+        Ok(())
+    }
+}
+
+impl<S: CacheStrategy> Pipe<S> {
+    pub(crate) fn set_shard_session(&mut self, session: &ShardSession) -> Result<&mut Self, Error> {
+        self.set_resource(
+            ResourceType::SHARD,
+            RedisKey::ShardSession {
+                shard_id: session.shard_id,
+            },
+            session.to_bytes::<S::Codec>()?,
+        );
+        self.pipeline
+            .set(self.key(RedisKey::ShardCount), session.shard_total);
+
+        Ok(self)
+    }
+
+    pub(crate) fn set_guild_shard(
+        &mut self,
+        guild_id: Id<GuildMarker>,
+        shard_id: u64,
+    ) -> &mut Self {
+        self.pipeline
+            .hset(self.key(RedisKey::GuildShards), guild_id.get(), shard_id);
+        self
+    }
+
+    pub(crate) fn remove_guild_shard(&mut self, guild_id: Id<GuildMarker>) -> &mut Self {
+        self.pipeline
+            .hdel(self.key(RedisKey::GuildShards), guild_id.get());
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use twilight_model::{
+        gateway::{
+            payload::incoming::{GuildCreate, Ready},
+            ShardId,
+        },
+        guild::UnavailableGuild,
+        id::Id,
+        oauth::{ApplicationFlags, PartialApplication},
+    };
+
+    use super::guild_shard_id;
+    use crate::{test, ConfigBuilder, Error};
+
+    #[test]
+    fn test_guild_shard_id() {
+        assert_eq!(guild_shard_id(Id::new(41_771_983_423_143_937), 1), 0);
+        assert_eq!(guild_shard_id(Id::new(41_771_983_423_143_937), 16), 6);
+        assert_eq!(guild_shard_id(Id::new(3 << 22), 2), 1);
+    }
+
+    #[test]
+    fn test_clear_shard() {
+        test::block_on(async {
+            let mut cache = test::redis_cache_with_config(
+                ConfigBuilder::default().namespace("clear_shard").build(),
+            )
+            .await;
+            let kept_guild_id = Id::new(2 << 22);
+            let cleared_guild_id = Id::new(3 << 22);
+            let other_cleared_guild_id = Id::new(5 << 22);
+            let user_id = Id::new(971);
+            let cleared_user_id = Id::new(972);
+
+            for (shard, guild_ids) in [
+                (0, vec![kept_guild_id]),
+                (1, vec![cleared_guild_id, other_cleared_guild_id]),
+            ] {
+                cache
+                    .update(Ready {
+                        user: test::model::current_user(),
+                        application: PartialApplication {
+                            flags: ApplicationFlags::empty(),
+                            id: Id::new(1),
+                        },
+                        guilds: guild_ids
+                            .into_iter()
+                            .map(|id| UnavailableGuild {
+                                id,
+                                unavailable: true,
+                            })
+                            .collect(),
+                        resume_gateway_url: String::new(),
+                        session_id: String::new(),
+                        shard: Some(ShardId::new(shard, 2)),
+                        version: 1,
+                    })
+                    .await
+                    .unwrap();
+            }
+
+            for guild_id in [kept_guild_id, cleared_guild_id, other_cleared_guild_id] {
+                let mut guild = test::model::guild(guild_id);
+                guild.members = vec![test::model::member(user_id)];
+                if guild_id != kept_guild_id {
+                    guild.members.push(test::model::member(cleared_user_id));
+                }
+                cache.update(GuildCreate(guild)).await.unwrap();
+            }
+
+            assert!(matches!(
+                cache.clear_shard(0, 0).await,
+                Err(Error::InvalidShard { .. })
+            ));
+            assert!(matches!(
+                cache.clear_shard(2, 2).await,
+                Err(Error::InvalidShard { .. })
+            ));
+            assert_eq!(cache.clear_shard(1, 2).await.unwrap(), 2);
+
+            let mut conn = cache.get_connection().await.unwrap();
+            let (kept, cleared): (bool, bool) = cache
+                .pipe()
+                .guilds_contains(kept_guild_id)
+                .unwrap()
+                .guilds_contains(cleared_guild_id)
+                .unwrap()
+                .query(&mut conn)
+                .await
+                .unwrap();
+            assert!(kept);
+            assert!(!cleared);
+            assert!(cache
+                .get_member(&mut conn, kept_guild_id, user_id)
+                .await
+                .unwrap()
+                .is_some());
+            assert!(cache
+                .get_member(&mut conn, cleared_guild_id, user_id)
+                .await
+                .unwrap()
+                .is_none());
+
+            // The guilds of a user are counted once for the whole batch.
+            let (kept_user, cleared_user, cleared_user_guilds): (bool, bool, usize) = cache
+                .pipe()
+                .users_contains(user_id)
+                .unwrap()
+                .users_contains(cleared_user_id)
+                .unwrap()
+                .len_user_guilds(cleared_user_id)
+                .query(&mut conn)
+                .await
+                .unwrap();
+            assert!(kept_user);
+            assert!(!cleared_user);
+            assert_eq!(cleared_user_guilds, 0);
+            assert_eq!(
+                cache
+                    .get_guild_shard(&mut conn, cleared_guild_id)
+                    .await
+                    .unwrap(),
+                None
+            );
+        });
+    }
+}
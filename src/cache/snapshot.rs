@@ -0,0 +1,648 @@
+//! Export of the cache to newline-delimited JSON and import back.
+//!
+//! Every line of a snapshot is a record tagged with the name of its
+//! [`ResourceType`], along with the ids needed to index it again, for example:
+//!
+//! ```json
+//! {"type":"MEMBER","guild_id":"1","id":"2","data":{...}}
+//! ```
+//!
+//! The data is written as JSON whatever the codec of the cache, so a snapshot
+//! can be imported into a cache using another codec.
+//!
+//! [`ResourceType`]: crate::ResourceType
+
+use std::{
+    collections::HashSet,
+    io::{BufRead, Write},
+};
+
+use serde::{Deserialize, Serialize};
+use twilight_model::id::{
+    marker::{
+        ChannelMarker, EmojiMarker, GuildMarker, IntegrationMarker, MessageMarker, RoleMarker,
+        StageMarker, StickerMarker, UserMarker,
+    },
+    Id,
+};
+
+use super::RedisKey;
+use crate::{
+    config::ResourceType,
+    event::{thread::index_thread, voice_state::set_voice_state_cache},
+    traits::{CacheableChannel, CacheableMessage, CacheableVoiceState},
+    CacheStrategy, Connection, Error, RedisCache,
+};
+
+/// Number of values read, or records written, in a single round trip.
+const BATCH_SIZE: usize = 100;
+
+#[derive(Deserialize, Serialize)]
+#[serde(
+    tag = "type",
+    rename_all = "SCREAMING_SNAKE_CASE",
+    bound(serialize = "", deserialize = "")
+)]
+enum Record<S: CacheStrategy> {
+    #[serde(rename = "USER_CURRENT")]
+    CurrentUser { data: S::CurrentUser },
+    /// A guild, whose data is missing if it is only known to be unavailable.
+    Guild {
+        id: Id<GuildMarker>,
+        unavailable: bool,
+        data: Option<S::Guild>,
+    },
+    Channel {
+        guild_id: Id<GuildMarker>,
+        id: Id<ChannelMarker>,
+        data: S::Channel,
+    },
+    Emoji {
+        guild_id: Id<GuildMarker>,
+        id: Id<EmojiMarker>,
+        data: S::Emoji,
+    },
+    Integration {
+        guild_id: Id<GuildMarker>,
+        id: Id<IntegrationMarker>,
+        data: S::GuildIntegration,
+    },
+    Member {
+        guild_id: Id<GuildMarker>,
+        id: Id<UserMarker>,
+        data: S::Member,
+    },
+    Presence {
+        guild_id: Id<GuildMarker>,
+        id: Id<UserMarker>,
+        data: S::Presence,
+    },
+    Role {
+        guild_id: Id<GuildMarker>,
+        id: Id<RoleMarker>,
+        data: S::Role,
+    },
+    StageInstance {
+        guild_id: Id<GuildMarker>,
+        id: Id<StageMarker>,
+        data: S::StageInstance,
+    },
+    Sticker {
+        guild_id: Id<GuildMarker>,
+        id: Id<StickerMarker>,
+        data: S::Sticker,
+    },
+    VoiceState {
+        guild_id: Id<GuildMarker>,
+        id: Id<UserMarker>,
+        data: S::VoiceState,
+    },
+    ThreadMember {
+        thread_id: Id<ChannelMarker>,
+        id: Id<UserMarker>,
+        data: S::ThreadMember,
+    },
+    /// A message, written in the order of the messages of its channel.
+    Message {
+        channel_id: Id<ChannelMarker>,
+        id: Id<MessageMarker>,
+        data: S::Message,
+    },
+    User {
+        id: Id<UserMarker>,
+        guild_ids: Vec<Id<GuildMarker>>,
+        data: S::User,
+    },
+}
+
+impl<S: CacheStrategy> Record<S> {
+    fn resource_type(&self) -> ResourceType {
+        match self {
+            Self::CurrentUser { .. } => ResourceType::USER_CURRENT,
+            Self::Guild { .. } => ResourceType::GUILD,
+            Self::Channel { .. } => ResourceType::CHANNEL,
+            Self::Emoji { .. } => ResourceType::EMOJI,
+            Self::Integration { .. } => ResourceType::INTEGRATION,
+            Self::Member { .. } => ResourceType::MEMBER,
+            Self::Presence { .. } => ResourceType::PRESENCE,
+            Self::Role { .. } => ResourceType::ROLE,
+            Self::StageInstance { .. } => ResourceType::STAGE_INSTANCE,
+            Self::Sticker { .. } => ResourceType::STICKER,
+            Self::VoiceState { .. } => ResourceType::VOICE_STATE,
+            Self::ThreadMember { .. } => ResourceType::THREAD_MEMBER,
+            Self::Message { .. } => ResourceType::MESSAGE,
+            Self::User { .. } => ResourceType::USER,
+        }
+    }
+
+    fn write(&self, out: &mut impl Write) -> Result<(), Error> {
+        serde_json::to_writer(&mut *out, self)?;
+        out.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+/// Write a record for each value of a set of a guild, fetching the values in
+/// batches and skipping ids whose value is missing.
+macro_rules! export_guild_set {
+    (
+        $cache:ident, $conn:ident, $out:ident, $count:ident, $guild_id:ident,
+        RedisKey::$set:ident => Record::$variant:ident,
+        |$ids:ident| $get:expr,
+        |$value:ident| $data:expr $(,)?
+    ) => {
+        let ids = $cache
+            .scan_ids(
+                $conn,
+                RedisKey::$set {
+                    guild_id: $guild_id,
+                },
+            )
+            .await?;
+
+        for $ids in ids.chunks(BATCH_SIZE) {
+            let values = $get.await?;
+
+            for (id, $value) in $ids.iter().zip(values) {
+                if let Some($value) = $value {
+                    Record::<S>::$variant {
+                        guild_id: $guild_id,
+                        id: *id,
+                        data: $data,
+                    }
+                    .write($out)?;
+                    $count += 1;
+                }
+            }
+        }
+    };
+}
+
+impl<S: CacheStrategy> RedisCache<S> {
+    /// Write every value of the cache to `out` as newline-delimited JSON,
+    /// returning the number of records written.
+    ///
+    /// Values are found through the index sets, so channels outside of guilds
+    /// are not exported, except for their messages.
+    pub async fn export(&self, mut out: impl Write) -> Result<usize, Error> {
+        let out = &mut out;
+        let conn = &mut self.get_read_connection().await?;
+        let mut count = 0;
+
+        if let Some(data) = self.get_current_user(conn).await? {
+            Record::<S>::CurrentUser { data }.write(out)?;
+            count += 1;
+        }
+
+        let mut guild_ids: Vec<Id<GuildMarker>> = self.scan_ids(conn, RedisKey::Guilds).await?;
+        let unavailable_guild_ids: HashSet<Id<GuildMarker>> = self
+            .scan_ids(conn, RedisKey::UnavailableGuilds)
+            .await?
+            .into_iter()
+            .collect();
+        let available_guild_ids: HashSet<Id<GuildMarker>> = guild_ids.iter().copied().collect();
+        guild_ids.extend(
+            unavailable_guild_ids
+                .iter()
+                .filter(|guild_id| !available_guild_ids.contains(guild_id)),
+        );
+
+        for ids in guild_ids.chunks(BATCH_SIZE) {
+            let guilds = self.get_guilds(conn, ids).await?;
+
+            for (id, data) in ids.iter().zip(guilds) {
+                Record::<S>::Guild {
+                    id: *id,
+                    unavailable: unavailable_guild_ids.contains(id),
+                    data,
+                }
+                .write(out)?;
+                count += 1;
+            }
+        }
+
+        for guild_id in guild_ids {
+            export_guild_set!(
+                self, conn, out, count, guild_id,
+                RedisKey::GuildChannels => Record::Channel,
+                |ids| self.get_channels(conn, Some(guild_id), ids),
+                |channel| channel,
+            );
+            export_guild_set!(
+                self, conn, out, count, guild_id,
+                RedisKey::GuildEmojis => Record::Emoji,
+                |ids| self.get_emojis(conn, guild_id, ids),
+                |emoji| emoji.resource,
+            );
+            export_guild_set!(
+                self, conn, out, count, guild_id,
+                RedisKey::GuildIntegrations => Record::Integration,
+                |ids| self.get_guild_integrations(conn, guild_id, ids),
+                |integration| integration,
+            );
+            export_guild_set!(
+                self, conn, out, count, guild_id,
+                RedisKey::GuildMembers => Record::Member,
+                |ids| self.get_members(conn, guild_id, ids),
+                |member| member,
+            );
+            export_guild_set!(
+                self, conn, out, count, guild_id,
+                RedisKey::GuildPresences => Record::Presence,
+                |ids| self.get_presences(conn, guild_id, ids),
+                |presence| presence,
+            );
+            export_guild_set!(
+                self, conn, out, count, guild_id,
+                RedisKey::GuildRoles => Record::Role,
+                |ids| self.get_roles(conn, guild_id, ids),
+                |role| role.resource,
+            );
+            export_guild_set!(
+                self, conn, out, count, guild_id,
+                RedisKey::GuildStageInstances => Record::StageInstance,
+                |ids| self.get_stage_instances(conn, guild_id, ids),
+                |stage_instance| stage_instance.resource,
+            );
+            export_guild_set!(
+                self, conn, out, count, guild_id,
+                RedisKey::GuildStickers => Record::Sticker,
+                |ids| self.get_stickers(conn, guild_id, ids),
+                |sticker| sticker.resource,
+            );
+            export_guild_set!(
+                self, conn, out, count, guild_id,
+                RedisKey::GuildVoiceStates => Record::VoiceState,
+                |ids| self.get_voice_states(conn, guild_id, ids),
+                |voice_state| voice_state,
+            );
+        }
+
+        count += self.export_thread_members(conn, out).await?;
+        count += self.export_messages(conn, out).await?;
+        count += self.export_users(conn, out).await?;
+
+        Ok(count)
+    }
+
+    async fn export_thread_members(
+        &self,
+        conn: &mut Connection<'_>,
+        out: &mut impl Write,
+    ) -> Result<usize, Error> {
+        let mut count = 0;
+
+        for key in self.scan_keys(conn, "THREAD_MEMBERS").await? {
+            let Some(RedisKey::ThreadMembers { thread_id }) = self.parse_key(&key) else {
+                continue;
+            };
+            let user_ids: Vec<Id<UserMarker>> = self
+                .scan_ids(conn, RedisKey::ThreadMembers { thread_id })
+                .await?;
+
+            for ids in user_ids.chunks(BATCH_SIZE) {
+                let members = self.get_thread_members(conn, thread_id, ids).await?;
+
+                for (id, data) in ids.iter().zip(members) {
+                    if let Some(data) = data {
+                        Record::<S>::ThreadMember {
+                            thread_id,
+                            id: *id,
+                            data,
+                        }
+                        .write(out)?;
+                        count += 1;
+                    }
+                }
+            }
+        }
+
+        Ok(count)
+    }
+
+    async fn export_messages(
+        &self,
+        conn: &mut Connection<'_>,
+        out: &mut impl Write,
+    ) -> Result<usize, Error> {
+        let mut count = 0;
+
+        for key in self.scan_keys(conn, "CHANNEL_MESSAGES").await? {
+            let Some(RedisKey::ChannelMessages { channel_id }) = self.parse_key(&key) else {
+                continue;
+            };
+            let ids: Vec<Id<MessageMarker>> =
+                self.range_channel_message_ids(conn, channel_id).await?;
+
+            for ids in ids.chunks(BATCH_SIZE) {
+                let messages = self.get_messages(conn, ids).await?;
+
+                for (id, data) in ids.iter().zip(messages) {
+                    if let Some(data) = data {
+                        Record::<S>::Message {
+                            channel_id,
+                            id: *id,
+                            data,
+                        }
+                        .write(out)?;
+                        count += 1;
+                    }
+                }
+            }
+        }
+
+        Ok(count)
+    }
+
+    async fn export_users(
+        &self,
+        conn: &mut Connection<'_>,
+        out: &mut impl Write,
+    ) -> Result<usize, Error> {
+        let user_ids: Vec<Id<UserMarker>> = self.scan_ids(conn, RedisKey::Users).await?;
+        let mut count = 0;
+
+        for ids in user_ids.chunks(BATCH_SIZE) {
+            let users = self.get_users(conn, ids).await?;
+
+            let mut pipe = self.pipe();
+            for user_id in ids {
+                pipe.pipeline
+                    .smembers(pipe.key(RedisKey::UserGuilds { user_id: *user_id }));
+            }
+            let guild_ids: Vec<Vec<u64>> = pipe.pipeline.query_async(conn).await?;
+
+            for ((id, data), guild_ids) in ids.iter().zip(users).zip(guild_ids) {
+                if let Some(data) = data {
+                    Record::<S>::User {
+                        id: *id,
+                        guild_ids: guild_ids.into_iter().map(Id::new).collect(),
+                        data,
+                    }
+                    .write(out)?;
+                    count += 1;
+                }
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Read the ids of the messages of a channel, oldest first.
+    async fn range_channel_message_ids(
+        &self,
+        conn: &mut Connection<'_>,
+        channel_id: Id<ChannelMarker>,
+    ) -> Result<Vec<Id<MessageMarker>>, Error> {
+        let ids: Vec<u64> = redis::cmd("LRANGE")
+            .arg(self.key(RedisKey::ChannelMessages { channel_id }))
+            .arg(0)
+            .arg(-1)
+            .query_async(conn)
+            .await?;
+
+        Ok(ids.into_iter().map(Id::new).collect())
+    }
+
+    /// Load a snapshot written by [`export`], returning the number of records
+    /// read.
+    ///
+    /// Records of resource types not enabled in the configuration are skipped.
+    /// Messages are appended to the messages of their channel, so the snapshot
+    /// should be imported into an empty cache.
+    ///
+    /// [`export`]: Self::export
+    pub async fn import(&self, input: impl BufRead) -> Result<usize, Error> {
+        let mut conn = self.get_connection().await?;
+        let mut pipe = self.pipe();
+        let mut count = 0;
+        let mut queued = 0;
+
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let record: Record<S> = serde_json::from_str(&line)?;
+            count += 1;
+
+            if !self.wants(record.resource_type()) {
+                continue;
+            }
+
+            match record {
+                Record::CurrentUser { data } => {
+                    pipe.set_current_user(&data)?;
+                }
+                Record::Guild {
+                    id,
+                    unavailable,
+                    data,
+                } => {
+                    if let Some(data) = data {
+                        pipe.add_guild(id).set_guild(id, &data)?;
+                    }
+                    if unavailable {
+                        pipe.add_unavailable_guild(id);
+                    }
+                }
+                Record::Channel { guild_id, id, data } => {
+                    if data.kind().is_thread() {
+                        index_thread(&mut pipe, &data);
+                    }
+                    pipe.add_guild_channel(guild_id, id)
+                        .set_channel(Some(guild_id), id, &data)?;
+                }
+                Record::Emoji { guild_id, id, data } => {
+                    pipe.add_guild_emoji(guild_id, [id].into_iter())
+                        .set_emojis(guild_id, [(id, data)].into_iter())?;
+                }
+                Record::Integration { guild_id, id, data } => {
+                    pipe.add_guild_integration(guild_id, id)
+                        .set_integration(guild_id, id, &data)?;
+                }
+                Record::Member { guild_id, id, data } => {
+                    pipe.add_guild_member(guild_id, id)
+                        .set_member(guild_id, id, &data)?;
+                }
+                Record::Presence { guild_id, id, data } => {
+                    pipe.add_guild_presence(guild_id, id)
+                        .set_presence(guild_id, id, &data)?;
+                }
+                Record::Role { guild_id, id, data } => {
+                    pipe.add_guild_role(guild_id, id)
+                        .set_role(guild_id, id, &data)?;
+                }
+                Record::StageInstance { guild_id, id, data } => {
+                    pipe.add_guild_stage_instance(guild_id, id)
+                        .set_stage_instance(guild_id, id, &data)?;
+                }
+                Record::Sticker { guild_id, id, data } => {
+                    pipe.add_guild_sticker(guild_id, id)
+                        .set_sticker(guild_id, id, &data)?;
+                }
+                Record::VoiceState { guild_id, id, data } => {
+                    set_voice_state_cache(&mut pipe, guild_id, data.channel_id(), id, &data)?;
+                }
+                Record::ThreadMember {
+                    thread_id,
+                    id,
+                    data,
+                } => {
+                    pipe.add_thread_member(thread_id, id)
+                        .set_thread_member(thread_id, id, &data)?;
+                }
+                Record::Message {
+                    channel_id,
+                    id,
+                    data,
+                } => {
+                    pipe.push_channel_message(channel_id, id)
+                        .set_message(id, &data)?;
+                    if data.pinned() {
+                        pipe.add_channel_pin(channel_id, id);
+                    }
+                }
+                Record::User {
+                    id,
+                    guild_ids,
+                    data,
+                } => {
+                    for guild_id in guild_ids {
+                        pipe.add_user_guild(id, guild_id);
+                    }
+                    pipe.add_user(id).set_user(id, &data)?;
+                }
+            }
+
+            queued += 1;
+            if queued == BATCH_SIZE {
+                let _: redis::Value = pipe.pipeline.query_async(&mut conn).await?;
+                pipe = self.pipe();
+                queued = 0;
+            }
+        }
+
+        if !pipe.is_empty() {
+            let _: redis::Value = pipe.pipeline.query_async(&mut conn).await?;
+        }
+
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use redis::AsyncCommands;
+    use twilight_model::{
+        gateway::payload::incoming::{GuildCreate, MessageCreate, ThreadMembersUpdate},
+        id::Id,
+    };
+
+    use crate::{cache::RedisKey, test, ConfigBuilder};
+
+    #[test]
+    fn test_export_import() {
+        test::block_on(async {
+            let mut source = test::redis_cache_with_config(
+                ConfigBuilder::default()
+                    .namespace("snapshot_source")
+                    .build(),
+            )
+            .await;
+            let target = test::redis_cache_with_config(
+                ConfigBuilder::default()
+                    .namespace("snapshot_target")
+                    .build(),
+            )
+            .await;
+
+            let guild_id = Id::new(941);
+            let channel_id = Id::new(942);
+            let user_id = Id::new(943);
+            let role_id = Id::new(944);
+            let message_id = Id::new(945);
+            let thread_id = Id::new(946);
+
+            for cache in [&source, &target] {
+                let _: () = cache
+                    .get_connection()
+                    .await
+                    .unwrap()
+                    .del(cache.key(RedisKey::ChannelMessages { channel_id }))
+                    .await
+                    .unwrap();
+            }
+
+            let mut guild = test::model::guild(guild_id);
+            guild.channels = vec![test::model::channel(channel_id, Some(guild_id))];
+            guild.threads = vec![test::model::thread(thread_id, guild_id, channel_id, false)];
+            guild.members = vec![test::model::member(user_id)];
+            guild.roles = vec![test::model::role(role_id)];
+            source.update(GuildCreate(guild)).await.unwrap();
+            source
+                .update(ThreadMembersUpdate {
+                    added_members: vec![test::model::thread_member(thread_id, user_id)],
+                    guild_id,
+                    id: thread_id,
+                    member_count: 1,
+                    removed_member_ids: Vec::new(),
+                })
+                .await
+                .unwrap();
+            source
+                .update(MessageCreate(test::model::message(
+                    message_id, channel_id, user_id,
+                )))
+                .await
+                .unwrap();
+
+            let mut snapshot = Vec::new();
+            let exported = source.export(&mut snapshot).await.unwrap();
+            assert_eq!(target.import(&snapshot[..]).await.unwrap(), exported);
+
+            let mut conn = target.get_connection().await.unwrap();
+            assert!(target
+                .get_guild(&mut conn, guild_id)
+                .await
+                .unwrap()
+                .is_some());
+            assert!(target
+                .guild_channels_contains(&mut conn, guild_id, channel_id)
+                .await
+                .unwrap());
+            assert!(target
+                .get_member(&mut conn, guild_id, user_id)
+                .await
+                .unwrap()
+                .is_some());
+            assert!(target
+                .get_role(&mut conn, guild_id, role_id)
+                .await
+                .unwrap()
+                .is_some());
+            assert!(target
+                .get_thread_member(&mut conn, thread_id, user_id)
+                .await
+                .unwrap()
+                .is_some());
+            assert_eq!(
+                target
+                    .range_channel_message_ids(&mut conn, channel_id)
+                    .await
+                    .unwrap(),
+                [message_id]
+            );
+
+            let mut reexported = Vec::new();
+            target.export(&mut reexported).await.unwrap();
+
+            let mut snapshot: Vec<_> = snapshot.split(|byte| *byte == b'\n').collect();
+            let mut reexported: Vec<_> = reexported.split(|byte| *byte == b'\n').collect();
+            snapshot.sort_unstable();
+            reexported.sort_unstable();
+            assert_eq!(snapshot, reexported);
+        });
+    }
+}
@@ -0,0 +1,220 @@
+use redis::AsyncCommands;
+use twilight_model::id::{marker::GuildMarker, Id};
+
+use super::{Pipe, RedisKey};
+use crate::{CacheStrategy, Error, RedisCache};
+
+/// Number of sets counted by [`Pipe::len_guild_sets`].
+const GUILD_SETS: usize = 9;
+
+/// Counts of the resources in the cache of a single guild.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct GuildStats {
+    pub channels: usize,
+    pub emojis: usize,
+    pub integrations: usize,
+    pub members: usize,
+    pub presences: usize,
+    pub roles: usize,
+    pub stage_instances: usize,
+    pub stickers: usize,
+    pub voice_states: usize,
+}
+
+impl GuildStats {
+    fn from_lens(lens: &[usize]) -> Self {
+        Self {
+            channels: lens[0],
+            emojis: lens[1],
+            integrations: lens[2],
+            members: lens[3],
+            presences: lens[4],
+            roles: lens[5],
+            stage_instances: lens[6],
+            stickers: lens[7],
+            voice_states: lens[8],
+        }
+    }
+}
+
+/// Counts of the resources in the whole cache.
+///
+/// The counts other than `guilds`, `unavailable_guilds` and `users` are the
+/// sums of the per-guild counts, so channels and messages outside of guilds are
+/// not included.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CacheStats {
+    pub guilds: usize,
+    pub unavailable_guilds: usize,
+    pub users: usize,
+    pub channels: usize,
+    pub emojis: usize,
+    pub integrations: usize,
+    pub members: usize,
+    pub presences: usize,
+    pub roles: usize,
+    pub stage_instances: usize,
+    pub stickers: usize,
+    pub voice_states: usize,
+}
+
+impl CacheStats {
+    fn add_guild(&mut self, guild: GuildStats) {
+        self.channels += guild.channels;
+        self.emojis += guild.emojis;
+        self.integrations += guild.integrations;
+        self.members += guild.members;
+        self.presences += guild.presences;
+        self.roles += guild.roles;
+        self.stage_instances += guild.stage_instances;
+        self.stickers += guild.stickers;
+        self.voice_states += guild.voice_states;
+    }
+}
+
+impl<S: CacheStrategy> Pipe<S> {
+    /// Count every per-guild set of a guild, in the order of the fields of
+    /// [`GuildStats`].
+    fn len_guild_sets(&mut self, guild_id: Id<GuildMarker>) -> &mut Self {
+        self.len_guild_channels(guild_id)
+            .len_guild_emojis(guild_id)
+            .len_guild_integrations(guild_id)
+            .len_guild_members(guild_id)
+            .len_guild_presences(guild_id)
+            .len_guild_roles(guild_id)
+            .len_guild_stage_instances(guild_id)
+            .len_guild_stickers(guild_id)
+            .len_guild_voice_states(guild_id)
+    }
+}
+
+impl<S: CacheStrategy> RedisCache<S> {
+    /// Count the resources in the whole cache.
+    ///
+    /// The ids of the guilds are read first, then every count is fetched in
+    /// a single round trip.
+    ///
+    /// Statistics are not tied to an update, so they are read from
+    /// [`get_read_connection`], which may be a replica, rather than from a
+    /// connection passed in.
+    ///
+    /// [`get_read_connection`]: Self::get_read_connection
+    pub async fn stats(&self) -> Result<CacheStats, Error> {
+        let mut conn = self.get_read_connection().await?;
+
+        let mut guild_ids = Vec::new();
+        let mut iter = conn.sscan::<_, u64>(self.key(RedisKey::Guilds)).await?;
+        while let Some(guild_id) = iter.next_item().await {
+            guild_ids.push(Id::<GuildMarker>::new(guild_id));
+        }
+        drop(iter);
+
+        let mut pipe = self.pipe();
+        pipe.len_guilds().len_unavailable_guilds().len_users();
+        for guild_id in &guild_ids {
+            pipe.len_guild_sets(*guild_id);
+        }
+
+        let lens: Vec<usize> = pipe.query(&mut conn).await?;
+        let mut stats = CacheStats {
+            guilds: lens[0],
+            unavailable_guilds: lens[1],
+            users: lens[2],
+            ..CacheStats::default()
+        };
+
+        for lens in lens[3..].chunks(GUILD_SETS) {
+            stats.add_guild(GuildStats::from_lens(lens));
+        }
+
+        Ok(stats)
+    }
+
+    /// Count the resources of a guild in a single round trip, on a connection
+    /// from [`get_read_connection`] like [`stats`].
+    ///
+    /// [`get_read_connection`]: Self::get_read_connection
+    /// [`stats`]: Self::stats
+    pub async fn guild_stats(&self, guild_id: Id<GuildMarker>) -> Result<GuildStats, Error> {
+        let mut pipe = self.pipe();
+        pipe.len_guild_sets(guild_id);
+
+        let lens: Vec<usize> = pipe.query(&mut self.get_read_connection().await?).await?;
+
+        Ok(GuildStats::from_lens(&lens))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use twilight_model::{gateway::payload::incoming::GuildCreate, id::Id};
+
+    use super::{CacheStats, GuildStats};
+    use crate::{test, ConfigBuilder};
+
+    #[test]
+    fn test_guild_stats() {
+        test::block_on(async {
+            let mut cache =
+                test::redis_cache_with_config(ConfigBuilder::default().namespace("stats").build())
+                    .await;
+            let guild_id = Id::new(501);
+            let channel_id = Id::new(502);
+            let user_id = Id::new(503);
+
+            let mut guild = test::model::guild(guild_id);
+            guild.channels = vec![
+                test::model::channel(channel_id, Some(guild_id)),
+                test::model::channel(Id::new(504), Some(guild_id)),
+            ];
+            // Members of a guild are not cached as users, but authors of its
+            // emojis are.
+            guild.emojis = vec![test::model::emoji(Id::new(505), Some(user_id))];
+            guild.members = vec![test::model::member(user_id)];
+            guild.presences = vec![test::model::presence(guild_id, user_id)];
+            guild.roles = vec![test::model::role(Id::new(506))];
+            guild.stickers = vec![test::model::sticker(Id::new(507), guild_id)];
+            guild.voice_states = vec![test::model::voice_state(guild_id, channel_id, user_id)];
+
+            cache.update(GuildCreate(guild)).await.unwrap();
+
+            assert_eq!(
+                cache.guild_stats(guild_id).await.unwrap(),
+                GuildStats {
+                    channels: 2,
+                    emojis: 1,
+                    integrations: 0,
+                    members: 1,
+                    presences: 1,
+                    roles: 1,
+                    stage_instances: 0,
+                    stickers: 1,
+                    voice_states: 1,
+                }
+            );
+
+            assert_eq!(
+                cache.stats().await.unwrap(),
+                CacheStats {
+                    guilds: 1,
+                    unavailable_guilds: 0,
+                    users: 1,
+                    channels: 2,
+                    emojis: 1,
+                    integrations: 0,
+                    members: 1,
+                    presences: 1,
+                    roles: 1,
+                    stage_instances: 0,
+                    stickers: 1,
+                    voice_states: 1,
+                }
+            );
+
+            assert_eq!(
+                cache.guild_stats(Id::new(508)).await.unwrap(),
+                GuildStats::default()
+            );
+        });
+    }
+}
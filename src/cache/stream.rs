@@ -0,0 +1,281 @@
+use std::collections::VecDeque;
+
+use futures_core::Stream;
+use futures_util::{stream::try_unfold, TryStreamExt};
+use twilight_model::id::{
+    marker::{ChannelMarker, GuildMarker},
+    Id,
+};
+
+use super::{cmd, FromCachedRedisValue, RedisKey, WithGuildId};
+use crate::{CacheStrategy, Connection, Error, RedisCache};
+
+/// Number of ids asked for by each `SSCAN`, whose values are then fetched with
+/// a single `MGET`.
+const BATCH_SIZE: usize = 100;
+
+/// State of a stream resolving the ids of a set to values.
+struct Batches<'a, T> {
+    conn: Option<Connection<'a>>,
+    /// Cursor of the next `SSCAN`, `None` once the whole set was scanned.
+    cursor: Option<u64>,
+    values: VecDeque<T>,
+}
+
+macro_rules! impl_guild_stream_methods {
+    ($(
+        $set_name:ident ($what:literal): RedisKey::$set_key:ident,
+        $value:ty => $resource:ty,
+        $value_key:expr,
+        $map:expr;
+    )*) => {
+        paste::paste! {
+            impl<S: CacheStrategy> RedisCache<S> {
+                $(
+                    #[doc = concat!("Stream the ", $what, " of a guild.")]
+                    ///
+                    /// Unlike the `get_*` getters, this takes no connection:
+                    /// the stream holds one of its own between polls, taken
+                    /// from [`get_read_connection`].
+                    ///
+                    /// [`get_read_connection`]: Self::get_read_connection
+                    pub fn [<stream_ $set_name>](
+                        &self,
+                        guild_id: Id<GuildMarker>,
+                    ) -> impl Stream<Item = Result<$resource, Error>> + '_ {
+                        self.stream_set::<_, $value, _>(
+                            RedisKey::$set_key { guild_id },
+                            guild_id,
+                            $value_key,
+                            $map,
+                        )
+                    }
+
+                    #[doc = concat!("Get the ", $what, " of a guild.")]
+                    ///
+                    /// Like the stream, this fetches the values in batches on
+                    /// a read connection of its own and skips ids whose value
+                    /// is missing.
+                    pub async fn $set_name(
+                        &self,
+                        guild_id: Id<GuildMarker>,
+                    ) -> Result<Vec<$resource>, Error> {
+                        self.[<stream_ $set_name>](guild_id).try_collect().await
+                    }
+                )*
+            }
+        }
+    };
+}
+
+impl_guild_stream_methods! {
+    guild_channels ("channels"): RedisKey::GuildChannels, S::Channel => S::Channel,
+        |guild_id, id| RedisKey::Channel { guild_id: Some(guild_id), channel_id: Id::new(id) },
+        |channel| channel;
+    guild_emojis ("emojis"): RedisKey::GuildEmojis, WithGuildId<S::Emoji> => S::Emoji,
+        |guild_id, id| RedisKey::Emoji { guild_id, emoji_id: Id::new(id) },
+        |emoji| emoji.resource;
+    guild_integrations ("integrations"): RedisKey::GuildIntegrations,
+        S::GuildIntegration => S::GuildIntegration,
+        |guild_id, id| RedisKey::Integration { guild_id, integration_id: Id::new(id) },
+        |integration| integration;
+    guild_members ("members"): RedisKey::GuildMembers, S::Member => S::Member,
+        |guild_id, id| RedisKey::Member { guild_id, user_id: Id::new(id) }, |member| member;
+    guild_presences ("presences"): RedisKey::GuildPresences, S::Presence => S::Presence,
+        |guild_id, id| RedisKey::Presence { guild_id, user_id: Id::new(id) },
+        |presence| presence;
+    guild_roles ("roles"): RedisKey::GuildRoles, WithGuildId<S::Role> => S::Role,
+        |guild_id, id| RedisKey::Role { guild_id, role_id: Id::new(id) }, |role| role.resource;
+    guild_stage_instances ("stage instances"): RedisKey::GuildStageInstances,
+        WithGuildId<S::StageInstance> => S::StageInstance,
+        |guild_id, id| RedisKey::StageInstance { guild_id, stage_id: Id::new(id) },
+        |stage_instance| stage_instance.resource;
+    guild_stickers ("stickers"): RedisKey::GuildStickers, WithGuildId<S::Sticker> => S::Sticker,
+        |guild_id, id| RedisKey::Sticker { guild_id, sticker_id: Id::new(id) },
+        |sticker| sticker.resource;
+    guild_voice_states ("voice states"): RedisKey::GuildVoiceStates, S::VoiceState => S::VoiceState,
+        |guild_id, id| RedisKey::VoiceState { guild_id, user_id: Id::new(id) },
+        |voice_state| voice_state;
+}
+
+impl<S: CacheStrategy> RedisCache<S> {
+    /// Stream the pinned messages of a channel.
+    ///
+    /// Only the pinned messages still in the cache are returned, like the ids
+    /// of [`scan_channel_pins`]: a pinned message evicted from the cache is
+    /// dropped from the pins as well, so neither is a full pin history.
+    ///
+    /// Like the streams of guild sets, it reads on a connection of its own
+    /// from [`get_read_connection`].
+    ///
+    /// [`get_read_connection`]: Self::get_read_connection
+    /// [`scan_channel_pins`]: Self::scan_channel_pins
+    pub fn stream_channel_pins(
+        &self,
+        channel_id: Id<ChannelMarker>,
+    ) -> impl Stream<Item = Result<S::Message, Error>> + '_ {
+        self.stream_set::<_, S::Message, _>(
+            RedisKey::ChannelPins { channel_id },
+            channel_id,
+            |_, id| RedisKey::Message { id: Id::new(id) },
+            |message| message,
+        )
+    }
+
+    /// Get the pinned messages of a channel still in the cache, reading on a
+    /// connection of its own like [`stream_channel_pins`].
+    ///
+    /// [`stream_channel_pins`]: Self::stream_channel_pins
+    pub async fn channel_pins(
+        &self,
+        channel_id: Id<ChannelMarker>,
+    ) -> Result<Vec<S::Message>, Error> {
+        self.stream_channel_pins(channel_id).try_collect().await
+    }
+
+    /// Stream the values of the ids in a set, such as a set of a guild.
+    ///
+    /// The set is scanned in batches, and the values of each batch are fetched
+    /// with a single `MGET`. Ids whose value is missing, such as expired ones,
+    /// are skipped.
+    fn stream_set<'a, P, V, T>(
+        &'a self,
+        set: RedisKey,
+        parent_id: P,
+        value_key: fn(P, u64) -> RedisKey,
+        map: fn(V) -> T,
+    ) -> impl Stream<Item = Result<T, Error>> + 'a
+    where
+        P: Copy + 'a,
+        V: FromCachedRedisValue + 'a,
+        T: 'a,
+    {
+        let state = Batches {
+            conn: None,
+            cursor: Some(0),
+            values: VecDeque::new(),
+        };
+
+        try_unfold(state, move |mut state| async move {
+            loop {
+                if let Some(value) = state.values.pop_front() {
+                    return Ok(Some((value, state)));
+                }
+
+                let Some(cursor) = state.cursor else {
+                    return Ok(None);
+                };
+
+                let conn = match &mut state.conn {
+                    Some(conn) => conn,
+                    None => state.conn.insert(self.get_read_connection().await?),
+                };
+
+                let (cursor, ids): (u64, Vec<u64>) = redis::cmd("SSCAN")
+                    .arg(self.key(set))
+                    .arg(cursor)
+                    .arg("COUNT")
+                    .arg(BATCH_SIZE)
+                    .query_async(conn)
+                    .await?;
+                state.cursor = (cursor != 0).then_some(cursor);
+
+                if ids.is_empty() {
+                    continue;
+                }
+
+                let keys: Vec<Vec<u8>> = ids
+                    .into_iter()
+                    .map(|id| self.key(value_key(parent_id, id)))
+                    .collect();
+                let values = cmd::get_many::<S::Codec, V>(conn, keys).await?;
+
+                state.values.extend(values.into_iter().flatten().map(map));
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use futures_util::StreamExt;
+    use redis::AsyncCommands;
+    use twilight_model::{
+        gateway::payload::incoming::GuildCreate,
+        id::{marker::UserMarker, Id},
+    };
+
+    use crate::{cache::RedisKey, test};
+
+    #[test]
+    fn test_stream_guild_members() {
+        test::block_on(async {
+            let mut cache = test::redis_cache().await;
+            let guild_id = Id::new(701);
+            let user_ids: HashSet<Id<UserMarker>> = (702..=704).map(Id::new).collect();
+
+            let mut guild = test::model::guild(guild_id);
+            guild.members = user_ids.iter().copied().map(test::model::member).collect();
+            cache.update(GuildCreate(guild)).await.unwrap();
+
+            // A member whose value is gone is skipped.
+            let mut conn = cache.get_connection().await.unwrap();
+            let _: () = conn
+                .sadd(RedisKey::GuildMembers { guild_id }, 705)
+                .await
+                .unwrap();
+
+            let mut streamed = HashSet::new();
+            let mut stream = std::pin::pin!(cache.stream_guild_members(guild_id));
+            while let Some(member) = stream.next().await {
+                streamed.insert(member.unwrap().user_id());
+            }
+
+            assert_eq!(streamed, user_ids);
+
+            let scanned = cache
+                .scan_guild_members(&mut conn, guild_id)
+                .await
+                .unwrap()
+                .map(Result::unwrap)
+                .collect::<HashSet<_>>()
+                .await;
+
+            assert_eq!(scanned.len(), 4);
+        });
+    }
+
+    #[test]
+    fn test_guild_roles() {
+        test::block_on(async {
+            let mut cache = test::redis_cache().await;
+            let guild_id = Id::new(901);
+
+            let mut guild = test::model::guild(guild_id);
+            guild.roles = vec![
+                test::model::role(Id::new(902)),
+                test::model::role(Id::new(903)),
+            ];
+            cache.update(GuildCreate(guild)).await.unwrap();
+
+            let mut conn = cache.get_connection().await.unwrap();
+            let _: () = conn
+                .sadd(RedisKey::GuildRoles { guild_id }, 904)
+                .await
+                .unwrap();
+
+            let role_ids = cache
+                .guild_roles(guild_id)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|role| role.id)
+                .collect::<HashSet<_>>();
+
+            assert_eq!(role_ids, HashSet::from([Id::new(902), Id::new(903)]));
+            assert!(cache.guild_emojis(Id::new(905)).await.unwrap().is_empty());
+        });
+    }
+}
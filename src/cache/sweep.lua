@@ -0,0 +1,20 @@
+-- Remove the entries of an index whose value no longer exists, checking and
+-- removing them at once so that a value written meanwhile keeps its entry.
+--
+-- KEYS starts with the index, followed by the value key of each entry. ARGV
+-- starts with the kind of the index, `list` or `set`, followed by each entry.
+-- Returns the removed entries.
+local removed = {}
+
+for i = 2, #KEYS do
+    if redis.call('EXISTS', KEYS[i]) == 0 then
+        if ARGV[1] == 'list' then
+            redis.call('LREM', KEYS[1], 0, ARGV[i])
+        else
+            redis.call('SREM', KEYS[1], ARGV[i])
+        end
+        removed[#removed + 1] = ARGV[i]
+    end
+end
+
+return removed
@@ -0,0 +1,594 @@
+use std::sync::OnceLock;
+
+use redis::{AsyncCommands, Script};
+use twilight_model::id::Id;
+
+use super::{cmd, RedisKey, ToBytes};
+use crate::{config::ResourceType, CacheStrategy, Connection, Error, RedisCache};
+
+/// Number of index entries checked in a single round trip.
+const BATCH_SIZE: usize = 1000;
+
+/// A node to run `SCAN` on, as the host and port of a master of a Redis
+/// Cluster, or `None` for the server behind the connection.
+pub(super) type ScanNode = Option<(String, u16)>;
+
+/// The nodes on which `SCAN` sees every key: each master of a Redis Cluster
+/// holds the keys of its own slots only.
+pub(super) async fn scan_nodes(conn: &mut Connection<'_>) -> Result<Vec<ScanNode>, Error> {
+    match conn {
+        #[cfg(feature = "cluster")]
+        Connection::ClusterConnection(conn) => Ok(crate::cluster::masters(conn)
+            .await?
+            .into_iter()
+            .map(Some)
+            .collect()),
+        _ => Ok(vec![None]),
+    }
+}
+
+/// Where the ids pointing at values of a resource type are kept.
+pub(super) enum Index {
+    /// A single set of ids, such as `GUILDS`.
+    Global(RedisKey),
+    /// A set of ids per parent id, such as `GUILD_MEMBERS:{<guild_id>}`. The
+    /// parent id is the first id of the key, so that of `CHANNEL_THREADS` is
+    /// the guild id in its hash tag rather than the channel id.
+    Set(&'static str),
+    /// A list of ids per parent id, such as `CHANNEL_MESSAGES:<channel_id>`.
+    List(&'static str),
+}
+
+/// The index of each resource type which can expire, along with the key of the
+/// value an entry points at, built from the parent id and the entry.
+#[allow(clippy::type_complexity)]
+pub(super) const INDEXES: &[(ResourceType, Index, fn(u64, u64) -> RedisKey)] = &[
+    (
+        ResourceType::GUILD,
+        Index::Global(RedisKey::Guilds),
+        |_, id| RedisKey::Guild { id: Id::new(id) },
+    ),
+    (
+        ResourceType::USER,
+        Index::Global(RedisKey::Users),
+        |_, id| RedisKey::User { id: Id::new(id) },
+    ),
+    (ResourceType::GUILD, Index::Set("USER_GUILDS"), |_, id| {
+        RedisKey::Guild { id: Id::new(id) }
+    }),
+    (
+        ResourceType::CHANNEL,
+        Index::Set("GUILD_CHANNELS"),
+        |guild_id, id| RedisKey::Channel {
+            guild_id: Some(Id::new(guild_id)),
+            channel_id: Id::new(id),
+        },
+    ),
+    (
+        ResourceType::CHANNEL,
+        Index::Set("CHANNEL_THREADS"),
+        |guild_id, id| RedisKey::Channel {
+            guild_id: Some(Id::new(guild_id)),
+            channel_id: Id::new(id),
+        },
+    ),
+    (
+        ResourceType::CHANNEL,
+        Index::Set("GUILD_ACTIVE_THREADS"),
+        |guild_id, id| RedisKey::Channel {
+            guild_id: Some(Id::new(guild_id)),
+            channel_id: Id::new(id),
+        },
+    ),
+    (
+        ResourceType::EMOJI,
+        Index::Set("GUILD_EMOJIS"),
+        |guild_id, id| RedisKey::Emoji {
+            guild_id: Id::new(guild_id),
+            emoji_id: Id::new(id),
+        },
+    ),
+    (
+        ResourceType::INTEGRATION,
+        Index::Set("GUILD_INTEGRATIONS"),
+        |guild_id, id| RedisKey::Integration {
+            guild_id: Id::new(guild_id),
+            integration_id: Id::new(id),
+        },
+    ),
+    (
+        ResourceType::MEMBER,
+        Index::Set("GUILD_MEMBERS"),
+        |guild_id, id| RedisKey::Member {
+            guild_id: Id::new(guild_id),
+            user_id: Id::new(id),
+        },
+    ),
+    (
+        ResourceType::PRESENCE,
+        Index::Set("GUILD_PRESENCES"),
+        |guild_id, id| RedisKey::Presence {
+            guild_id: Id::new(guild_id),
+            user_id: Id::new(id),
+        },
+    ),
+    (
+        ResourceType::ROLE,
+        Index::Set("GUILD_ROLES"),
+        |guild_id, id| RedisKey::Role {
+            guild_id: Id::new(guild_id),
+            role_id: Id::new(id),
+        },
+    ),
+    (
+        ResourceType::STAGE_INSTANCE,
+        Index::Set("GUILD_STAGE_INSTANCES"),
+        |guild_id, id| RedisKey::StageInstance {
+            guild_id: Id::new(guild_id),
+            stage_id: Id::new(id),
+        },
+    ),
+    (
+        ResourceType::STICKER,
+        Index::Set("GUILD_STICKERS"),
+        |guild_id, id| RedisKey::Sticker {
+            guild_id: Id::new(guild_id),
+            sticker_id: Id::new(id),
+        },
+    ),
+    (
+        ResourceType::VOICE_STATE,
+        Index::Set("GUILD_VOICE_STATES"),
+        |guild_id, id| RedisKey::VoiceState {
+            guild_id: Id::new(guild_id),
+            user_id: Id::new(id),
+        },
+    ),
+    (
+        ResourceType::THREAD_MEMBER,
+        Index::Set("THREAD_MEMBERS"),
+        |thread_id, id| RedisKey::ThreadMember {
+            thread_id: Id::new(thread_id),
+            user_id: Id::new(id),
+        },
+    ),
+    (
+        ResourceType::MESSAGE,
+        Index::List("CHANNEL_MESSAGES"),
+        |_, id| RedisKey::Message { id: Id::new(id) },
+    ),
+    (
+        ResourceType::MESSAGE,
+        Index::Set("CHANNEL_PINS"),
+        |_, id| RedisKey::Message { id: Id::new(id) },
+    ),
+];
+
+impl<S: CacheStrategy> RedisCache<S> {
+    /// Remove the entries of the index sets and message lists whose value has expired.
+    ///
+    /// Values of a resource type with a TTL (see [`ConfigBuilder::ttl`]) are
+    /// dropped by Redis on their own, but the ids pointing at them are not.
+    /// Call this periodically to clean them up. Returns the number of entries
+    /// removed.
+    ///
+    /// [`ConfigBuilder::ttl`]: crate::ConfigBuilder::ttl
+    pub async fn sweep(&self) -> Result<usize, Error> {
+        let mut conn = self.get_connection().await?;
+        let mut removed = 0;
+
+        for (resource_type, index, value_key) in INDEXES {
+            if self.config.ttl(*resource_type).is_none() {
+                continue;
+            }
+
+            let expired: Vec<(u64, u64)> = self
+                .sweep_index(&mut conn, index, *value_key, true)
+                .await?
+                .into_iter()
+                .map(|(_, parent_id, id)| (parent_id, id))
+                .collect();
+            removed += expired.len();
+
+            if *resource_type == ResourceType::VOICE_STATE && !expired.is_empty() {
+                self.sweep_channel_voice_states(&mut conn, &expired).await?;
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// Find the entries of an index whose value no longer exists, removing
+    /// them if `remove` is set. Returns the index key, parent id and id of
+    /// each entry.
+    ///
+    /// Entries are checked and removed by a script in batches, so that an entry
+    /// whose value is written again in between is kept. A script may only use
+    /// keys of a single slot on a Redis Cluster, so an entry whose value is in
+    /// another slot than its index, such as a guild of `GUILDS` or a message of
+    /// a channel, is checked first and removed afterwards instead: if its value
+    /// is written in between, the entry is missing until the value is written
+    /// again.
+    #[allow(clippy::type_complexity)]
+    pub(super) async fn sweep_index(
+        &self,
+        conn: &mut Connection<'_>,
+        index: &Index,
+        value_key: fn(u64, u64) -> RedisKey,
+        remove: bool,
+    ) -> Result<Vec<(Vec<u8>, u64, u64)>, Error> {
+        let index_keys = match index {
+            Index::Global(key) => vec![(self.key(*key), 0)],
+            Index::Set(name) | Index::List(name) => self.scan_index_keys(conn, name).await?,
+        };
+        let mut expired = Vec::new();
+
+        for (index_key, parent_id) in index_keys {
+            let ids: Vec<u64> = if let Index::List(_) = index {
+                conn.lrange(&index_key, 0, -1).await?
+            } else {
+                let mut iter = conn.sscan::<_, u64>(&index_key).await?;
+                let mut ids = Vec::new();
+                while let Some(id) = iter.next_item().await {
+                    ids.push(id);
+                }
+                ids
+            };
+
+            let index_slot = conn.slot(&index_key);
+
+            for ids in ids.chunks(BATCH_SIZE) {
+                let (scripted, checked): (Vec<u64>, Vec<u64>) = if remove {
+                    ids.iter().partition(|id| {
+                        conn.slot(&self.key(value_key(parent_id, **id))) == index_slot
+                    })
+                } else {
+                    (Vec::new(), ids.to_vec())
+                };
+
+                let mut dangling: Vec<u64> = if scripted.is_empty() {
+                    Vec::new()
+                } else {
+                    static SCRIPT: OnceLock<Script> = OnceLock::new();
+                    let script = SCRIPT.get_or_init(|| Script::new(include_str!("sweep.lua")));
+
+                    let mut invocation = script.prepare_invoke();
+                    invocation.key(&index_key).arg(match index {
+                        Index::List(_) => "list",
+                        Index::Global(_) | Index::Set(_) => "set",
+                    });
+                    for id in &scripted {
+                        invocation.key(self.key(value_key(parent_id, *id))).arg(*id);
+                    }
+
+                    invocation.invoke_async(conn).await?
+                };
+
+                let missing = self
+                    .missing_values(conn, parent_id, &checked, value_key)
+                    .await?;
+                if remove && !missing.is_empty() {
+                    let mut pipe = self.pipe();
+                    for id in &missing {
+                        match index {
+                            Index::List(_) => pipe.pipeline.lrem(&index_key, 0, *id),
+                            Index::Global(_) | Index::Set(_) => pipe.pipeline.srem(&index_key, *id),
+                        };
+                    }
+                    let _: redis::Value = pipe.query(conn).await?;
+                }
+                dangling.extend(missing);
+
+                expired.extend(
+                    dangling
+                        .into_iter()
+                        .map(|id| (index_key.clone(), parent_id, id)),
+                );
+            }
+        }
+
+        Ok(expired)
+    }
+
+    /// Find the ids among `ids` whose value does not exist.
+    async fn missing_values(
+        &self,
+        conn: &mut Connection<'_>,
+        parent_id: u64,
+        ids: &[u64],
+        value_key: fn(u64, u64) -> RedisKey,
+    ) -> Result<Vec<u64>, Error> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut pipe = self.pipe();
+        for id in ids {
+            pipe.pipeline.exists(pipe.key(value_key(parent_id, *id)));
+        }
+        let exists: Vec<bool> = pipe.query(conn).await?;
+
+        Ok(ids
+            .iter()
+            .zip(exists)
+            .filter_map(|(id, exists)| (!exists).then_some(*id))
+            .collect())
+    }
+
+    /// Remove expired voice states from the per-channel sets, which hold the
+    /// guild and user id pair instead of an id.
+    pub(super) async fn sweep_channel_voice_states(
+        &self,
+        conn: &mut Connection<'_>,
+        expired: &[(u64, u64)],
+    ) -> Result<(), Error> {
+        let users = expired
+            .iter()
+            .map(|(guild_id, user_id)| {
+                S::ChannelVoiceState::from((Id::new(*guild_id), Id::new(*user_id)))
+                    .to_bytes::<S::Codec>()
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let mut pipe = self.pipe();
+        for (index_key, _) in self.scan_index_keys(conn, "CHANNEL_VOICE_STATES").await? {
+            pipe.pipeline.srem(index_key, &users);
+        }
+
+        if !pipe.is_empty() {
+            let _: redis::Value = pipe.query(conn).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Find the keys of the index named `name` for every parent id, along with
+    /// that parent id.
+    async fn scan_index_keys(
+        &self,
+        conn: &mut Connection<'_>,
+        name: &str,
+    ) -> Result<Vec<(Vec<u8>, u64)>, Error> {
+        let prefix_len = self.key_prefix(name).len();
+        let mut keys = Vec::new();
+
+        for key in self.scan_keys(conn, name).await? {
+            let id = &key[prefix_len..];
+            let id = id.strip_prefix(b"{").unwrap_or(id);
+
+            if let Some(parent_id) = atoi::atoi::<u64>(id) {
+                keys.push((key, parent_id));
+            }
+        }
+
+        Ok(keys)
+    }
+
+    /// Find every key named `name` with an id, such as `MEMBER:{<guild_id>}:<user_id>`
+    /// for `MEMBER`.
+    pub(super) async fn scan_keys(
+        &self,
+        conn: &mut Connection<'_>,
+        name: &str,
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        let mut keys = Vec::new();
+
+        for node in scan_nodes(conn).await? {
+            let mut cursor = 0;
+
+            loop {
+                let (next, page) = self
+                    .scan_page(conn, &node, name, cursor, BATCH_SIZE)
+                    .await?;
+                keys.extend(page);
+
+                if next == 0 {
+                    break;
+                }
+                cursor = next;
+            }
+        }
+
+        Ok(keys)
+    }
+
+    /// Run a single `SCAN` on `node` for the keys named `name` with an id,
+    /// returning the next cursor and the keys found.
+    pub(super) async fn scan_page(
+        &self,
+        conn: &mut Connection<'_>,
+        node: &ScanNode,
+        name: &str,
+        cursor: u64,
+        count: usize,
+    ) -> Result<(u64, Vec<Vec<u8>>), Error> {
+        let mut cmd = redis::cmd("SCAN");
+        cmd.arg(cursor)
+            .arg("MATCH")
+            .arg(self.key_pattern(name))
+            .arg("COUNT")
+            .arg(count);
+
+        match (conn, node) {
+            #[cfg(feature = "cluster")]
+            (Connection::ClusterConnection(conn), Some(node)) => {
+                let page = crate::cluster::route_to_node(conn, &cmd, node).await?;
+
+                Ok(redis::from_redis_value(&page)?)
+            }
+            (conn, _) => Ok(cmd.query_async(conn).await?),
+        }
+    }
+
+    /// Read every id of a set.
+    pub(crate) async fn scan_ids<M>(
+        &self,
+        conn: &mut Connection<'_>,
+        set: RedisKey,
+    ) -> Result<Vec<Id<M>>, Error> {
+        let mut iter = cmd::scan::<S::Codec, Id<M>>(conn, self.key(set)).await?;
+        let mut ids = Vec::new();
+        while let Some(id) = iter.next_item().await? {
+            ids.push(id);
+        }
+
+        Ok(ids)
+    }
+
+    /// The `SCAN` pattern matching every key named `name` with an id.
+    fn key_pattern(&self, name: &str) -> Vec<u8> {
+        let prefix = self.key_prefix(name);
+
+        let mut pattern = Vec::with_capacity(prefix.len() + 1);
+        for byte in &prefix {
+            if matches!(byte, b'*' | b'?' | b'[' | b']' | b'\\') {
+                pattern.push(b'\\');
+            }
+            pattern.push(*byte);
+        }
+        pattern.push(b'*');
+
+        pattern
+    }
+
+    /// The namespace and name in front of the ids of a key, such as `bot:MEMBER:`.
+    fn key_prefix(&self, name: &str) -> Vec<u8> {
+        let mut prefix = Vec::new();
+        if let Some(namespace) = self.config.namespace() {
+            prefix.extend_from_slice(namespace.as_bytes());
+            prefix.push(b':');
+        }
+        prefix.extend_from_slice(name.as_bytes());
+        prefix.push(b':');
+
+        prefix
+    }
+
+    /// Parse a key read from Redis, which is in the namespace of this cache.
+    pub(super) fn parse_key(&self, key: &[u8]) -> Option<RedisKey> {
+        let key = match self.config.namespace() {
+            Some(namespace) => key.strip_prefix(namespace.as_bytes())?.strip_prefix(b":")?,
+            None => key,
+        };
+
+        RedisKey::parse(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use redis::AsyncCommands;
+    use twilight_model::{gateway::payload::incoming::MemberAdd, id::Id};
+
+    use crate::{cache::RedisKey, test, ConfigBuilder, ResourceType};
+
+    #[test]
+    fn test_ttl() {
+        test::block_on(async {
+            let mut cache = test::redis_cache_with_config(
+                ConfigBuilder::default()
+                    .ttl(ResourceType::MEMBER, Duration::from_secs(60))
+                    .build(),
+            )
+            .await;
+
+            let guild_id = Id::new(301);
+            let user_id = Id::new(302);
+
+            cache
+                .update(MemberAdd {
+                    guild_id,
+                    member: test::model::member(user_id),
+                })
+                .await
+                .unwrap();
+
+            let mut conn = cache.get_connection().await.unwrap();
+            let member_ttl: i64 = conn
+                .pttl(RedisKey::Member { guild_id, user_id })
+                .await
+                .unwrap();
+            let user_ttl: i64 = conn.pttl(RedisKey::User { id: user_id }).await.unwrap();
+
+            assert!(member_ttl > 0);
+            assert_eq!(user_ttl, -1);
+        });
+    }
+
+    #[test]
+    fn test_zero_ttl() {
+        test::block_on(async {
+            let mut cache = test::redis_cache_with_config(
+                ConfigBuilder::default()
+                    .namespace("zero_ttl")
+                    .ttl(ResourceType::MEMBER, Duration::ZERO)
+                    .build(),
+            )
+            .await;
+
+            let guild_id = Id::new(1058);
+            let user_id = Id::new(1059);
+
+            cache
+                .update(MemberAdd {
+                    guild_id,
+                    member: test::model::member(user_id),
+                })
+                .await
+                .unwrap();
+
+            std::thread::sleep(Duration::from_millis(10));
+
+            let mut conn = cache.get_connection().await.unwrap();
+            assert!(cache
+                .get_member(&mut conn, guild_id, user_id)
+                .await
+                .unwrap()
+                .is_none());
+        });
+    }
+
+    #[test]
+    fn test_sweep() {
+        test::block_on(async {
+            let mut cache = test::redis_cache_with_config(
+                ConfigBuilder::default()
+                    .ttl(ResourceType::MEMBER, Duration::from_millis(50))
+                    .build(),
+            )
+            .await;
+
+            let guild_id = Id::new(303);
+            let user_id = Id::new(304);
+
+            cache
+                .update(MemberAdd {
+                    guild_id,
+                    member: test::model::member(user_id),
+                })
+                .await
+                .unwrap();
+
+            let mut conn = cache.get_connection().await.unwrap();
+            assert!(cache
+                .guild_members_contains(&mut conn, guild_id, user_id)
+                .await
+                .unwrap());
+
+            std::thread::sleep(Duration::from_millis(100));
+
+            assert!(cache
+                .get_member(&mut conn, guild_id, user_id)
+                .await
+                .unwrap()
+                .is_none());
+            assert!(cache.sweep().await.unwrap() >= 1);
+            assert!(!cache
+                .guild_members_contains(&mut conn, guild_id, user_id)
+                .await
+                .unwrap());
+        });
+    }
+}
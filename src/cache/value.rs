@@ -5,23 +5,23 @@ use redis::{FromRedisValue, ToRedisArgs, Value};
 
 use twilight_model::id::Id;
 
-use crate::Error;
+use crate::{codec::Codec, Error};
 
 pub trait FromBytes: Sized {
-    fn from_bytes(raw: &[u8]) -> Result<Self, Error>;
+    fn from_bytes<C: Codec>(raw: &[u8]) -> Result<Self, Error>;
 }
 
 /// Implement `FromBytes` for the given models that implement `serde::Deserialize`.
 ///
 /// # Notes
-/// This macro uses bincode crate to deserialize the data.
+/// The data is deserialized with the [`Codec`] of the cache strategy.
 #[macro_export]
 macro_rules! __impl_from_bytes_for_model {
     ($($model:ty),*) => {
         $(
             impl $crate::cache::value::FromBytes for $model {
-                fn from_bytes(data: &[u8]) -> Result<Self, $crate::Error> {
-                    Ok(::serde_json::from_slice(data)?)
+                fn from_bytes<C: $crate::codec::Codec>(data: &[u8]) -> Result<Self, $crate::Error> {
+                    C::decode(data)
                 }
             }
         )*
@@ -31,7 +31,7 @@ macro_rules! __impl_from_bytes_for_model {
 pub use __impl_from_bytes_for_model as impl_from_bytes_for_model;
 
 impl<M> FromBytes for Id<M> {
-    fn from_bytes(raw: &[u8]) -> Result<Self, Error> {
+    fn from_bytes<C: Codec>(raw: &[u8]) -> Result<Self, Error> {
         let n = atoi(raw).ok_or_else(|| Error::Parse {
             msg: "Failed to parse ID.".to_owned(),
             response: format!("{raw:?}"),
@@ -41,19 +41,19 @@ impl<M> FromBytes for Id<M> {
 }
 
 pub trait FromCachedRedisValue: Sized {
-    fn from_cached_redis_value(value: &Value) -> Result<Self, Error>;
+    fn from_cached_redis_value<C: Codec>(value: &Value) -> Result<Self, Error>;
 }
 
 impl FromCachedRedisValue for Value {
-    fn from_cached_redis_value(value: &Value) -> Result<Self, Error> {
+    fn from_cached_redis_value<C: Codec>(value: &Value) -> Result<Self, Error> {
         Ok(value.clone())
     }
 }
 
 impl<T: FromBytes> FromCachedRedisValue for T {
-    fn from_cached_redis_value(value: &Value) -> Result<Self, Error> {
+    fn from_cached_redis_value<C: Codec>(value: &Value) -> Result<Self, Error> {
         match value {
-            Value::BulkString(data) => Ok(T::from_bytes(data)?),
+            Value::BulkString(data) => Ok(T::from_bytes::<C>(data)?),
             _ => Err(Error::Parse {
                 msg: "The value is not bytes.".to_owned(),
                 response: format!("{value:?}"),
@@ -66,7 +66,7 @@ macro_rules! impl_from_cached_redis_value_for_number {
     ($($num:ty),* $(,)?) => {
         $(
             impl FromCachedRedisValue for $num {
-                fn from_cached_redis_value(value: &Value) -> Result<Self, Error> {
+                fn from_cached_redis_value<C: Codec>(value: &Value) -> Result<Self, Error> {
                     Ok(Self::from_redis_value(value)?)
                 }
             }
@@ -79,26 +79,26 @@ impl_from_cached_redis_value_for_number!(
 );
 
 impl FromCachedRedisValue for bool {
-    fn from_cached_redis_value(value: &Value) -> Result<Self, Error> {
+    fn from_cached_redis_value<C: Codec>(value: &Value) -> Result<Self, Error> {
         Ok(FromRedisValue::from_redis_value(value)?)
     }
 }
 
 impl<T: FromCachedRedisValue> FromCachedRedisValue for Option<T> {
-    fn from_cached_redis_value(value: &Value) -> Result<Self, Error> {
+    fn from_cached_redis_value<C: Codec>(value: &Value) -> Result<Self, Error> {
         match value {
             Value::Nil => Ok(None),
-            _ => T::from_cached_redis_value(value).map(Some),
+            _ => T::from_cached_redis_value::<C>(value).map(Some),
         }
     }
 }
 
 impl<T: FromCachedRedisValue> FromCachedRedisValue for Vec<T> {
-    fn from_cached_redis_value(value: &Value) -> Result<Self, Error> {
+    fn from_cached_redis_value<C: Codec>(value: &Value) -> Result<Self, Error> {
         match value {
             Value::Array(values) => values
                 .iter()
-                .map(|v| T::from_cached_redis_value(v))
+                .map(|v| T::from_cached_redis_value::<C>(v))
                 .collect(),
             _ => Err(Error::Parse {
                 msg: "The value is not Array.".to_owned(),
@@ -109,8 +109,8 @@ impl<T: FromCachedRedisValue> FromCachedRedisValue for Vec<T> {
 }
 
 impl<T: FromCachedRedisValue> FromCachedRedisValue for VecDeque<T> {
-    fn from_cached_redis_value(value: &Value) -> Result<Self, Error> {
-        Vec::from_cached_redis_value(value).map(VecDeque::from)
+    fn from_cached_redis_value<C: Codec>(value: &Value) -> Result<Self, Error> {
+        Vec::from_cached_redis_value::<C>(value).map(VecDeque::from)
     }
 }
 
@@ -119,7 +119,7 @@ macro_rules! impl_drv_for_tuple {
         impl<#(T~N,)*> FromCachedRedisValue for (#(T~N,)*)
         where #(T~N: FromCachedRedisValue,)*
         {
-            fn from_cached_redis_value(value: &Value) -> Result<Self, Error> {
+            fn from_cached_redis_value<C: Codec>(value: &Value) -> Result<Self, Error> {
                 let Value::Array(values) = value else {
                     return Err(Error::Parse {
                         msg: "The value is not Array.".to_owned(),
@@ -128,7 +128,7 @@ macro_rules! impl_drv_for_tuple {
                 };
 
                 let data = (#(
-                    T~N::from_cached_redis_value(
+                    T~N::from_cached_redis_value::<C>(
                         values
                             .get(N)
                             .ok_or_else(|| Error::Parse {
@@ -156,20 +156,20 @@ seq_macro::seq!(I in 0..16 {
 });
 
 pub trait ToBytes {
-    fn to_bytes(&self) -> Result<Vec<u8>, Error>;
+    fn to_bytes<C: Codec>(&self) -> Result<Vec<u8>, Error>;
 }
 
 /// Implement `ToBytes` for the given models that implement `serde::Serialize`.
 ///
 /// # Notes
-/// This macro uses bincode crate to serialize the data.
+/// The data is serialized with the [`Codec`] of the cache strategy.
 #[macro_export]
 macro_rules! __impl_to_bytes_for_model {
     ($($model:ty),*) => {
         $(
             impl $crate::cache::ToBytes for $model {
-                fn to_bytes(&self) -> Result<Vec<u8>, $crate::Error> {
-                    Ok(::serde_json::to_vec(self)?)
+                fn to_bytes<C: $crate::codec::Codec>(&self) -> Result<Vec<u8>, $crate::Error> {
+                    C::encode(self)
                 }
             }
         )*
@@ -179,7 +179,7 @@ macro_rules! __impl_to_bytes_for_model {
 pub use __impl_to_bytes_for_model as impl_to_bytes_for_model;
 
 impl<M> ToBytes for Id<M> {
-    fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+    fn to_bytes<C: Codec>(&self) -> Result<Vec<u8>, Error> {
         Ok(self.get().to_redis_args().remove(0))
     }
 }
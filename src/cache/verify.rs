@@ -0,0 +1,362 @@
+use std::sync::OnceLock;
+
+use redis::Script;
+
+use super::{sweep::INDEXES, RedisKey};
+use crate::{config::ResourceType, CacheStrategy, Connection, Error, RedisCache};
+
+/// Number of value keys checked in a single round trip.
+const BATCH_SIZE: usize = 1000;
+
+/// Names of the value keys checked for an index entry pointing at them, along
+/// with their resource type.
+///
+/// Messages are not checked, as their value does not tell their channel.
+const VALUES: &[(ResourceType, &str)] = &[
+    (ResourceType::CHANNEL, "CHANNEL"),
+    (ResourceType::EMOJI, "EMOJI"),
+    (ResourceType::GUILD, "GUILD"),
+    (ResourceType::INTEGRATION, "INTEGRATION"),
+    (ResourceType::MEMBER, "MEMBER"),
+    (ResourceType::PRESENCE, "PRESENCE"),
+    (ResourceType::ROLE, "ROLE"),
+    (ResourceType::STAGE_INSTANCE, "STAGE_INSTANCE"),
+    (ResourceType::STICKER, "STICKER"),
+    (ResourceType::THREAD_MEMBER, "THREAD_MEMBER"),
+    (ResourceType::USER, "USER"),
+    (ResourceType::VOICE_STATE, "VOICE_STATE"),
+];
+
+/// Inconsistencies between the index sets and the values of the cache, found
+/// by [`RedisCache::verify`] or fixed by [`RedisCache::repair`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct VerifyReport {
+    /// Entries of an index whose value does not exist, as the index and the id.
+    pub dangling_ids: Vec<(RedisKey, u64)>,
+    /// Values which no index points at.
+    pub orphan_keys: Vec<RedisKey>,
+}
+
+impl VerifyReport {
+    /// Returns whether no inconsistency was found.
+    pub fn is_consistent(&self) -> bool {
+        self.dangling_ids.is_empty() && self.orphan_keys.is_empty()
+    }
+}
+
+impl<S: CacheStrategy> RedisCache<S> {
+    /// Check that every id in an index set or message list has a value, and
+    /// that every value has an id pointing at it.
+    ///
+    /// Only the resource types enabled in the configuration are checked. Every
+    /// index and value is read, so this is meant to be run occasionally, for
+    /// example after a failure with [`Config::atomic`] off.
+    ///
+    /// The message lists and pins of the channels are checked for messages
+    /// which no longer exist, but messages are not checked for an entry in
+    /// them, as the value of a message does not tell its channel.
+    ///
+    /// [`Config::atomic`]: crate::Config::atomic
+    pub async fn verify(&self) -> Result<VerifyReport, Error> {
+        self.check(false).await
+    }
+
+    /// Check the cache like [`verify`] and fix what was found, returning the
+    /// fixed inconsistencies.
+    ///
+    /// Dangling ids are removed from their index, and orphan values are
+    /// deleted, as the indexes tell what the cache holds. Both are checked
+    /// again right before being removed, so entries and values written by
+    /// other clients in the meantime are kept.
+    ///
+    /// [`verify`]: Self::verify
+    pub async fn repair(&self) -> Result<VerifyReport, Error> {
+        self.check(true).await
+    }
+
+    async fn check(&self, repair: bool) -> Result<VerifyReport, Error> {
+        let mut conn = self.get_connection().await?;
+        let mut report = VerifyReport::default();
+
+        for (resource_type, index, value_key) in INDEXES {
+            if !self.wants(*resource_type) {
+                continue;
+            }
+
+            let dangling = self
+                .sweep_index(&mut conn, index, *value_key, repair)
+                .await?;
+
+            if repair && *resource_type == ResourceType::VOICE_STATE && !dangling.is_empty() {
+                let expired: Vec<(u64, u64)> = dangling
+                    .iter()
+                    .map(|(_, parent_id, id)| (*parent_id, *id))
+                    .collect();
+                self.sweep_channel_voice_states(&mut conn, &expired).await?;
+            }
+
+            report.dangling_ids.extend(
+                dangling
+                    .into_iter()
+                    .filter_map(|(key, _, id)| Some((self.parse_key(&key)?, id))),
+            );
+        }
+
+        for (resource_type, name) in VALUES {
+            if !self.wants(*resource_type) {
+                continue;
+            }
+
+            let keys = self.scan_keys(&mut conn, name).await?;
+            let mut orphans = Vec::new();
+            for keys in keys.chunks(BATCH_SIZE) {
+                let keys: Vec<RedisKey> =
+                    keys.iter().filter_map(|key| self.parse_key(key)).collect();
+                orphans.extend(self.find_orphans(&mut conn, &keys).await?);
+            }
+
+            // Orphans are deleted once every value of the name was checked, so
+            // that a value written before its index entry has its entry by then.
+            for orphans in orphans.chunks(BATCH_SIZE) {
+                if repair {
+                    let deleted = self.delete_orphans(&mut conn, orphans).await?;
+                    report.orphan_keys.extend(deleted);
+                } else {
+                    report
+                        .orphan_keys
+                        .extend(orphans.iter().map(|(key, _)| *key));
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Find the values among `keys` which no index points at, along with the
+    /// indexes which could point at them.
+    #[allow(clippy::type_complexity)]
+    async fn find_orphans(
+        &self,
+        conn: &mut Connection<'_>,
+        keys: &[RedisKey],
+    ) -> Result<Vec<(RedisKey, Vec<(RedisKey, u64)>)>, Error> {
+        let mut pipe = self.pipe();
+        let mut checked = Vec::with_capacity(keys.len());
+        for key in keys {
+            let indexes = match *key {
+                RedisKey::Channel {
+                    guild_id: Some(guild_id),
+                    channel_id,
+                } => vec![(RedisKey::GuildChannels { guild_id }, channel_id.get())],
+                RedisKey::Emoji { guild_id, emoji_id } => {
+                    vec![(RedisKey::GuildEmojis { guild_id }, emoji_id.get())]
+                }
+                RedisKey::Role { guild_id, role_id } => {
+                    vec![(RedisKey::GuildRoles { guild_id }, role_id.get())]
+                }
+                RedisKey::StageInstance { guild_id, stage_id } => {
+                    vec![(RedisKey::GuildStageInstances { guild_id }, stage_id.get())]
+                }
+                RedisKey::Sticker {
+                    guild_id,
+                    sticker_id,
+                } => vec![(RedisKey::GuildStickers { guild_id }, sticker_id.get())],
+                RedisKey::Guild { id } => vec![
+                    (RedisKey::Guilds, id.get()),
+                    (RedisKey::UnavailableGuilds, id.get()),
+                ],
+                RedisKey::Integration {
+                    guild_id,
+                    integration_id,
+                } => vec![(
+                    RedisKey::GuildIntegrations { guild_id },
+                    integration_id.get(),
+                )],
+                RedisKey::Member { guild_id, user_id } => {
+                    vec![(RedisKey::GuildMembers { guild_id }, user_id.get())]
+                }
+                RedisKey::Presence { guild_id, user_id } => {
+                    vec![(RedisKey::GuildPresences { guild_id }, user_id.get())]
+                }
+                RedisKey::VoiceState { guild_id, user_id } => {
+                    vec![(RedisKey::GuildVoiceStates { guild_id }, user_id.get())]
+                }
+                RedisKey::ThreadMember { thread_id, user_id } => {
+                    vec![(RedisKey::ThreadMembers { thread_id }, user_id.get())]
+                }
+                RedisKey::User { id } => vec![(RedisKey::Users, id.get())],
+                // Channels outside of guilds.
+                _ => Vec::new(),
+            };
+
+            for (index, id) in &indexes {
+                pipe.pipeline.sismember(pipe.key(*index), *id);
+            }
+            checked.push((*key, indexes));
+        }
+
+        if pipe.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let indexed: Vec<bool> = pipe.query(conn).await?;
+        let mut offset = 0;
+
+        Ok(checked
+            .into_iter()
+            .filter(|(_, indexes)| {
+                let len = indexes.len();
+                let found = &indexed[offset..offset + len];
+                offset += len;
+
+                len != 0 && !found.contains(&true)
+            })
+            .collect())
+    }
+
+    /// Delete the orphans which are still not indexed, checking and deleting
+    /// them by a script in a single step. Returns the deleted values.
+    ///
+    /// A script may only use keys of a single slot on a Redis Cluster, so there
+    /// each slot gets a script of its own, and an orphan indexed in another
+    /// slot than its value, such as a guild of `GUILDS`, is checked first and
+    /// deleted afterwards instead.
+    #[allow(clippy::type_complexity)]
+    async fn delete_orphans(
+        &self,
+        conn: &mut Connection<'_>,
+        orphans: &[(RedisKey, Vec<(RedisKey, u64)>)],
+    ) -> Result<Vec<RedisKey>, Error> {
+        let mut groups: Vec<(Option<u16>, Vec<&(RedisKey, Vec<(RedisKey, u64)>)>)> = Vec::new();
+        let mut checked = Vec::new();
+        for orphan in orphans {
+            let (key, indexes) = orphan;
+            let slot = conn.slot(&self.key(*key));
+
+            if indexes
+                .iter()
+                .any(|(index, _)| conn.slot(&self.key(*index)) != slot)
+            {
+                checked.push(*key);
+            } else if let Some((_, group)) = groups.iter_mut().find(|(other, _)| *other == slot) {
+                group.push(orphan);
+            } else {
+                groups.push((slot, vec![orphan]));
+            }
+        }
+
+        let mut deleted = Vec::new();
+        for (_, group) in groups {
+            static SCRIPT: OnceLock<Script> = OnceLock::new();
+            let script = SCRIPT.get_or_init(|| Script::new(include_str!("repair.lua")));
+
+            let mut invocation = script.prepare_invoke();
+            for (key, indexes) in &group {
+                invocation.key(self.key(*key)).arg(indexes.len());
+                for (index, id) in indexes {
+                    invocation.key(self.key(*index)).arg(*id);
+                }
+            }
+            let positions: Vec<usize> = invocation.invoke_async(conn).await?;
+            deleted.extend(
+                positions
+                    .into_iter()
+                    .filter_map(|position| Some(group.get(position.checked_sub(1)?)?.0)),
+            );
+        }
+
+        if !checked.is_empty() {
+            let orphans = self.find_orphans(conn, &checked).await?;
+
+            if !orphans.is_empty() {
+                let mut pipe = self.pipe();
+                for (key, _) in &orphans {
+                    pipe.pipeline.del(pipe.key(*key));
+                }
+                let _: redis::Value = pipe.query(conn).await?;
+
+                deleted.extend(orphans.into_iter().map(|(key, _)| key));
+            }
+        }
+
+        let voice_states: Vec<(u64, u64)> = deleted
+            .iter()
+            .filter_map(|key| match key {
+                RedisKey::VoiceState { guild_id, user_id } => Some((guild_id.get(), user_id.get())),
+                _ => None,
+            })
+            .collect();
+        if !voice_states.is_empty() {
+            self.sweep_channel_voice_states(conn, &voice_states).await?;
+        }
+
+        Ok(deleted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use redis::AsyncCommands;
+    use twilight_model::{gateway::payload::incoming::GuildCreate, id::Id};
+
+    use crate::{cache::RedisKey, test, ConfigBuilder};
+
+    #[test]
+    fn test_repair() {
+        test::block_on(async {
+            let mut cache =
+                test::redis_cache_with_config(ConfigBuilder::default().namespace("verify").build())
+                    .await;
+            let guild_id = Id::new(931);
+            let user_id = Id::new(932);
+            let role_id = Id::new(933);
+
+            let mut guild = test::model::guild(guild_id);
+            guild.members = vec![test::model::member(user_id)];
+            guild.roles = vec![test::model::role(role_id)];
+            cache.update(GuildCreate(guild)).await.unwrap();
+
+            let mut conn = cache.get_connection().await.unwrap();
+            let _: () = conn
+                .sadd(cache.key(RedisKey::GuildMembers { guild_id }), 934)
+                .await
+                .unwrap();
+            let _: () = conn
+                .srem(cache.key(RedisKey::GuildRoles { guild_id }), role_id.get())
+                .await
+                .unwrap();
+
+            let channel_id = Id::new(936);
+            let _: () = conn
+                .sadd(cache.key(RedisKey::ChannelPins { channel_id }), 935)
+                .await
+                .unwrap();
+
+            let report = cache.verify().await.unwrap();
+            assert!(report
+                .dangling_ids
+                .contains(&(RedisKey::ChannelPins { channel_id }, 935)));
+            assert!(report
+                .dangling_ids
+                .contains(&(RedisKey::GuildMembers { guild_id }, 934)));
+            assert!(report
+                .orphan_keys
+                .contains(&RedisKey::Role { guild_id, role_id }));
+
+            let repaired = cache.repair().await.unwrap();
+            assert_eq!(repaired, report);
+
+            assert!(cache.verify().await.unwrap().is_consistent());
+            assert!(cache
+                .get_role(&mut conn, guild_id, role_id)
+                .await
+                .unwrap()
+                .is_none());
+            assert!(cache
+                .get_member(&mut conn, guild_id, user_id)
+                .await
+                .unwrap()
+                .is_some());
+        });
+    }
+}
@@ -0,0 +1,204 @@
+//! Running pipelines and scans on a Redis Cluster.
+//!
+//! A pipeline sent to a cluster, and a `MULTI` transaction in particular, may
+//! only use keys of a single slot. Pipelines of the cache touch keys of several
+//! slots, for example a member and its user, so they are split into one
+//! pipeline per slot. Keys of the same guild share a hash tag (see
+//! [`RedisKey`]), which keeps the data of a guild and its index sets in the
+//! same transaction.
+//!
+//! `SCAN` only lists the keys of the node it runs on, so it is sent to every
+//! master.
+//!
+//! [`RedisKey`]: crate::cache::RedisKey
+
+use redis::{
+    aio::ConnectionLike,
+    cluster_async::ClusterConnection,
+    cluster_routing::{get_slot, MultipleNodeRoutingInfo, RoutingInfo, SingleNodeRoutingInfo},
+    Arg, Cmd, ErrorKind, Pipeline, RedisError, RedisResult, Value,
+};
+
+/// Find the slot of the keys of a command, `None` if it has no key or keys in
+/// several slots.
+pub(crate) fn slot(cmd: &Cmd) -> Option<u16> {
+    let Some(RoutingInfo::SingleNode(SingleNodeRoutingInfo::SpecificNode(_))) =
+        RoutingInfo::for_routable(cmd)
+    else {
+        return None;
+    };
+
+    let mut args = cmd.args_iter().filter_map(|arg| match arg {
+        Arg::Simple(arg) => Some(arg),
+        Arg::Cursor => None,
+    });
+    let name = args.next()?;
+
+    let key = if name.eq_ignore_ascii_case(b"EVAL") || name.eq_ignore_ascii_case(b"EVALSHA") {
+        args.nth(2)?
+    } else {
+        args.next()?
+    };
+
+    Some(get_slot(key))
+}
+
+/// Group the indices of the commands by the slot of their keys, keeping the
+/// order of the commands. Commands without a single slot get a group of their
+/// own.
+pub(crate) fn groups<'a>(
+    cmds: impl IntoIterator<Item = &'a Cmd>,
+) -> Vec<(Option<u16>, Vec<usize>)> {
+    let mut groups: Vec<(Option<u16>, Vec<usize>)> = Vec::new();
+
+    for (index, cmd) in cmds.into_iter().enumerate() {
+        let slot = slot(cmd);
+
+        match groups
+            .iter_mut()
+            .find(|(group_slot, _)| slot.is_some() && *group_slot == slot)
+        {
+            Some((_, indices)) => indices.push(index),
+            None => groups.push((slot, vec![index])),
+        }
+    }
+
+    groups
+}
+
+/// Send a pipeline to the cluster, splitting it by slot if needed.
+///
+/// A transaction is sent by the pipeline as `MULTI`, the commands and `EXEC`,
+/// of which only the reply of `EXEC` is read, so `offset` is not zero for it.
+/// Each slot then gets a transaction of its own.
+pub(crate) async fn req_packed_commands(
+    conn: &mut ClusterConnection,
+    pipeline: &Pipeline,
+    offset: usize,
+    count: usize,
+) -> RedisResult<Vec<Value>> {
+    let cmds: Vec<&Cmd> = pipeline.cmd_iter().collect();
+    let groups = groups(cmds.iter().copied());
+
+    if let [(Some(_), _)] = groups[..] {
+        return conn.req_packed_commands(pipeline, offset, count).await;
+    }
+
+    let atomic = offset > 0;
+    let mut values = vec![Value::Nil; cmds.len()];
+
+    for (slot, indices) in groups {
+        if slot.is_none() {
+            // The cluster connection splits a command with keys in several
+            // slots by itself, but not inside a pipeline.
+            values[indices[0]] = conn.req_packed_command(cmds[indices[0]]).await?;
+            continue;
+        }
+
+        let mut group = Pipeline::with_capacity(indices.len());
+        for index in &indices {
+            group.add_command(cmds[*index].clone());
+        }
+        if atomic {
+            group.atomic();
+        }
+
+        let replies: Vec<Value> = group.query_async(conn).await?;
+        for (index, reply) in indices.into_iter().zip(replies) {
+            values[index] = reply;
+        }
+    }
+
+    Ok(if atomic {
+        vec![Value::Array(values)]
+    } else {
+        values
+    })
+}
+
+/// Find the host and port of every master of the cluster, as known by the
+/// connection.
+pub(crate) async fn masters(conn: &mut ClusterConnection) -> RedisResult<Vec<(String, u16)>> {
+    // Without a response policy, the reply maps the address of each node to
+    // its own reply.
+    let routing = RoutingInfo::MultiNode((MultipleNodeRoutingInfo::AllMasters, None));
+    let Value::Map(replies) = conn.route_command(&redis::cmd("PING"), routing).await? else {
+        return Err((ErrorKind::TypeError, "Expected a reply per master").into());
+    };
+
+    replies
+        .into_iter()
+        .map(|(address, _)| {
+            let address: String = redis::from_redis_value(&address)?;
+
+            parse_address(&address).ok_or_else(|| {
+                RedisError::from((ErrorKind::TypeError, "Invalid node address", address))
+            })
+        })
+        .collect()
+}
+
+/// Split an address such as `127.0.0.1:7000` into its host and port.
+fn parse_address(address: &str) -> Option<(String, u16)> {
+    let (host, port) = address.rsplit_once(':')?;
+
+    Some((host.to_owned(), port.parse().ok()?))
+}
+
+/// Send a command to the node with the given host and port.
+pub(crate) async fn route_to_node(
+    conn: &mut ClusterConnection,
+    cmd: &Cmd,
+    (host, port): &(String, u16),
+) -> RedisResult<Value> {
+    let routing = RoutingInfo::SingleNode(SingleNodeRoutingInfo::ByAddress {
+        host: host.clone(),
+        port: *port,
+    });
+
+    conn.route_command(cmd, routing).await
+}
+
+#[cfg(test)]
+mod tests {
+    use redis::Cmd;
+    use twilight_model::id::Id;
+
+    use super::{groups, parse_address};
+    use crate::cache::RedisKey;
+
+    #[test]
+    fn test_groups() {
+        let guild_id = Id::new(1);
+        let user_id = Id::new(2);
+
+        let cmds = [
+            Cmd::set(RedisKey::Member { guild_id, user_id }, 0),
+            Cmd::set(RedisKey::User { id: user_id }, 0),
+            Cmd::sadd(RedisKey::GuildMembers { guild_id }, user_id.get()),
+            Cmd::sadd(RedisKey::Users, user_id.get()),
+            Cmd::del(&[
+                RedisKey::User { id: user_id },
+                RedisKey::Guild { id: guild_id },
+            ]),
+            Cmd::get(RedisKey::Guild { id: guild_id }),
+        ];
+        let groups = groups(&cmds);
+
+        assert_eq!(groups.len(), 4);
+        assert_eq!(groups[0].1, [0, 2, 5]);
+        assert_eq!(groups[1].1, [1]);
+        assert_eq!(groups[2].1, [3]);
+        assert_eq!((groups[3].0, &groups[3].1[..]), (None, &[4][..]));
+    }
+
+    #[test]
+    fn test_parse_address() {
+        assert_eq!(
+            parse_address("127.0.0.1:7000"),
+            Some(("127.0.0.1".to_owned(), 7000))
+        );
+        assert_eq!(parse_address("::1:7001"), Some(("::1".to_owned(), 7001)));
+        assert_eq!(parse_address("redis-0"), None);
+    }
+}
@@ -0,0 +1,151 @@
+//! Formats the cached models are stored in.
+//!
+//! The codec is chosen per [`CacheStrategy`] through [`CacheStrategy::Codec`].
+//! [`Json`] is always available, the others are enabled with cargo features:
+//!
+//! | Codec           | Feature   |
+//! |-----------------|-----------|
+//! | [`Json`]        |           |
+//! | `MessagePack`   | `msgpack` |
+//! | `Cbor`          | `cbor`    |
+//!
+//! Formats which are not self-describing, such as bincode and postcard, are not
+//! provided: twilight's IDs, timestamps and image hashes are deserialized with
+//! `deserialize_any`, which those formats do not support.
+//!
+//! [`CacheStrategy`]: crate::CacheStrategy
+//! [`CacheStrategy::Codec`]: crate::CacheStrategy::Codec
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::Error;
+
+/// Serializes cached models to the bytes stored in Redis and back.
+pub trait Codec: Send + Sync + 'static {
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Error>;
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Error>;
+}
+
+/// Stores models as JSON with `serde_json`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Json;
+
+impl Codec for Json {
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Error> {
+        Ok(serde_json::to_vec(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Error> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+/// Stores models as MessagePack with `rmp-serde`.
+///
+/// Structs are written as maps, so that fields skipped when serializing are
+/// still read back correctly.
+#[cfg(feature = "msgpack")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MessagePack;
+
+#[cfg(feature = "msgpack")]
+impl Codec for MessagePack {
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Error> {
+        rmp_serde::to_vec_named(value).map_err(|error| Error::Codec(error.into()))
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Error> {
+        rmp_serde::from_slice(bytes).map_err(|error| Error::Codec(error.into()))
+    }
+}
+
+/// Stores models as CBOR with `ciborium`.
+#[cfg(feature = "cbor")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Cbor;
+
+#[cfg(feature = "cbor")]
+impl Codec for Cbor {
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Error> {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(value, &mut bytes).map_err(|error| Error::Codec(error.into()))?;
+        Ok(bytes)
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Error> {
+        ciborium::from_reader(bytes).map_err(|error| Error::Codec(error.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Debug;
+
+    use twilight_model::id::Id;
+
+    use super::*;
+    use crate::{
+        cache::{FromBytes, ToBytes},
+        model::*,
+        test,
+    };
+
+    fn round_trip<C: Codec, T: ToBytes + FromBytes + PartialEq + Debug>(value: T) {
+        let bytes = value.to_bytes::<C>().unwrap();
+        assert_eq!(T::from_bytes::<C>(&bytes).unwrap(), value);
+    }
+
+    fn round_trip_models<C: Codec>() {
+        let guild_id = Id::new(1);
+        let channel_id = Id::new(2);
+        let user_id = Id::new(3);
+
+        round_trip::<C, _>(CachedChannelVoiceState::from((guild_id, user_id)));
+        round_trip::<C, _>(CachedEmoji::from(test::model::emoji(
+            Id::new(4),
+            Some(user_id),
+        )));
+        round_trip::<C, _>(CachedGuild::from(test::model::guild(guild_id)));
+        round_trip::<C, _>(CachedMember::from(test::model::member(user_id)));
+        round_trip::<C, _>(CachedMessage::from(test::model::message(
+            Id::new(5),
+            channel_id,
+            user_id,
+        )));
+        round_trip::<C, _>(CachedPresence::from(test::model::presence(
+            guild_id, user_id,
+        )));
+        round_trip::<C, _>(CachedSticker::from(test::model::sticker(
+            Id::new(6),
+            guild_id,
+        )));
+        round_trip::<C, _>(CachedThreadMember::from((
+            channel_id,
+            user_id,
+            test::model::thread_member(channel_id, user_id),
+        )));
+        round_trip::<C, _>(CachedVoiceState::from((
+            guild_id,
+            channel_id,
+            test::model::voice_state(guild_id, channel_id, user_id),
+        )));
+    }
+
+    #[test]
+    fn test_json() {
+        round_trip_models::<Json>();
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn test_message_pack() {
+        round_trip_models::<MessagePack>();
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn test_cbor() {
+        round_trip_models::<Cbor>();
+    }
+}
@@ -1,3 +1,5 @@
+use std::{collections::HashMap, num::NonZeroUsize, time::Duration};
+
 use bitflags::bitflags;
 
 bitflags! {
@@ -37,6 +39,10 @@ bitflags! {
         const INTEGRATION = 1 << 12;
         /// Information relating to guild stickers.
         const STICKER = 1 << 13;
+        /// Information relating to shard sessions and the shard of each guild.
+        const SHARD = 1 << 14;
+        /// Information relating to thread members.
+        const THREAD_MEMBER = 1 << 15;
     }
 }
 
@@ -47,7 +53,12 @@ bitflags! {
 pub struct Config {
     pub(super) resource_type: ResourceType,
     pub(super) atomic: bool,
+    pub(super) max_update_attempts: NonZeroUsize,
     pub(super) message_cache_size: usize,
+    pub(super) namespace: Option<String>,
+    pub(super) notification_channel: Option<String>,
+    pub(super) event_log: Option<usize>,
+    pub(super) ttl: HashMap<ResourceType, Duration>,
 }
 
 impl Config {
@@ -73,6 +84,69 @@ impl Config {
         &mut self.atomic
     }
 
+    /// Returns how many times an event changing a value is applied before giving
+    /// up, when other clients keep changing the value at the same time.
+    ///
+    /// Defaults to 32.
+    pub const fn max_update_attempts(&self) -> NonZeroUsize {
+        self.max_update_attempts
+    }
+
+    /// Returns a mutable reference to the maximum number of update attempts.
+    pub fn max_update_attempts_mut(&mut self) -> &mut NonZeroUsize {
+        &mut self.max_update_attempts
+    }
+
+    /// Returns the namespace put in front of every key the cache reads or writes.
+    ///
+    /// Defaults to no namespace.
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    /// Returns a mutable reference to the namespace.
+    pub fn namespace_mut(&mut self) -> &mut Option<String> {
+        &mut self.namespace
+    }
+
+    /// Returns the pub/sub channel on which the changes made by each update are
+    /// published.
+    ///
+    /// Defaults to no channel, publishing nothing.
+    pub fn notification_channel(&self) -> Option<&str> {
+        self.notification_channel.as_deref()
+    }
+
+    /// Returns a mutable reference to the notification channel.
+    pub fn notification_channel_mut(&mut self) -> &mut Option<String> {
+        &mut self.notification_channel
+    }
+
+    /// Returns the approximate maximum length of the stream to which the
+    /// applied events are appended.
+    ///
+    /// Defaults to no event log.
+    pub const fn event_log(&self) -> Option<usize> {
+        self.event_log
+    }
+
+    /// Returns a mutable reference to the maximum length of the event log.
+    pub fn event_log_mut(&mut self) -> &mut Option<usize> {
+        &mut self.event_log
+    }
+
+    /// Returns the time after which cached values of the resource type expire.
+    ///
+    /// Defaults to no expiry for every resource type.
+    pub fn ttl(&self, resource_type: ResourceType) -> Option<Duration> {
+        self.ttl.get(&resource_type).copied()
+    }
+
+    /// Returns a mutable reference to the expiry of each resource type.
+    pub fn ttl_mut(&mut self) -> &mut HashMap<ResourceType, Duration> {
+        &mut self.ttl
+    }
+
     /// Returns an immutable reference to the resource types enabled.
     ///
     /// Defaults to all resource types.
@@ -91,7 +165,12 @@ impl Default for Config {
         Self {
             resource_type: ResourceType::all(),
             atomic: true,
+            max_update_attempts: NonZeroUsize::new(32).unwrap(),
             message_cache_size: 100,
+            namespace: None,
+            notification_channel: None,
+            event_log: None,
+            ttl: HashMap::new(),
         }
     }
 }
@@ -117,6 +196,59 @@ impl ConfigBuilder {
         self
     }
 
+    /// Set how many times an event changing a value is applied before giving
+    /// up with [`Error::Conflict`].
+    ///
+    /// [`Error::Conflict`]: crate::Error::Conflict
+    pub fn max_update_attempts(mut self, max_update_attempts: NonZeroUsize) -> Self {
+        self.value.max_update_attempts = max_update_attempts;
+        self
+    }
+
+    /// Set the namespace put in front of every key, so that several caches can
+    /// share one Redis database.
+    pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.value.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Publish the changes made by each update on the pub/sub channel, to be
+    /// read by a [`ChangeSubscriber`].
+    ///
+    /// [`ChangeSubscriber`]: crate::cache::ChangeSubscriber
+    pub fn notification_channel(mut self, channel: impl Into<String>) -> Self {
+        self.value.notification_channel = Some(channel.into());
+        self
+    }
+
+    /// Append every event applied to the cache to a Redis Stream, trimmed to
+    /// about `max_len` entries.
+    ///
+    /// Each entry holds the `type` of the event, such as `GUILD_CREATE`, and
+    /// its `payload` serialized with the codec of the cache. The entry is
+    /// written in the same pipe as the changes of the event, so it is committed
+    /// together with them when [`atomic`] is on.
+    ///
+    /// [`atomic`]: Self::atomic
+    pub fn event_log(mut self, max_len: usize) -> Self {
+        self.value.event_log = Some(max_len);
+        self
+    }
+
+    /// Set the time after which cached values of the given resource types expire.
+    ///
+    /// The index sets pointing at expired values are cleaned up by
+    /// [`RedisCache::sweep`]. Durations under a millisecond, including zero,
+    /// are rounded up to a millisecond, the smallest expiry Redis accepts.
+    ///
+    /// [`RedisCache::sweep`]: crate::RedisCache::sweep
+    pub fn ttl(mut self, resource_type: ResourceType, ttl: Duration) -> Self {
+        for resource_type in resource_type.iter() {
+            self.value.ttl.insert(resource_type, ttl);
+        }
+        self
+    }
+
     pub fn build(self) -> Config {
         self.value
     }
@@ -1,23 +1,46 @@
-use std::marker::PhantomData;
+use std::{
+    marker::PhantomData,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
 
 #[cfg(feature = "bb8")]
 use bb8_redis::bb8::{Pool, PooledConnection};
-use redis::aio::MultiplexedConnection;
+#[cfg(feature = "cluster")]
+use redis::cluster_async::ClusterConnection;
+use redis::{aio::MultiplexedConnection, Client, ErrorKind, RedisError, RedisResult};
+
+use tokio::sync::Mutex;
 
 use crate::Error;
 
 pub enum Connection<'a> {
     MultiplexedConnection(MultiplexedConnection),
+    Reconnecting(ReconnectingConnection<'a>),
     #[cfg(feature = "bb8")]
     BB8PooledConnection(PooledConnection<'a, bb8_redis::RedisConnectionManager>),
+    #[cfg(feature = "cluster")]
+    ClusterConnection(ClusterConnection),
     #[allow(missing_docs)]
     _RedisCacheReference(PhantomData<&'a ()>),
 }
 
 pub enum ConnectionDriver {
     MultiplexedClone(MultiplexedConnection),
+    /// Connection which is opened again when it is lost, retrying the failed
+    /// commands with a backoff.
+    Reconnecting(ReconnectingClient),
     #[cfg(feature = "bb8")]
     BB8(Pool<bb8_redis::RedisConnectionManager>),
+    /// Connection to a Redis Cluster, which is cloned like `MultiplexedClone`.
+    ///
+    /// The data of a guild shares a slot, as its keys are tagged with the
+    /// guild id. Pipelines are split by the slot of their keys, so that each
+    /// part is still sent as one transaction: an update is atomic for the keys
+    /// of each slot, but not across slots, such as a member and its user.
+    /// `SCAN` is sent to every master.
+    #[cfg(feature = "cluster")]
+    ClusterClone(ClusterConnection),
 }
 
 impl ConnectionDriver {
@@ -26,10 +49,207 @@ impl ConnectionDriver {
             ConnectionDriver::MultiplexedClone(conn) => {
                 Connection::MultiplexedConnection(conn.clone())
             }
+            ConnectionDriver::Reconnecting(client) => {
+                Connection::Reconnecting(ReconnectingConnection(client))
+            }
             #[cfg(feature = "bb8")]
             ConnectionDriver::BB8(pool) => Connection::BB8PooledConnection(pool.get().await?),
+            #[cfg(feature = "cluster")]
+            ConnectionDriver::ClusterClone(conn) => Connection::ClusterConnection(conn.clone()),
         })
     }
+
+    /// Returns whether the last command sent through the driver reached Redis,
+    /// `None` if the driver does not keep track of it.
+    pub fn is_connected(&self) -> Option<bool> {
+        match self {
+            ConnectionDriver::Reconnecting(client) => Some(client.is_connected()),
+            _ => None,
+        }
+    }
+}
+
+/// How the commands failing for a transient reason are retried.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Backoff {
+    /// Number of retries after the first attempt.
+    pub max_retries: usize,
+    /// Delay before the first retry, doubled for each of the next ones.
+    pub min_delay: Duration,
+    /// Longest delay between two retries.
+    pub max_delay: Duration,
+}
+
+impl Backoff {
+    fn delay(&self, retry: usize) -> Duration {
+        let factor = 1_u32.checked_shl(retry as u32).unwrap_or(u32::MAX);
+        self.min_delay.saturating_mul(factor).min(self.max_delay)
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            min_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Driver opening a multiplexed connection from a client, and opening it again
+/// when it is lost, for example when Redis restarts.
+///
+/// Commands failing because of the connection, or because Redis is still
+/// loading its data, are retried following the [`Backoff`]. As a command may
+/// have run before its connection was lost, a retried command can run twice.
+///
+/// The backoff sleeps with the Tokio timer, which must be enabled in the
+/// runtime.
+pub struct ReconnectingClient {
+    client: Client,
+    backoff: Backoff,
+    /// Current connection, along with a generation counting the connections
+    /// opened so far.
+    connection: Mutex<(u64, Option<MultiplexedConnection>)>,
+    connected: AtomicBool,
+}
+
+impl ReconnectingClient {
+    /// Create a driver for the client, connecting on the first command.
+    pub fn new(client: Client, backoff: Backoff) -> Self {
+        Self {
+            client,
+            backoff,
+            connection: Mutex::new((0, None)),
+            connected: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns how the failed commands are retried.
+    pub const fn backoff(&self) -> Backoff {
+        self.backoff
+    }
+
+    /// Returns whether the last command reached Redis.
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    /// Get the current connection along with its generation, opening a new one
+    /// if there is none.
+    async fn connection(&self) -> RedisResult<(u64, MultiplexedConnection)> {
+        let mut connection = self.connection.lock().await;
+        let (generation, conn) = &mut *connection;
+        if let Some(conn) = conn {
+            return Ok((*generation, conn.clone()));
+        }
+
+        let new = self.client.get_multiplexed_tokio_connection().await?;
+        *generation += 1;
+        *conn = Some(new.clone());
+
+        Ok((*generation, new))
+    }
+
+    /// Drop the connection of the generation, unless another task already
+    /// replaced it.
+    async fn invalidate(&self, generation: u64) {
+        let mut connection = self.connection.lock().await;
+        if connection.0 == generation {
+            connection.1 = None;
+        }
+    }
+
+    async fn run<T>(
+        &self,
+        mut request: impl FnMut(MultiplexedConnection) -> redis::RedisFuture<'static, T>,
+    ) -> RedisResult<T> {
+        let mut retry = 0;
+
+        loop {
+            let (generation, result) = match self.connection().await {
+                Ok((generation, conn)) => (Some(generation), request(conn).await),
+                Err(error) => (None, Err(error)),
+            };
+
+            let error = match result {
+                Ok(value) => {
+                    self.connected.store(true, Ordering::Relaxed);
+                    return Ok(value);
+                }
+                Err(error) => error,
+            };
+
+            let lost = is_connection_error(&error);
+            if lost {
+                self.connected.store(false, Ordering::Relaxed);
+                if let Some(generation) = generation {
+                    self.invalidate(generation).await;
+                }
+            }
+
+            if !(lost || error.kind() == ErrorKind::BusyLoadingError)
+                || retry == self.backoff.max_retries
+            {
+                return Err(error);
+            }
+
+            tokio::time::sleep(self.backoff.delay(retry)).await;
+            retry += 1;
+        }
+    }
+}
+
+fn is_connection_error(error: &RedisError) -> bool {
+    error.is_io_error()
+        || error.is_connection_dropped()
+        || error.is_connection_refusal()
+        || error.is_timeout()
+}
+
+/// Connection of a [`ReconnectingClient`].
+pub struct ReconnectingConnection<'a>(&'a ReconnectingClient);
+
+impl redis::aio::ConnectionLike for ReconnectingConnection<'_> {
+    fn req_packed_command<'a>(
+        &'a mut self,
+        cmd: &'a redis::Cmd,
+    ) -> redis::RedisFuture<'a, redis::Value> {
+        Box::pin(self.0.run(move |mut conn| {
+            let cmd = cmd.clone();
+            Box::pin(async move { conn.req_packed_command(&cmd).await })
+        }))
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a redis::Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> redis::RedisFuture<'a, Vec<redis::Value>> {
+        Box::pin(self.0.run(move |mut conn| {
+            let cmd = cmd.clone();
+            Box::pin(async move { conn.req_packed_commands(&cmd, offset, count).await })
+        }))
+    }
+
+    fn get_db(&self) -> i64 {
+        self.0.client.get_connection_info().redis.db
+    }
+}
+
+impl Connection<'_> {
+    /// The slot of a key on a Redis Cluster, `None` on a single server, where
+    /// any keys may be used together in a script or transaction.
+    #[cfg_attr(not(feature = "cluster"), allow(unused_variables))]
+    pub(crate) fn slot(&self, key: &[u8]) -> Option<u16> {
+        match self {
+            #[cfg(feature = "cluster")]
+            Connection::ClusterConnection(_) => Some(redis::cluster_routing::get_slot(key)),
+            _ => None,
+        }
+    }
 }
 
 impl<'b> redis::aio::ConnectionLike for Connection<'b> {
@@ -39,8 +259,11 @@ impl<'b> redis::aio::ConnectionLike for Connection<'b> {
     ) -> redis::RedisFuture<'a, redis::Value> {
         match self {
             Connection::MultiplexedConnection(conn) => conn.req_packed_command(cmd),
+            Connection::Reconnecting(conn) => conn.req_packed_command(cmd),
             #[cfg(feature = "bb8")]
             Connection::BB8PooledConnection(conn) => conn.req_packed_command(cmd),
+            #[cfg(feature = "cluster")]
+            Connection::ClusterConnection(conn) => conn.req_packed_command(cmd),
             Connection::_RedisCacheReference(_) => unreachable!(),
         }
     }
@@ -53,8 +276,13 @@ impl<'b> redis::aio::ConnectionLike for Connection<'b> {
     ) -> redis::RedisFuture<'a, Vec<redis::Value>> {
         let future = match self {
             Connection::MultiplexedConnection(conn) => conn.req_packed_commands(cmd, offset, count),
+            Connection::Reconnecting(conn) => conn.req_packed_commands(cmd, offset, count),
             #[cfg(feature = "bb8")]
             Connection::BB8PooledConnection(conn) => conn.req_packed_commands(cmd, offset, count),
+            #[cfg(feature = "cluster")]
+            Connection::ClusterConnection(conn) => Box::pin(crate::cluster::req_packed_commands(
+                conn, cmd, offset, count,
+            )),
             Connection::_RedisCacheReference(_) => unreachable!(),
         };
         Box::pin(future)
@@ -63,9 +291,132 @@ impl<'b> redis::aio::ConnectionLike for Connection<'b> {
     fn get_db(&self) -> i64 {
         match self {
             Connection::MultiplexedConnection(conn) => conn.get_db(),
+            Connection::Reconnecting(conn) => conn.get_db(),
             #[cfg(feature = "bb8")]
             Connection::BB8PooledConnection(conn) => conn.get_db(),
+            #[cfg(feature = "cluster")]
+            Connection::ClusterConnection(conn) => conn.get_db(),
             Connection::_RedisCacheReference(_) => unreachable!(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use redis::Client;
+    use twilight_model::{gateway::payload::incoming::RoleCreate, id::Id};
+
+    use super::{Backoff, ConnectionDriver, ReconnectingClient};
+    use crate::{test, Config, DefaultCacheStrategy, RedisCache};
+
+    fn backoff() -> Backoff {
+        Backoff {
+            max_retries: 2,
+            min_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(2),
+        }
+    }
+
+    #[test]
+    fn test_backoff_delay() {
+        let backoff = Backoff::default();
+
+        assert_eq!(backoff.delay(0), Duration::from_millis(100));
+        assert_eq!(backoff.delay(2), Duration::from_millis(400));
+        assert_eq!(backoff.delay(10), Duration::from_secs(5));
+        assert_eq!(backoff.delay(100), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_reconnecting() {
+        test::block_on(async {
+            let url = option_env!("TEST_REDIS_URL").unwrap_or("redis://127.0.0.1");
+            let client = ReconnectingClient::new(Client::open(url).unwrap(), backoff());
+            let mut cache: RedisCache<DefaultCacheStrategy> = RedisCache::new(
+                ConnectionDriver::Reconnecting(client),
+                None,
+                Config::default(),
+            );
+            assert_eq!(cache.connection_driver().is_connected(), Some(false));
+
+            let guild_id = Id::new(951);
+            let role_id = Id::new(952);
+            cache
+                .update(RoleCreate {
+                    guild_id,
+                    role: test::model::role(role_id),
+                })
+                .await
+                .unwrap();
+
+            let mut conn = cache.get_connection().await.unwrap();
+            assert!(cache
+                .get_role(&mut conn, guild_id, role_id)
+                .await
+                .unwrap()
+                .is_some());
+            cache.ping().await.unwrap();
+            assert_eq!(cache.connection_driver().is_connected(), Some(true));
+        });
+    }
+
+    #[test]
+    fn test_reconnecting_refused() {
+        test::block_on(async {
+            // Nothing listens on the discard port.
+            let client =
+                ReconnectingClient::new(Client::open("redis://127.0.0.1:9").unwrap(), backoff());
+            let cache: RedisCache<DefaultCacheStrategy> = RedisCache::new(
+                ConnectionDriver::Reconnecting(client),
+                None,
+                Config::default(),
+            );
+
+            assert!(cache.ping().await.is_err());
+            assert_eq!(cache.connection_driver().is_connected(), Some(false));
+        });
+    }
+
+    #[test]
+    fn test_read_connection_driver() {
+        test::block_on(async {
+            let url = option_env!("TEST_REDIS_URL").unwrap_or("redis://127.0.0.1");
+            let primary = Client::open(url).unwrap();
+            let replica = ReconnectingClient::new(
+                Client::open("redis://127.0.0.1:9").unwrap(),
+                Backoff {
+                    max_retries: 0,
+                    ..backoff()
+                },
+            );
+            let mut cache: RedisCache<DefaultCacheStrategy> = RedisCache::new(
+                ConnectionDriver::MultiplexedClone(
+                    primary.get_multiplexed_tokio_connection().await.unwrap(),
+                ),
+                Some(ConnectionDriver::Reconnecting(replica)),
+                Config::default(),
+            );
+
+            let guild_id = Id::new(953);
+            let role_id = Id::new(954);
+            cache
+                .update(RoleCreate {
+                    guild_id,
+                    role: test::model::role(role_id),
+                })
+                .await
+                .unwrap();
+
+            let mut conn = cache.get_connection().await.unwrap();
+            assert!(cache
+                .get_role(&mut conn, guild_id, role_id)
+                .await
+                .unwrap()
+                .is_some());
+            assert!(cache.guild_roles(guild_id).await.is_err());
+            assert!(cache.guild_stats(guild_id).await.is_err());
+        });
+    }
+}
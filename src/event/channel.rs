@@ -1,21 +1,24 @@
 use twilight_model::{
     channel::Channel,
-    gateway::payload::incoming::{ChannelCreate, ChannelDelete, ChannelUpdate},
+    gateway::payload::incoming::{ChannelCreate, ChannelDelete, ChannelPinsUpdate, ChannelUpdate},
     id::{
-        marker::{ChannelMarker, GuildMarker},
+        marker::{ChannelMarker, GuildMarker, MessageMarker, UserMarker},
         Id,
     },
 };
 
 use crate::{
-    cache::Pipe, config::ResourceType, traits::CacheStrategy, Error, RedisCache, UpdateCache,
+    cache::{FromCachedRedisValue, Pipe, RedisKey},
+    config::ResourceType,
+    traits::{CacheStrategy, CacheableChannel, CacheableChannelVoiceState, CacheableMessage},
+    Connection, Error, RedisCache, UpdateCache,
 };
 
 fn cache_channel_model<S: CacheStrategy>(
     pipe: &mut Pipe<S>,
     channel: Channel,
 ) -> Result<(), Error> {
-    pipe.set_channel(channel.id, &S::Channel::from(channel))?;
+    pipe.set_channel(channel.guild_id, channel.id, &S::Channel::from(channel))?;
     Ok(())
 }
 
@@ -24,6 +27,10 @@ pub fn cache_channel<S: CacheStrategy>(pipe: &mut Pipe<S>, channel: Channel) ->
         pipe.add_guild_channel(guild_id, channel.id);
     }
 
+    if channel.kind.is_thread() {
+        super::thread::index_thread(pipe, &channel);
+    }
+
     cache_channel_model(pipe, channel)
 }
 
@@ -36,7 +43,109 @@ pub fn uncache_channel<S: CacheStrategy>(
         pipe.remove_guild_channel(guild_id, channel_id);
     }
 
-    pipe.delete_channel(channel_id);
+    pipe.delete_channel(guild_id, channel_id);
+}
+
+/// Remove the contents of channels, keeping the channels themselves: their
+/// messages, their pins and the voice states of the users in them, along with
+/// their members if `thread_members` is set and they are threads.
+///
+/// What the channels hold is read in a single round trip on `conn`. Returns the
+/// authors of the removed messages if users are cached, as they are linked to
+/// the guild of the channels.
+pub(crate) async fn uncache_channel_contents<S: CacheStrategy>(
+    cache: &RedisCache<S>,
+    conn: &mut Connection<'_>,
+    pipe: &mut Pipe<S>,
+    channel_ids: &[Id<ChannelMarker>],
+    thread_members: bool,
+) -> Result<Vec<Id<UserMarker>>, Error> {
+    let messages = cache.wants(ResourceType::MESSAGE);
+    let voice_states = cache.wants(ResourceType::VOICE_STATE);
+    let thread_members = thread_members && cache.wants(ResourceType::THREAD_MEMBER);
+
+    let mut query = cache.pipe();
+    for channel_id in channel_ids {
+        let channel_id = *channel_id;
+
+        if messages {
+            query
+                .pipeline
+                .lrange(query.key(RedisKey::ChannelMessages { channel_id }), 0, -1);
+        }
+        if voice_states {
+            query
+                .pipeline
+                .smembers(query.key(RedisKey::ChannelVoiceStates { channel_id }));
+        }
+        if thread_members {
+            query.pipeline.smembers(query.key(RedisKey::ThreadMembers {
+                thread_id: channel_id,
+            }));
+        }
+    }
+
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let replies: Vec<redis::Value> = query.pipeline.query_async(conn).await?;
+    let mut replies = replies.iter();
+    let mut next = || {
+        replies.next().ok_or_else(|| Error::Parse {
+            msg: "Insufficient replies for the channel contents.".to_owned(),
+            response: String::new(),
+        })
+    };
+
+    let mut removed_message_ids = Vec::new();
+    for channel_id in channel_ids {
+        let channel_id = *channel_id;
+
+        if messages {
+            let message_ids: Vec<Id<MessageMarker>> =
+                Vec::from_cached_redis_value::<S::Codec>(next()?)?;
+
+            for message_id in &message_ids {
+                pipe.delete_message(*message_id);
+            }
+            removed_message_ids.extend(message_ids);
+            pipe.delete_channel_messages(channel_id)
+                .delete_channel_pins(channel_id);
+        }
+
+        if voice_states {
+            let users: Vec<S::ChannelVoiceState> =
+                Vec::from_cached_redis_value::<S::Codec>(next()?)?;
+
+            for user in users {
+                pipe.remove_guild_voice_state(user.guild_id(), user.user_id())
+                    .delete_voice_state(user.guild_id(), user.user_id());
+            }
+            pipe.delete_channel_voice_states(channel_id);
+        }
+
+        if thread_members {
+            let user_ids: Vec<Id<UserMarker>> = Vec::from_cached_redis_value::<S::Codec>(next()?)?;
+
+            for user_id in user_ids {
+                pipe.delete_thread_member(channel_id, user_id);
+            }
+            pipe.delete_thread_members(channel_id);
+        }
+    }
+
+    if !cache.wants(ResourceType::USER) {
+        return Ok(Vec::new());
+    }
+
+    Ok(cache
+        .get_messages(conn, &removed_message_ids)
+        .await?
+        .into_iter()
+        .flatten()
+        .map(|message| message.author())
+        .collect())
 }
 
 impl<S: CacheStrategy> UpdateCache<S> for ChannelCreate {
@@ -52,7 +161,15 @@ impl<S: CacheStrategy> UpdateCache<S> for ChannelCreate {
 impl<S: CacheStrategy> UpdateCache<S> for ChannelUpdate {
     async fn update(&self, cache: &mut RedisCache<S>, pipe: &mut Pipe<S>) -> Result<(), Error> {
         if cache.wants(ResourceType::CHANNEL) {
-            pipe.set_channel(self.id, &S::Channel::from(self.0.clone()))?;
+            if self.kind.is_thread() {
+                super::thread::index_thread(pipe, &self.0);
+            }
+            pipe.set_channel(self.guild_id, self.id, &S::Channel::from(self.0.clone()))?;
+        }
+
+        if self.0.archived() {
+            let mut conn = cache.get_connection().await?;
+            uncache_channel_contents(cache, &mut conn, pipe, &[self.id], false).await?;
         }
 
         Ok(())
@@ -61,10 +178,244 @@ impl<S: CacheStrategy> UpdateCache<S> for ChannelUpdate {
 
 impl<S: CacheStrategy> UpdateCache<S> for ChannelDelete {
     async fn update(&self, cache: &mut RedisCache<S>, pipe: &mut Pipe<S>) -> Result<(), Error> {
+        let mut conn = cache.get_connection().await?;
+
         if cache.wants(ResourceType::CHANNEL) {
             uncache_channel(pipe, self.guild_id, self.id);
+            super::thread::uncache_channel_threads(cache, &mut conn, pipe, self.guild_id, self.id)
+                .await?;
         }
 
+        uncache_channel_contents(cache, &mut conn, pipe, &[self.id], false).await?;
+
         Ok(())
     }
 }
+
+impl<S: CacheStrategy> UpdateCache<S> for ChannelPinsUpdate {
+    async fn update(&self, cache: &mut RedisCache<S>, pipe: &mut Pipe<S>) -> Result<(), Error> {
+        if cache.wants(ResourceType::CHANNEL) {
+            if let Some(mut channel) = cache
+                .get_guarded::<S::Channel>(pipe, (self.guild_id, self.channel_id))
+                .await?
+            {
+                channel.set_last_pin_timestamp(self.last_pin_timestamp);
+                pipe.set_channel(self.guild_id, self.channel_id, &channel)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use redis::AsyncCommands;
+    use twilight_model::{
+        channel::thread::ThreadMember,
+        gateway::payload::incoming::{
+            ChannelCreate, ChannelDelete, ChannelPinsUpdate, MessageCreate, MessageDelete,
+            ThreadCreate, ThreadMembersUpdate, ThreadUpdate, VoiceStateUpdate,
+        },
+        id::Id,
+        util::Timestamp,
+    };
+
+    use crate::{cache::RedisKey, config::ResourceType, test, ConfigBuilder};
+
+    #[test]
+    fn test_channel_pins() {
+        test::block_on(async {
+            let mut cache =
+                test::redis_cache_with_config(ConfigBuilder::default().namespace("pins").build())
+                    .await;
+            let channel_id = Id::new(991);
+            let guild_id = Id::new(992);
+            let user_id = Id::new(993);
+            let pinned_id = Id::new(994);
+            let unpinned_id = Id::new(995);
+            let timestamp = Timestamp::from_secs(1_700_000_000).unwrap();
+
+            cache
+                .update(ChannelCreate(test::model::channel(
+                    channel_id,
+                    Some(guild_id),
+                )))
+                .await
+                .unwrap();
+            let mut pinned = test::model::message(pinned_id, channel_id, user_id);
+            pinned.pinned = true;
+            cache.update(MessageCreate(pinned)).await.unwrap();
+            cache
+                .update(MessageCreate(test::model::message(
+                    unpinned_id,
+                    channel_id,
+                    user_id,
+                )))
+                .await
+                .unwrap();
+            cache
+                .update(ChannelPinsUpdate {
+                    channel_id,
+                    guild_id: Some(guild_id),
+                    last_pin_timestamp: Some(timestamp),
+                })
+                .await
+                .unwrap();
+
+            {
+                let mut conn = cache.get_connection().await.unwrap();
+                let channel = cache
+                    .get_channel(&mut conn, Some(guild_id), channel_id)
+                    .await
+                    .unwrap();
+                assert_eq!(channel.unwrap().last_pin_timestamp, Some(timestamp));
+
+                let pins = cache.channel_pins(channel_id).await.unwrap();
+                assert_eq!(pins.len(), 1);
+                assert_eq!(pins[0].id(), pinned_id);
+                assert!(!cache
+                    .channel_pins_contains(&mut conn, channel_id, unpinned_id)
+                    .await
+                    .unwrap());
+            }
+
+            cache
+                .update(MessageDelete {
+                    channel_id,
+                    guild_id: Some(guild_id),
+                    id: pinned_id,
+                })
+                .await
+                .unwrap();
+            let mut conn = cache.get_connection().await.unwrap();
+            assert_eq!(
+                cache.len_channel_pins(&mut conn, channel_id).await.unwrap(),
+                0
+            );
+        });
+    }
+
+    #[test]
+    fn test_channel_delete_cascade() {
+        test::block_on(async {
+            let mut cache = test::redis_cache_with_config(
+                ConfigBuilder::default()
+                    .namespace("channel_cascade")
+                    .resource_type(
+                        ResourceType::CHANNEL
+                            | ResourceType::MESSAGE
+                            | ResourceType::THREAD_MEMBER
+                            | ResourceType::VOICE_STATE,
+                    )
+                    .build(),
+            )
+            .await;
+            let guild_id = Id::new(1021);
+            let channel_id = Id::new(1022);
+            let thread_id = Id::new(1023);
+            let archived_id = Id::new(1024);
+            let user_id = Id::new(1025);
+            let archived_message_id = Id::new(1029);
+
+            cache
+                .update(ChannelCreate(test::model::channel(
+                    channel_id,
+                    Some(guild_id),
+                )))
+                .await
+                .unwrap();
+            cache
+                .update(VoiceStateUpdate(test::model::voice_state(
+                    guild_id, channel_id, user_id,
+                )))
+                .await
+                .unwrap();
+            for thread in [thread_id, archived_id] {
+                cache
+                    .update(ThreadCreate(test::model::thread(
+                        thread, guild_id, channel_id, false,
+                    )))
+                    .await
+                    .unwrap();
+            }
+            cache
+                .update(ThreadMembersUpdate {
+                    added_members: vec![ThreadMember {
+                        flags: 0,
+                        id: Some(thread_id),
+                        join_timestamp: Timestamp::from_secs(1_700_000_000).unwrap(),
+                        member: None,
+                        presence: None,
+                        user_id: Some(user_id),
+                    }],
+                    guild_id,
+                    id: thread_id,
+                    member_count: 1,
+                    removed_member_ids: Vec::new(),
+                })
+                .await
+                .unwrap();
+
+            let messages = [
+                (1026, channel_id),
+                (1027, channel_id),
+                (1028, thread_id),
+                (archived_message_id.get(), archived_id),
+            ];
+            for (message_id, channel_id) in messages {
+                let mut message = test::model::message(Id::new(message_id), channel_id, user_id);
+                message.pinned = true;
+                cache.update(MessageCreate(message)).await.unwrap();
+            }
+
+            cache
+                .update(ThreadUpdate(test::model::thread(
+                    archived_id,
+                    guild_id,
+                    channel_id,
+                    true,
+                )))
+                .await
+                .unwrap();
+
+            {
+                let mut conn = cache.get_connection().await.unwrap();
+                assert!(cache
+                    .get_message(&mut conn, archived_message_id)
+                    .await
+                    .unwrap()
+                    .is_none());
+                assert!(cache
+                    .get_channel(&mut conn, Some(guild_id), archived_id)
+                    .await
+                    .unwrap()
+                    .is_some());
+            }
+
+            cache
+                .update(ChannelDelete(test::model::channel(
+                    channel_id,
+                    Some(guild_id),
+                )))
+                .await
+                .unwrap();
+
+            let mut conn = cache.get_connection().await.unwrap();
+            let mut keys = HashSet::new();
+            {
+                let mut iter = conn
+                    .scan_match::<_, Vec<u8>>("channel_cascade:*")
+                    .await
+                    .unwrap();
+                while let Some(key) = iter.next_item().await {
+                    keys.insert(RedisKey::parse(&key["channel_cascade:".len()..]).unwrap());
+                }
+            }
+
+            assert!(keys.is_empty(), "{keys:?}");
+        });
+    }
+}
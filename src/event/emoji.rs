@@ -18,7 +18,7 @@ pub fn uncache_emoji<S: CacheStrategy>(
     emoji_id: Id<EmojiMarker>,
 ) {
     pipe.remove_guild_emoji(guild_id, emoji_id)
-        .delete_emoji(emoji_id);
+        .delete_emoji(guild_id, emoji_id);
 }
 
 pub async fn cache_emojis<S: CacheStrategy>(
@@ -56,6 +56,7 @@ pub async fn cache_emojis<S: CacheStrategy>(
 
         pipe.add_guild_emoji(guild_id, additional_emojis.iter().map(|e| e.id));
         pipe.set_emojis(
+            guild_id,
             additional_emojis
                 .into_iter()
                 .map(|e| (e.id, S::Emoji::from(e))),
@@ -64,7 +65,7 @@ pub async fn cache_emojis<S: CacheStrategy>(
 
     if !removal_emoji_ids.is_empty() {
         pipe.remove_guild_emojis(guild_id, &removal_emoji_ids);
-        pipe.delete_emojis(removal_emoji_ids.iter().copied());
+        pipe.delete_emojis(guild_id, removal_emoji_ids.iter().copied());
     }
 
     Ok(())
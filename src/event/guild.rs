@@ -3,18 +3,24 @@ use std::mem::take;
 use twilight_model::{
     gateway::payload::incoming::{GuildCreate, GuildDelete, GuildUpdate, UnavailableGuild},
     guild::Guild,
-    id::{marker::GuildMarker, Id},
+    id::{
+        marker::{ChannelMarker, EmojiMarker, GuildMarker, UserMarker},
+        Id,
+    },
 };
 
 use crate::{
-    cache::Pipe,
+    cache::{Pipe, RedisKey},
     config::ResourceType,
-    traits::{CacheStrategy, CacheableGuild},
-    Error, RedisCache, UpdateCache,
+    traits::{CacheStrategy, CacheableEmoji, CacheableGuild, CacheableVoiceState},
+    Connection, Error, RedisCache, UpdateCache,
 };
 
 use super::channel::cache_channel;
 
+/// Number of users checked in a single round trip when a guild is removed.
+const BATCH_SIZE: usize = 1000;
+
 pub async fn cache_guild<S: CacheStrategy>(
     cache: &mut RedisCache<S>,
     pipe: &mut Pipe<S>,
@@ -90,15 +96,34 @@ pub async fn cache_guild<S: CacheStrategy>(
 }
 
 pub async fn uncache_guild<S: CacheStrategy>(
-    cache: &mut RedisCache<S>,
+    cache: &RedisCache<S>,
+    conn: &mut Connection<'_>,
     pipe: &mut Pipe<S>,
     guild_id: Id<GuildMarker>,
     unavailable: bool,
 ) -> Result<(), Error> {
+    let user_ids = uncache_guild_data(cache, conn, pipe, guild_id, unavailable).await?;
+
+    if cache.wants(ResourceType::USER) {
+        uncache_guild_users(cache, conn, pipe, &[guild_id], user_ids).await?;
+    }
+
+    Ok(())
+}
+
+/// Remove a guild along with its data, leaving its users to
+/// [`uncache_guild_users`]. Returns the users linked to the guild.
+pub(crate) async fn uncache_guild_data<S: CacheStrategy>(
+    cache: &RedisCache<S>,
+    conn: &mut Connection<'_>,
+    pipe: &mut Pipe<S>,
+    guild_id: Id<GuildMarker>,
+    unavailable: bool,
+) -> Result<Vec<Id<UserMarker>>, Error> {
     if cache.wants(ResourceType::GUILD) {
         if unavailable {
             if let Some(mut guild) = cache
-                .get_guild(&mut cache.get_connection().await?, guild_id)
+                .get_guarded_on::<S::Guild>(conn, pipe, guild_id)
                 .await?
             {
                 guild.set_unavailable(true);
@@ -106,19 +131,21 @@ pub async fn uncache_guild<S: CacheStrategy>(
                     .add_unavailable_guild(guild_id);
             }
         } else {
-            pipe.remove_guild(guild_id);
+            pipe.remove_guild(guild_id).delete_guild(guild_id);
         }
     }
 
+    if cache.wants(ResourceType::SHARD) && !unavailable {
+        pipe.remove_guild_shard(guild_id);
+    }
+
     macro_rules! remove_ids {
         (
-            cache.$scan_method:ident($($arg:expr),* $(,)?),
+            $key:expr,
             $value_name:ident,
             $inner:tt
         ) => {
-            let mut iter = cache.$scan_method($($arg),*).await?;
-
-            while let Some($value_name) = iter.next_item().await? {
+            for $value_name in cache.scan_ids(conn, $key).await? {
                 $inner
             }
         };
@@ -126,44 +153,80 @@ pub async fn uncache_guild<S: CacheStrategy>(
 
     if (cache.config.resource_type - ResourceType::GUILD).is_empty() {
         // If no other resource types are enabled, we shouldn't remove any other data.
-        return Ok(());
+        return Ok(Vec::new());
     }
 
-    let mut conn = cache.get_connection().await?;
+    // Users linked to the guild, to dissociate from it once the guild is gone.
+    let mut user_ids = Vec::new();
 
     if cache.wants(ResourceType::CHANNEL) {
+        let channel_ids: Vec<Id<ChannelMarker>> = cache
+            .scan_ids(conn, RedisKey::GuildChannels { guild_id })
+            .await?;
+
+        // Threads are channels of the guild too, so this also removes them
+        // from the active threads and drops their members.
+        let authors =
+            super::thread::uncache_threads(cache, conn, pipe, Some(guild_id), None, &channel_ids)
+                .await?;
+        user_ids.extend(authors);
+        for channel_id in channel_ids {
+            pipe.delete_channel_threads(guild_id, channel_id);
+        }
+    }
+
+    if cache.wants(ResourceType::EMOJI) {
+        let emoji_ids: Vec<Id<EmojiMarker>> = cache
+            .scan_ids(conn, RedisKey::GuildEmojis { guild_id })
+            .await?;
+
+        if cache.wants(ResourceType::USER) {
+            let emojis = cache.get_emojis(conn, guild_id, &emoji_ids).await?;
+            user_ids.extend(
+                emojis
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|emoji| emoji.resource.user_id()),
+            );
+        }
+        for emoji_id in emoji_ids {
+            super::emoji::uncache_emoji(pipe, guild_id, emoji_id);
+        }
+    }
+
+    if cache.wants(ResourceType::INTEGRATION) {
         remove_ids! {
-            cache.scan_guild_channels(&mut conn, guild_id),
+            RedisKey::GuildIntegrations { guild_id },
             id,
             {
-                super::channel::uncache_channel(pipe, Some(guild_id), id);
+                super::integration::uncache_integration(pipe, guild_id, id);
             }
-        };
+        }
     }
 
-    if cache.wants(ResourceType::EMOJI) {
+    if cache.wants(ResourceType::ROLE) {
         remove_ids! {
-            cache.scan_guild_emojis(&mut conn, guild_id),
+            RedisKey::GuildRoles { guild_id },
             id,
             {
-                super::emoji::uncache_emoji(pipe, guild_id, id);
+                super::role::uncache_role(pipe, guild_id, id);
             }
         }
     }
 
-    if cache.wants(ResourceType::ROLE) {
+    if cache.wants(ResourceType::STAGE_INSTANCE) {
         remove_ids! {
-            cache.scan_guild_roles(&mut conn, guild_id),
+            RedisKey::GuildStageInstances { guild_id },
             id,
             {
-                super::role::uncache_role(pipe, guild_id, id);
+                super::stage_instance::uncache_stage_instance(pipe, guild_id, id);
             }
         }
     }
 
     if cache.wants(ResourceType::STICKER) {
         remove_ids! {
-            cache.scan_guild_stickers(&mut conn, guild_id),
+            RedisKey::GuildStickers { guild_id },
             id,
             {
                 super::sticker::uncache_sticker(pipe, guild_id, id);
@@ -172,18 +235,37 @@ pub async fn uncache_guild<S: CacheStrategy>(
     }
 
     if cache.wants(ResourceType::VOICE_STATE) {
-        remove_ids! {
-            cache.scan_guild_members(&mut conn, guild_id),
-            id,
-            {
-                super::member::uncache_member(pipe, guild_id, id);
+        let user_ids: Vec<Id<UserMarker>> = cache
+            .scan_ids(conn, RedisKey::GuildVoiceStates { guild_id })
+            .await?;
+        let voice_states = cache.get_voice_states(conn, guild_id, &user_ids).await?;
+
+        for (user_id, voice_state) in user_ids.into_iter().zip(voice_states) {
+            if let Some(voice_state) = voice_state {
+                pipe.remove_channel_voice_state(
+                    voice_state.channel_id(),
+                    &S::ChannelVoiceState::from((guild_id, user_id)),
+                )?;
             }
+            pipe.remove_guild_voice_state(guild_id, user_id)
+                .delete_voice_state(guild_id, user_id);
         }
     }
 
+    if cache.wants(ResourceType::MEMBER) {
+        let member_ids: Vec<Id<UserMarker>> = cache
+            .scan_ids(conn, RedisKey::GuildMembers { guild_id })
+            .await?;
+
+        for user_id in &member_ids {
+            super::member::uncache_member(pipe, guild_id, *user_id);
+        }
+        user_ids.extend(member_ids);
+    }
+
     if cache.wants(ResourceType::PRESENCE) {
         remove_ids! {
-            cache.scan_guild_presences(&mut conn, guild_id),
+            RedisKey::GuildPresences { guild_id },
             user_id,
             {
                 super::presence::uncache_presence(pipe, guild_id, user_id);
@@ -191,11 +273,71 @@ pub async fn uncache_guild<S: CacheStrategy>(
         }
     }
 
+    Ok(user_ids)
+}
+
+/// Dissociate the users linked to removed guilds from them, removing the users
+/// which share no other guild with the current user.
+///
+/// Users are linked to a guild as members as well as authors of its messages or
+/// emojis, which `user_ids` holds as found while removing them. The guilds of
+/// each user are read once for all of `guild_ids`, and guarded, so that the
+/// update is retried if they change before it is committed.
+pub(crate) async fn uncache_guild_users<S: CacheStrategy>(
+    cache: &RedisCache<S>,
+    conn: &mut Connection<'_>,
+    pipe: &mut Pipe<S>,
+    guild_ids: &[Id<GuildMarker>],
+    mut user_ids: Vec<Id<UserMarker>>,
+) -> Result<(), Error> {
+    user_ids.sort_unstable();
+    user_ids.dedup();
+
+    let removed: Vec<(Id<GuildMarker>, Vec<u8>)> = guild_ids
+        .iter()
+        .map(|guild_id| (*guild_id, guild_id.to_string().into_bytes()))
+        .collect();
+
+    for user_ids in user_ids.chunks(BATCH_SIZE) {
+        let mut query = cache.pipe();
+        for user_id in user_ids {
+            query
+                .pipeline
+                .smembers(query.key(RedisKey::UserGuilds { user_id: *user_id }));
+        }
+        let replies: Vec<Vec<Vec<u8>>> = query.pipeline.query_async(conn).await?;
+
+        for (user_id, guilds) in user_ids.iter().zip(replies) {
+            let left: Vec<Id<GuildMarker>> = removed
+                .iter()
+                .filter_map(|(guild_id, guild)| guilds.contains(guild).then_some(*guild_id))
+                .collect();
+            if left.is_empty() {
+                continue;
+            }
+
+            let key = pipe.key(RedisKey::UserGuilds { user_id: *user_id });
+            pipe.guard_set(key, &guilds);
+
+            for guild_id in &left {
+                pipe.remove_user_guild(*user_id, *guild_id);
+            }
+            // The user is left without guilds if these guilds were its only ones.
+            if left.len() == guilds.len() {
+                pipe.delete_user(*user_id).remove_user(*user_id);
+            }
+        }
+    }
+
     Ok(())
 }
 
 impl<S: CacheStrategy> UpdateCache<S> for GuildCreate {
     async fn update(&self, cache: &mut RedisCache<S>, pipe: &mut Pipe<S>) -> Result<(), Error> {
+        if cache.wants(ResourceType::SHARD) {
+            cache.cache_guild_shard(pipe, self.id).await?;
+        }
+
         match self {
             g if g.0.unavailable => {
                 if cache.wants(ResourceType::GUILD) {
@@ -216,7 +358,14 @@ impl<S: CacheStrategy> UpdateCache<S> for GuildDelete {
         cache: &mut RedisCache<S>,
         pipe: &mut crate::cache::Pipe<S>,
     ) -> Result<(), Error> {
-        uncache_guild(cache, pipe, self.id, false).await?;
+        uncache_guild(
+            cache,
+            &mut cache.get_connection().await?,
+            pipe,
+            self.id,
+            false,
+        )
+        .await?;
 
         Ok(())
     }
@@ -224,7 +373,14 @@ impl<S: CacheStrategy> UpdateCache<S> for GuildDelete {
 
 impl<S: CacheStrategy> UpdateCache<S> for UnavailableGuild {
     async fn update(&self, cache: &mut RedisCache<S>, pipe: &mut Pipe<S>) -> Result<(), Error> {
-        uncache_guild(cache, pipe, self.id, true).await?;
+        uncache_guild(
+            cache,
+            &mut cache.get_connection().await?,
+            pipe,
+            self.id,
+            true,
+        )
+        .await?;
 
         Ok(())
     }
@@ -237,10 +393,7 @@ impl<S: CacheStrategy> UpdateCache<S> for GuildUpdate {
         pipe: &mut crate::cache::Pipe<S>,
     ) -> Result<(), Error> {
         if cache.wants(ResourceType::GUILD) {
-            if let Some(mut guild) = cache
-                .get_guild(&mut cache.get_connection().await?, self.id)
-                .await?
-            {
+            if let Some(mut guild) = cache.get_guarded::<S::Guild>(pipe, self.id).await? {
                 guild.update_with_guild_update(self);
 
                 pipe.set_guild(self.id, &guild)?;
@@ -250,3 +403,226 @@ impl<S: CacheStrategy> UpdateCache<S> for GuildUpdate {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use redis::AsyncCommands;
+    use twilight_model::{
+        channel::{
+            stage_instance::{PrivacyLevel, StageInstance},
+            thread::ThreadMember,
+        },
+        gateway::payload::incoming::{
+            ChannelCreate, GuildCreate, GuildDelete, IntegrationCreate, MessageCreate,
+            ThreadMembersUpdate,
+        },
+        guild::{GuildIntegration, GuildIntegrationType, IntegrationAccount},
+        id::Id,
+        util::Timestamp,
+    };
+
+    use crate::{
+        cache::RedisKey, config::ResourceType, test, ConfigBuilder, DefaultCacheStrategy,
+        RedisCache,
+    };
+
+    async fn keys(cache: &RedisCache<DefaultCacheStrategy>, namespace: &str) -> HashSet<RedisKey> {
+        let mut conn = cache.get_connection().await.unwrap();
+        let mut iter = conn
+            .scan_match::<_, Vec<u8>>(format!("{namespace}:*"))
+            .await
+            .unwrap();
+        let mut keys = HashSet::new();
+        while let Some(key) = iter.next_item().await {
+            keys.insert(RedisKey::parse(&key[namespace.len() + 1..]).unwrap());
+        }
+
+        keys
+    }
+
+    #[test]
+    fn test_guild_delete_cascade() {
+        test::block_on(async {
+            let mut cache = test::redis_cache_with_config(
+                ConfigBuilder::default().namespace("guild_cascade").build(),
+            )
+            .await;
+            let guild_id = Id::new(1031);
+            let other_guild_id = Id::new(1032);
+            let channel_id = Id::new(1033);
+            let thread_id = Id::new(1034);
+            let user_id = Id::new(1035);
+            let shared_user_id = Id::new(1036);
+            let emoji_author_id = Id::new(1047);
+            let message_author_id = Id::new(1048);
+            let dm_user_id = Id::new(1049);
+
+            let mut other_guild = test::model::guild(other_guild_id);
+            other_guild.members = vec![test::model::member(shared_user_id)];
+            cache.update(GuildCreate(other_guild)).await.unwrap();
+            let mut message = test::model::message(Id::new(1044), Id::new(1045), shared_user_id);
+            message.guild_id = Some(other_guild_id);
+            cache.update(MessageCreate(message)).await.unwrap();
+            // A user known from direct messages only, which is a member of the
+            // removed guild as well.
+            let message = test::model::message(Id::new(1050), Id::new(1051), dm_user_id);
+            cache.update(MessageCreate(message)).await.unwrap();
+            let kept = keys(&cache, "guild_cascade").await;
+
+            let mut guild = test::model::guild(guild_id);
+            guild.channels = vec![test::model::channel(channel_id, Some(guild_id))];
+            guild.threads = vec![test::model::thread(thread_id, guild_id, channel_id, false)];
+            guild.members = vec![
+                test::model::member(user_id),
+                test::model::member(shared_user_id),
+                test::model::member(dm_user_id),
+            ];
+            guild.presences = vec![test::model::presence(guild_id, user_id)];
+            guild.roles = vec![test::model::role(Id::new(1037))];
+            guild.emojis = vec![
+                test::model::emoji(Id::new(1038), Some(user_id)),
+                test::model::emoji(Id::new(1052), Some(emoji_author_id)),
+            ];
+            guild.stickers = vec![test::model::sticker(Id::new(1039), guild_id)];
+            guild.voice_states = vec![test::model::voice_state(guild_id, channel_id, user_id)];
+            guild.stage_instances = vec![StageInstance {
+                channel_id,
+                guild_id,
+                guild_scheduled_event_id: None,
+                id: Id::new(1040),
+                privacy_level: PrivacyLevel::GuildOnly,
+                topic: "topic".to_owned(),
+            }];
+            cache.update(GuildCreate(guild)).await.unwrap();
+
+            cache
+                .update(IntegrationCreate(GuildIntegration {
+                    account: IntegrationAccount {
+                        id: "account".to_owned(),
+                        name: "account".to_owned(),
+                    },
+                    application: None,
+                    enable_emoticons: None,
+                    enabled: None,
+                    expire_behavior: None,
+                    expire_grace_period: None,
+                    guild_id: Some(guild_id),
+                    id: Id::new(1041),
+                    kind: GuildIntegrationType::Discord,
+                    name: "integration".to_owned(),
+                    revoked: None,
+                    role_id: None,
+                    scopes: None,
+                    subscriber_count: None,
+                    synced_at: None,
+                    syncing: None,
+                    user: None,
+                }))
+                .await
+                .unwrap();
+            let messages = [
+                (1042, channel_id, user_id),
+                (1043, thread_id, user_id),
+                (1046, channel_id, shared_user_id),
+                (1053, channel_id, message_author_id),
+            ];
+            for (message_id, channel_id, author_id) in messages {
+                let mut message = test::model::message(Id::new(message_id), channel_id, author_id);
+                message.guild_id = Some(guild_id);
+                message.pinned = true;
+                cache.update(MessageCreate(message)).await.unwrap();
+            }
+            cache
+                .update(ThreadMembersUpdate {
+                    added_members: vec![ThreadMember {
+                        flags: 0,
+                        id: Some(thread_id),
+                        join_timestamp: Timestamp::from_secs(1_700_000_000).unwrap(),
+                        member: None,
+                        presence: None,
+                        user_id: Some(user_id),
+                    }],
+                    guild_id,
+                    id: thread_id,
+                    member_count: 1,
+                    removed_member_ids: Vec::new(),
+                })
+                .await
+                .unwrap();
+
+            cache
+                .update(GuildDelete {
+                    id: guild_id,
+                    unavailable: false,
+                })
+                .await
+                .unwrap();
+
+            assert_eq!(keys(&cache, "guild_cascade").await, kept);
+
+            let mut conn = cache.get_connection().await.unwrap();
+            assert!(!cache
+                .user_guilds_contains(&mut conn, shared_user_id, guild_id)
+                .await
+                .unwrap());
+            assert!(cache
+                .get_user(&mut conn, shared_user_id)
+                .await
+                .unwrap()
+                .is_some());
+            assert!(cache
+                .get_user(&mut conn, dm_user_id)
+                .await
+                .unwrap()
+                .is_some());
+        });
+    }
+
+    #[test]
+    fn test_guild_delete_users_without_members() {
+        test::block_on(async {
+            let mut cache = test::redis_cache_with_config(
+                ConfigBuilder::default()
+                    .namespace("guild_users")
+                    .resource_type(ResourceType::all() - ResourceType::MEMBER)
+                    .build(),
+            )
+            .await;
+            let guild_id = Id::new(1054);
+            let user_id = Id::new(1055);
+            let channel_id = Id::new(1057);
+
+            cache
+                .update(GuildCreate(test::model::guild(guild_id)))
+                .await
+                .unwrap();
+            cache
+                .update(ChannelCreate(test::model::channel(
+                    channel_id,
+                    Some(guild_id),
+                )))
+                .await
+                .unwrap();
+            let mut message = test::model::message(Id::new(1056), channel_id, user_id);
+            message.guild_id = Some(guild_id);
+            cache.update(MessageCreate(message)).await.unwrap();
+
+            cache
+                .update(GuildDelete {
+                    id: guild_id,
+                    unavailable: false,
+                })
+                .await
+                .unwrap();
+
+            let keys = keys(&cache, "guild_users").await;
+            assert!(!keys.contains(&RedisKey::User { id: user_id }), "{keys:?}");
+            assert!(
+                !keys.contains(&RedisKey::UserGuilds { user_id }),
+                "{keys:?}"
+            );
+        });
+    }
+}
@@ -71,10 +71,7 @@ pub fn uncache_member<S: CacheStrategy>(
 impl<S: CacheStrategy> UpdateCache<S> for MemberAdd {
     async fn update(&self, cache: &mut RedisCache<S>, pipe: &mut Pipe<S>) -> Result<(), Error> {
         if cache.wants(ResourceType::GUILD) {
-            if let Some(mut guild) = cache
-                .get_guild(&mut cache.get_connection().await?, self.guild_id)
-                .await?
-            {
+            if let Some(mut guild) = cache.get_guarded::<S::Guild>(pipe, self.guild_id).await? {
                 guild.increase_member_count(1);
                 pipe.set_guild(self.guild_id, &guild)?;
             };
@@ -117,10 +114,7 @@ impl<S: CacheStrategy> UpdateCache<S> for MemberChunk {
 impl<S: CacheStrategy> UpdateCache<S> for MemberRemove {
     async fn update(&self, cache: &mut RedisCache<S>, pipe: &mut Pipe<S>) -> Result<(), Error> {
         if cache.wants(ResourceType::GUILD) {
-            if let Some(mut guild) = cache
-                .get_guild(&mut cache.get_connection().await?, self.guild_id)
-                .await?
-            {
+            if let Some(mut guild) = cache.get_guarded::<S::Guild>(pipe, self.guild_id).await? {
                 guild.decrease_member_count(1);
                 pipe.set_guild(self.guild_id, &guild)?;
             };
@@ -142,11 +136,7 @@ impl<S: CacheStrategy> UpdateCache<S> for MemberUpdate {
     async fn update(&self, cache: &mut RedisCache<S>, pipe: &mut Pipe<S>) -> Result<(), Error> {
         if cache.wants(ResourceType::MEMBER) {
             if let Some(mut member) = cache
-                .get_member(
-                    &mut cache.get_connection().await?,
-                    self.guild_id,
-                    self.user.id,
-                )
+                .get_guarded::<S::Member>(pipe, (self.guild_id, self.user.id))
                 .await?
             {
                 member.update_with_member_update(self);
@@ -161,3 +151,64 @@ impl<S: CacheStrategy> UpdateCache<S> for MemberUpdate {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use twilight_model::{
+        gateway::payload::incoming::{GuildCreate, MemberAdd, MemberRemove},
+        id::Id,
+    };
+
+    use crate::test;
+
+    #[test]
+    fn test_concurrent_member_count() {
+        test::block_on(async {
+            let guild_id = Id::new(401);
+
+            let mut cache = test::redis_cache().await;
+            cache
+                .update(GuildCreate(test::model::guild(guild_id)))
+                .await
+                .unwrap();
+
+            let mut caches = Vec::new();
+            for user_id in 410..420 {
+                caches.push((Id::new(user_id), test::redis_cache().await));
+            }
+
+            let tasks: Vec<_> = caches
+                .into_iter()
+                .map(|(user_id, mut cache)| {
+                    tokio::spawn(async move {
+                        cache
+                            .update(MemberAdd {
+                                guild_id,
+                                member: test::model::member(user_id),
+                            })
+                            .await?;
+                        if user_id.get() % 2 == 0 {
+                            cache
+                                .update(MemberRemove {
+                                    guild_id,
+                                    user: test::model::user(user_id),
+                                })
+                                .await?;
+                        }
+                        Ok::<(), crate::Error>(())
+                    })
+                })
+                .collect();
+            for task in tasks {
+                task.await.unwrap().unwrap();
+            }
+
+            let guild = cache
+                .get_guild(&mut cache.get_connection().await.unwrap(), guild_id)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(guild.member_count(), Some(5));
+        });
+    }
+}
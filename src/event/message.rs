@@ -17,7 +17,8 @@ fn uncache_message<S: CacheStrategy>(
     message_id: Id<MessageMarker>,
 ) {
     pipe.delete_message(message_id)
-        .remove_channel_message(channel_id, message_id);
+        .remove_channel_message(channel_id, message_id)
+        .remove_channel_pin(channel_id, message_id);
 }
 
 impl<S: CacheStrategy> UpdateCache<S> for MessageCreate {
@@ -38,7 +39,8 @@ impl<S: CacheStrategy> UpdateCache<S> for MessageCreate {
             return Ok(());
         }
 
-        let (cache_size, oldest_id): (usize, Option<Id<MessageMarker>>) = Pipe::<S>::new()
+        let (cache_size, oldest_id): (usize, Option<Id<MessageMarker>>) = cache
+            .pipe()
             .len_channel_messages(self.channel_id)
             .index_channel_messages(self.channel_id, 0)
             .query(&mut cache.get_connection().await?)
@@ -47,12 +49,16 @@ impl<S: CacheStrategy> UpdateCache<S> for MessageCreate {
         if cache_size >= cache.config.message_cache_size {
             if let Some(oldest_id) = oldest_id {
                 pipe.pop_channel_message(self.channel_id)
-                    .delete_message(oldest_id);
+                    .delete_message(oldest_id)
+                    .remove_channel_pin(self.channel_id, oldest_id);
             }
         }
 
         pipe.push_channel_message(self.channel_id, self.id)
             .set_message(self.id, &S::Message::from(self.0.clone()))?;
+        if self.pinned {
+            pipe.add_channel_pin(self.channel_id, self.id);
+        }
 
         Ok(())
     }
@@ -87,15 +93,92 @@ impl<S: CacheStrategy> UpdateCache<S> for MessageUpdate {
         pipe: &mut crate::cache::Pipe<S>,
     ) -> Result<(), Error> {
         if cache.wants(ResourceType::MESSAGE) {
-            if let Some(mut message) = cache
-                .get_message(&mut cache.get_connection().await?, self.id)
-                .await?
-            {
+            if let Some(mut message) = cache.get_guarded::<S::Message>(pipe, self.id).await? {
                 message.update_with_message_update(self);
                 pipe.set_message(self.id, &message)?;
+
+                // Only cached messages are indexed as pins.
+                if self.pinned == Some(true) {
+                    pipe.add_channel_pin(self.channel_id, self.id);
+                }
             };
+
+            if self.pinned == Some(false) {
+                pipe.remove_channel_pin(self.channel_id, self.id);
+            }
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use twilight_model::{
+        gateway::payload::incoming::{MessageCreate, MessageUpdate},
+        id::Id,
+    };
+
+    use crate::{test, ConfigBuilder};
+
+    #[test]
+    fn test_evicted_pin() {
+        test::block_on(async {
+            let mut cache = test::redis_cache_with_config(
+                ConfigBuilder::default()
+                    .namespace("message_eviction")
+                    .message_cache_size(2)
+                    .build(),
+            )
+            .await;
+            let channel_id = Id::new(1101);
+            let user_id = Id::new(1102);
+            let message_ids = [Id::new(1103), Id::new(1104), Id::new(1105)];
+            let uncached_id = Id::new(1106);
+
+            for message_id in message_ids {
+                let mut message = test::model::message(message_id, channel_id, user_id);
+                message.pinned = message_id == message_ids[0];
+                cache.update(MessageCreate(message)).await.unwrap();
+            }
+            cache
+                .update(MessageUpdate {
+                    attachments: None,
+                    author: None,
+                    channel_id,
+                    content: None,
+                    edited_timestamp: None,
+                    embeds: None,
+                    guild_id: None,
+                    id: uncached_id,
+                    kind: None,
+                    mention_everyone: None,
+                    mention_roles: None,
+                    mentions: None,
+                    pinned: Some(true),
+                    timestamp: None,
+                    tts: None,
+                })
+                .await
+                .unwrap();
+
+            let mut conn = cache.get_connection().await.unwrap();
+            assert!(cache
+                .get_message(&mut conn, message_ids[0])
+                .await
+                .unwrap()
+                .is_none());
+            assert_eq!(
+                cache
+                    .len_channel_messages(&mut conn, channel_id)
+                    .await
+                    .unwrap(),
+                2
+            );
+            assert_eq!(
+                cache.len_channel_pins(&mut conn, channel_id).await.unwrap(),
+                0
+            );
+        });
+    }
+}
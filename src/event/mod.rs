@@ -1,10 +1,18 @@
-use twilight_model::gateway::payload::incoming::{Ready, UserUpdate};
+use twilight_model::gateway::{
+    event::Event,
+    payload::incoming::{Ready, UserUpdate},
+    ShardId,
+};
 
-use crate::{cache::Pipe, config::ResourceType, CacheStrategy, Error, RedisCache, UpdateCache};
+use crate::{
+    cache::{Pipe, ShardSession},
+    config::ResourceType,
+    CacheStrategy, Error, RedisCache, UpdateCache,
+};
 
 mod channel;
 mod emoji;
-mod guild;
+pub(crate) mod guild;
 mod integration;
 mod interaction;
 mod member;
@@ -14,9 +22,9 @@ mod reaction;
 mod role;
 mod stage_instance;
 mod sticker;
-mod thread;
+pub(crate) mod thread;
 mod user;
-mod voice_state;
+pub(crate) mod voice_state;
 
 impl<S: CacheStrategy> UpdateCache<S> for Ready {
     async fn update(&self, cache: &mut RedisCache<S>, pipe: &mut Pipe<S>) -> Result<(), Error> {
@@ -24,6 +32,27 @@ impl<S: CacheStrategy> UpdateCache<S> for Ready {
             pipe.set_current_user(&S::CurrentUser::from(self.user.clone()))?;
         }
 
+        if cache.wants(ResourceType::GUILD) {
+            for guild in &self.guilds {
+                pipe.add_unavailable_guild(guild.id);
+            }
+        }
+
+        if cache.wants(ResourceType::SHARD) {
+            let shard = self.shard.unwrap_or(ShardId::ONE);
+
+            pipe.set_shard_session(&ShardSession {
+                shard_id: shard.number(),
+                shard_total: shard.total(),
+                session_id: self.session_id.clone(),
+                resume_gateway_url: self.resume_gateway_url.clone(),
+                version: self.version,
+            })?;
+            for guild in &self.guilds {
+                pipe.set_guild_shard(guild.id, shard.number());
+            }
+        }
+
         Ok(())
     }
 }
@@ -38,15 +67,110 @@ impl<S: CacheStrategy> UpdateCache<S> for UserUpdate {
     }
 }
 
+impl<S: CacheStrategy> UpdateCache<S> for Event {
+    async fn update(&self, cache: &mut RedisCache<S>, pipe: &mut Pipe<S>) -> Result<(), Error> {
+        match self {
+            Event::ChannelCreate(v) => v.update(cache, pipe).await,
+            Event::ChannelDelete(v) => v.update(cache, pipe).await,
+            Event::ChannelPinsUpdate(v) => v.update(cache, pipe).await,
+            Event::ChannelUpdate(v) => v.update(cache, pipe).await,
+            Event::GuildCreate(v) => v.update(cache, pipe).await,
+            Event::GuildDelete(v) => v.update(cache, pipe).await,
+            Event::GuildEmojisUpdate(v) => v.update(cache, pipe).await,
+            Event::GuildStickersUpdate(v) => v.update(cache, pipe).await,
+            Event::GuildUpdate(v) => v.update(cache, pipe).await,
+            Event::IntegrationCreate(v) => v.update(cache, pipe).await,
+            Event::IntegrationDelete(v) => v.update(cache, pipe).await,
+            Event::IntegrationUpdate(v) => v.update(cache, pipe).await,
+            Event::InteractionCreate(v) => v.update(cache, pipe).await,
+            Event::MemberAdd(v) => v.update(cache, pipe).await,
+            Event::MemberChunk(v) => v.update(cache, pipe).await,
+            Event::MemberRemove(v) => v.update(cache, pipe).await,
+            Event::MemberUpdate(v) => v.update(cache, pipe).await,
+            Event::MessageCreate(v) => v.update(cache, pipe).await,
+            Event::MessageDelete(v) => v.update(cache, pipe).await,
+            Event::MessageDeleteBulk(v) => v.update(cache, pipe).await,
+            Event::MessageUpdate(v) => v.update(cache, pipe).await,
+            Event::PresenceUpdate(v) => v.update(cache, pipe).await,
+            Event::ReactionAdd(v) => v.update(cache, pipe).await,
+            Event::ReactionRemove(v) => v.update(cache, pipe).await,
+            Event::ReactionRemoveAll(v) => v.update(cache, pipe).await,
+            Event::ReactionRemoveEmoji(v) => v.update(cache, pipe).await,
+            Event::Ready(v) => v.update(cache, pipe).await,
+            Event::RoleCreate(v) => v.update(cache, pipe).await,
+            Event::RoleDelete(v) => v.update(cache, pipe).await,
+            Event::RoleUpdate(v) => v.update(cache, pipe).await,
+            Event::StageInstanceCreate(v) => v.update(cache, pipe).await,
+            Event::StageInstanceDelete(v) => v.update(cache, pipe).await,
+            Event::StageInstanceUpdate(v) => v.update(cache, pipe).await,
+            Event::ThreadCreate(v) => v.update(cache, pipe).await,
+            Event::ThreadDelete(v) => v.update(cache, pipe).await,
+            Event::ThreadListSync(v) => v.update(cache, pipe).await,
+            Event::ThreadMemberUpdate(v) => v.update(cache, pipe).await,
+            Event::ThreadMembersUpdate(v) => v.update(cache, pipe).await,
+            Event::ThreadUpdate(v) => v.update(cache, pipe).await,
+            Event::UnavailableGuild(v) => v.update(cache, pipe).await,
+            Event::UserUpdate(v) => v.update(cache, pipe).await,
+            Event::VoiceStateUpdate(v) => v.update(cache, pipe).await,
+            // Ignored events.
+            Event::AutoModerationActionExecution(_)
+            | Event::AutoModerationRuleCreate(_)
+            | Event::AutoModerationRuleDelete(_)
+            | Event::AutoModerationRuleUpdate(_)
+            | Event::BanAdd(_)
+            | Event::BanRemove(_)
+            | Event::CommandPermissionsUpdate(_)
+            | Event::GatewayClose(_)
+            | Event::GatewayHeartbeat(_)
+            | Event::GatewayHeartbeatAck
+            | Event::GatewayHello(_)
+            | Event::GatewayInvalidateSession(_)
+            | Event::GatewayReconnect
+            | Event::GiftCodeUpdate
+            | Event::GuildAuditLogEntryCreate(_)
+            | Event::GuildIntegrationsUpdate(_)
+            | Event::GuildScheduledEventCreate(_)
+            | Event::GuildScheduledEventDelete(_)
+            | Event::GuildScheduledEventUpdate(_)
+            | Event::GuildScheduledEventUserAdd(_)
+            | Event::GuildScheduledEventUserRemove(_)
+            | Event::InviteCreate(_)
+            | Event::InviteDelete(_)
+            | Event::PresencesReplace
+            | Event::Resumed
+            | Event::TypingStart(_)
+            | Event::VoiceServerUpdate(_)
+            | Event::WebhooksUpdate(_) => Ok(()),
+        }
+    }
+}
+
+impl<S: CacheStrategy> UpdateCache<S> for &Event {
+    async fn update(&self, cache: &mut RedisCache<S>, pipe: &mut Pipe<S>) -> Result<(), Error> {
+        <Event as UpdateCache<S>>::update(self, cache, pipe).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
+    use redis::AsyncCommands;
     use twilight_model::{
-        gateway::payload::incoming::Ready,
+        gateway::{
+            event::Event,
+            payload::incoming::{ChannelCreate, GuildCreate, MessageCreate, Ready, RoleDelete},
+            ShardId,
+        },
+        guild::UnavailableGuild,
         id::Id,
         oauth::{ApplicationFlags, PartialApplication},
     };
 
-    use crate::test;
+    use crate::{
+        cache::{RedisKey, ShardSession},
+        test, ConfigBuilder,
+    };
 
     #[test]
     fn test_ready() {
@@ -76,4 +200,200 @@ mod tests {
             assert_eq!(user, test::model::current_user());
         });
     }
+
+    #[test]
+    fn test_ready_shard() {
+        test::block_on(async {
+            let mut cache =
+                test::redis_cache_with_config(ConfigBuilder::default().namespace("shard").build())
+                    .await;
+            let ready_guild_id = Id::new(961);
+            let created_guild_id = Id::new(3 << 22);
+
+            let event = Ready {
+                user: test::model::current_user(),
+                application: PartialApplication {
+                    flags: ApplicationFlags::empty(),
+                    id: Id::new(1),
+                },
+                guilds: vec![UnavailableGuild {
+                    id: ready_guild_id,
+                    unavailable: true,
+                }],
+                resume_gateway_url: "wss://gateway.discord.gg".to_owned(),
+                session_id: "session".to_owned(),
+                shard: Some(ShardId::new(0, 2)),
+                version: 10,
+            };
+            cache.update(event).await.unwrap();
+            cache
+                .update(GuildCreate(test::model::guild(created_guild_id)))
+                .await
+                .unwrap();
+
+            let mut conn = cache.get_connection().await.unwrap();
+            let (unavailable,): (bool,) = cache
+                .pipe()
+                .unavailable_guilds_contains(ready_guild_id)
+                .unwrap()
+                .query(&mut conn)
+                .await
+                .unwrap();
+            assert!(unavailable);
+            assert_eq!(
+                cache.get_shard_session(&mut conn, 0).await.unwrap(),
+                Some(ShardSession {
+                    shard_id: 0,
+                    shard_total: 2,
+                    session_id: "session".to_owned(),
+                    resume_gateway_url: "wss://gateway.discord.gg".to_owned(),
+                    version: 10,
+                })
+            );
+            assert_eq!(
+                cache
+                    .get_guild_shard(&mut conn, ready_guild_id)
+                    .await
+                    .unwrap(),
+                Some(0)
+            );
+            assert_eq!(
+                cache
+                    .get_guild_shard(&mut conn, created_guild_id)
+                    .await
+                    .unwrap(),
+                Some(1)
+            );
+        });
+    }
+
+    #[test]
+    fn test_event_stream() {
+        test::block_on(async {
+            let mut cache = test::redis_cache().await;
+
+            let guild_id = Id::new(101);
+            let channel_id = Id::new(102);
+            let role_id = Id::new(103);
+            let user_id = Id::new(104);
+            let message_id = Id::new(105);
+
+            let mut guild = test::model::guild(guild_id);
+            guild.channels = vec![test::model::channel(channel_id, Some(guild_id))];
+            guild.roles = vec![test::model::role(role_id)];
+            guild.members = vec![test::model::member(user_id)];
+
+            let events = vec![
+                Event::Ready(Box::new(Ready {
+                    user: test::model::current_user(),
+                    application: PartialApplication {
+                        flags: ApplicationFlags::empty(),
+                        id: Id::new(1),
+                    },
+                    guilds: Vec::new(),
+                    resume_gateway_url: String::new(),
+                    session_id: String::new(),
+                    shard: None,
+                    version: 1,
+                })),
+                Event::GuildCreate(Box::new(GuildCreate(guild))),
+                Event::GatewayHeartbeatAck,
+                Event::ChannelCreate(Box::new(ChannelCreate(test::model::channel(
+                    Id::new(106),
+                    Some(guild_id),
+                )))),
+                Event::MessageCreate(Box::new(MessageCreate(test::model::message(
+                    message_id, channel_id, user_id,
+                )))),
+                Event::RoleDelete(RoleDelete { guild_id, role_id }),
+            ];
+
+            for event in &events {
+                cache.update(event).await.unwrap();
+            }
+            cache.update(Event::GatewayReconnect).await.unwrap();
+
+            let mut conn = cache.get_connection().await.unwrap();
+
+            assert!(cache.get_current_user(&mut conn).await.unwrap().is_some());
+            assert!(cache
+                .get_guild(&mut conn, guild_id)
+                .await
+                .unwrap()
+                .is_some());
+            assert!(cache
+                .get_channel(&mut conn, Some(guild_id), channel_id)
+                .await
+                .unwrap()
+                .is_some());
+            assert!(cache
+                .get_channel(&mut conn, Some(guild_id), Id::new(106))
+                .await
+                .unwrap()
+                .is_some());
+            assert!(cache
+                .get_member(&mut conn, guild_id, user_id)
+                .await
+                .unwrap()
+                .is_some());
+            assert!(cache
+                .get_message(&mut conn, message_id)
+                .await
+                .unwrap()
+                .is_some());
+            assert!(!cache
+                .guild_roles_contains(&mut conn, guild_id, role_id)
+                .await
+                .unwrap());
+        });
+    }
+
+    #[test]
+    fn test_event_log() {
+        test::block_on(async {
+            let mut cache = test::redis_cache_with_config(
+                ConfigBuilder::default()
+                    .namespace("event_log")
+                    .event_log(100)
+                    .build(),
+            )
+            .await;
+            let key = RedisKey::EventLog.to_namespaced_bytes(Some("event_log"));
+            let _: () = cache
+                .get_connection()
+                .await
+                .unwrap()
+                .del(&key)
+                .await
+                .unwrap();
+
+            let channel = ChannelCreate(test::model::channel(Id::new(921), Some(Id::new(922))));
+            cache.update(channel.clone()).await.unwrap();
+            cache.update(Event::GatewayHeartbeatAck).await.unwrap();
+            cache
+                .update(Event::RoleDelete(RoleDelete {
+                    guild_id: Id::new(922),
+                    role_id: Id::new(923),
+                }))
+                .await
+                .unwrap();
+
+            let mut conn = cache.get_connection().await.unwrap();
+            let entries: Vec<(String, HashMap<String, Vec<u8>>)> = redis::cmd("XRANGE")
+                .arg(&key)
+                .arg("-")
+                .arg("+")
+                .query_async(&mut conn)
+                .await
+                .unwrap();
+
+            assert_eq!(entries.len(), 2);
+            assert_eq!(entries[0].1["type"], b"CHANNEL_CREATE");
+            assert_eq!(
+                serde_json::from_slice::<ChannelCreate>(&entries[0].1["payload"]).unwrap(),
+                channel
+            );
+            assert_eq!(entries[1].1["type"], b"GUILD_ROLE_DELETE");
+        });
+    }
 }
@@ -1,4 +1,10 @@
-use twilight_model::{gateway::{payload::incoming::PresenceUpdate, presence::Presence}, id::{marker::{GuildMarker, UserMarker}, Id}};
+use twilight_model::{
+    gateway::{payload::incoming::PresenceUpdate, presence::Presence},
+    id::{
+        marker::{GuildMarker, UserMarker},
+        Id,
+    },
+};
 
 use crate::{cache::Pipe, config::ResourceType, CacheStrategy, Error, RedisCache, UpdateCache};
 
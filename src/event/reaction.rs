@@ -30,10 +30,13 @@ impl<S: CacheStrategy> UpdateCache<S> for ReactionAdd {
             return Ok(());
         };
 
-        let mut conn = cache.get_connection().await?;
-        let Some(mut message) = cache.get_message(&mut conn, self.message_id).await? else {
+        let Some(mut message) = cache
+            .get_guarded::<S::Message>(pipe, self.message_id)
+            .await?
+        else {
             return Ok(());
         };
+        let mut conn = cache.get_connection().await?;
 
         if let Some(reaction) = message
             .reactions_mut()
@@ -74,10 +77,13 @@ impl<S: CacheStrategy> UpdateCache<S> for ReactionRemove {
             return Ok(());
         };
 
-        let mut conn = cache.get_connection().await?;
-        let Some(mut message) = cache.get_message(&mut conn, self.message_id).await? else {
+        let Some(mut message) = cache
+            .get_guarded::<S::Message>(pipe, self.message_id)
+            .await?
+        else {
             return Ok(());
         };
+        let mut conn = cache.get_connection().await?;
 
         if let Some(reaction) = message
             .reactions_mut()
@@ -111,8 +117,10 @@ impl<S: CacheStrategy> UpdateCache<S> for ReactionRemoveAll {
             return Ok(());
         };
 
-        let mut conn = cache.get_connection().await?;
-        let Some(mut message) = cache.get_message(&mut conn, self.message_id).await? else {
+        let Some(mut message) = cache
+            .get_guarded::<S::Message>(pipe, self.message_id)
+            .await?
+        else {
             return Ok(());
         };
 
@@ -129,8 +137,10 @@ impl<S: CacheStrategy> UpdateCache<S> for ReactionRemoveEmoji {
             return Ok(());
         };
 
-        let mut conn = cache.get_connection().await?;
-        let Some(mut message) = cache.get_message(&mut conn, self.message_id).await? else {
+        let Some(mut message) = cache
+            .get_guarded::<S::Message>(pipe, self.message_id)
+            .await?
+        else {
             return Ok(());
         };
 
@@ -146,3 +156,85 @@ impl<S: CacheStrategy> UpdateCache<S> for ReactionRemoveEmoji {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use twilight_model::{
+        channel::message::ReactionType,
+        gateway::{
+            payload::incoming::{MessageCreate, ReactionAdd, ReactionRemove},
+            GatewayReaction,
+        },
+        id::{
+            marker::{ChannelMarker, MessageMarker, UserMarker},
+            Id,
+        },
+    };
+
+    use crate::test;
+
+    fn reaction(
+        channel_id: Id<ChannelMarker>,
+        message_id: Id<MessageMarker>,
+        user_id: Id<UserMarker>,
+    ) -> GatewayReaction {
+        GatewayReaction {
+            channel_id,
+            emoji: ReactionType::Unicode {
+                name: "👍".to_owned(),
+            },
+            guild_id: None,
+            member: None,
+            message_author_id: None,
+            message_id,
+            user_id,
+        }
+    }
+
+    #[test]
+    fn test_concurrent_reactions() {
+        test::block_on(async {
+            let channel_id = Id::new(421);
+            let message_id = Id::new(422);
+
+            let mut cache = test::redis_cache().await;
+            cache
+                .update(MessageCreate(test::model::message(
+                    message_id,
+                    channel_id,
+                    Id::new(423),
+                )))
+                .await
+                .unwrap();
+
+            let mut caches = Vec::new();
+            for user_id in 430..440 {
+                caches.push((Id::new(user_id), test::redis_cache().await));
+            }
+
+            let tasks: Vec<_> = caches
+                .into_iter()
+                .map(|(user_id, mut cache)| {
+                    tokio::spawn(async move {
+                        let reaction = reaction(channel_id, message_id, user_id);
+                        cache.update(ReactionAdd(reaction.clone())).await?;
+                        if user_id.get() % 2 == 0 {
+                            cache.update(ReactionRemove(reaction)).await?;
+                        }
+                        Ok::<(), crate::Error>(())
+                    })
+                })
+                .collect();
+            for task in tasks {
+                task.await.unwrap().unwrap();
+            }
+
+            let message = cache
+                .get_message(&mut cache.get_connection().await.unwrap(), message_id)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(message.reactions()[0].count, 5);
+        });
+    }
+}
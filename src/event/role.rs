@@ -26,7 +26,7 @@ pub fn uncache_role<S: CacheStrategy>(
     role_id: Id<RoleMarker>,
 ) {
     pipe.remove_guild_role(guild_id, role_id)
-        .delete_role(role_id);
+        .delete_role(guild_id, role_id);
 }
 
 impl<S: CacheStrategy> UpdateCache<S> for RoleCreate {
@@ -51,7 +51,7 @@ impl<S: CacheStrategy> UpdateCache<S> for RoleDelete {
 
 impl<S: CacheStrategy> UpdateCache<S> for RoleUpdate {
     async fn update(&self, cache: &mut RedisCache<S>, pipe: &mut Pipe<S>) -> Result<(), Error> {
-        if !cache.wants(ResourceType::ROLE) {
+        if cache.wants(ResourceType::ROLE) {
             pipe.set_role(
                 self.guild_id,
                 self.role.id,
@@ -28,7 +28,7 @@ pub fn uncache_stage_instance<S: CacheStrategy>(
     stage_id: Id<StageMarker>,
 ) {
     pipe.remove_guild_stage_instance(guild_id, stage_id)
-        .delete_stage_instance(stage_id);
+        .delete_stage_instance(guild_id, stage_id);
 }
 
 impl<S: CacheStrategy> UpdateCache<S> for StageInstanceCreate {
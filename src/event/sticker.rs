@@ -28,7 +28,7 @@ pub fn uncache_sticker<S: CacheStrategy>(
     sticker_id: Id<StickerMarker>,
 ) {
     pipe.remove_guild_sticker(guild_id, sticker_id)
-        .delete_sticker(sticker_id);
+        .delete_sticker(guild_id, sticker_id);
 }
 
 impl<S: CacheStrategy> UpdateCache<S> for GuildStickersUpdate {
@@ -1,10 +1,189 @@
-use twilight_model::gateway::payload::incoming::{
-    ThreadCreate, ThreadDelete, ThreadListSync, ThreadUpdate,
+use twilight_model::{
+    channel::thread::ThreadMember,
+    gateway::payload::incoming::{
+        ThreadCreate, ThreadDelete, ThreadListSync, ThreadMemberUpdate, ThreadMembersUpdate,
+        ThreadUpdate,
+    },
+    id::{
+        marker::{ChannelMarker, GuildMarker, UserMarker},
+        Id,
+    },
 };
 
-use crate::{cache::Pipe, config::ResourceType, CacheStrategy, Error, RedisCache, UpdateCache};
+use crate::{
+    cache::{Pipe, RedisKey},
+    config::ResourceType,
+    traits::{CacheStrategy, CacheableChannel},
+    Connection, Error, RedisCache, UpdateCache,
+};
+
+use super::channel::{cache_channel, uncache_channel, uncache_channel_contents};
+
+/// Add a thread to the threads of its parent channel, and to the active threads
+/// of its guild unless it is archived.
+pub(crate) fn index_thread<S: CacheStrategy>(pipe: &mut Pipe<S>, thread: &impl CacheableChannel) {
+    let Some(guild_id) = thread.guild_id() else {
+        return;
+    };
+
+    if let Some(parent_id) = thread.parent_id() {
+        pipe.add_channel_thread(guild_id, parent_id, thread.id());
+    }
+
+    if thread.archived() {
+        pipe.remove_guild_active_thread(guild_id, thread.id());
+    } else {
+        pipe.add_guild_active_thread(guild_id, thread.id());
+    }
+}
+
+/// Cache a member of a thread, skipping it if the payload tells neither the
+/// thread nor the user.
+fn cache_thread_member<S: CacheStrategy>(
+    pipe: &mut Pipe<S>,
+    thread_id: Option<Id<ChannelMarker>>,
+    member: ThreadMember,
+) -> Result<(), Error> {
+    let Some(thread_id) = member.id.or(thread_id) else {
+        return Ok(());
+    };
+    let Some(user_id) = member
+        .user_id
+        .or_else(|| member.member.as_ref().map(|member| member.user.id))
+    else {
+        return Ok(());
+    };
+
+    pipe.add_thread_member(thread_id, user_id)
+        .set_thread_member(
+            thread_id,
+            user_id,
+            &S::ThreadMember::from((thread_id, user_id, member)),
+        )?;
+
+    Ok(())
+}
+
+/// Remove the threads which are no longer active from the active threads of
+/// the guild, among the threads of the synced channels, or of the whole guild
+/// if no channel is given.
+///
+/// Threads which were already archived are skipped, as their contents are
+/// already gone.
+async fn prune_active_threads<S: CacheStrategy>(
+    cache: &RedisCache<S>,
+    conn: &mut Connection<'_>,
+    pipe: &mut Pipe<S>,
+    sync: &ThreadListSync,
+) -> Result<(), Error> {
+    let mut thread_ids: Vec<Id<ChannelMarker>> = if sync.channel_ids.is_empty() {
+        cache
+            .scan_ids(
+                conn,
+                RedisKey::GuildActiveThreads {
+                    guild_id: sync.guild_id,
+                },
+            )
+            .await?
+    } else {
+        let mut thread_ids = Vec::new();
+        for channel_id in &sync.channel_ids {
+            thread_ids.extend(
+                cache
+                    .scan_ids::<ChannelMarker>(
+                        conn,
+                        RedisKey::ChannelThreads {
+                            guild_id: sync.guild_id,
+                            channel_id: *channel_id,
+                        },
+                    )
+                    .await?,
+            );
+        }
+
+        // The threads of a channel include the archived ones.
+        let mut query = cache.pipe();
+        for thread_id in &thread_ids {
+            query.guild_active_threads_contains(sync.guild_id, *thread_id)?;
+        }
+        let active: Vec<bool> = if query.is_empty() {
+            Vec::new()
+        } else {
+            query.query(conn).await?
+        };
+
+        thread_ids
+            .into_iter()
+            .zip(active)
+            .filter_map(|(thread_id, active)| active.then_some(thread_id))
+            .collect()
+    };
+
+    thread_ids.retain(|thread_id| !sync.threads.iter().any(|thread| thread.id == *thread_id));
+
+    for thread_id in &thread_ids {
+        pipe.remove_guild_active_thread(sync.guild_id, *thread_id);
+    }
 
-use super::channel::{cache_channel, uncache_channel};
+    uncache_channel_contents(cache, conn, pipe, &thread_ids, false).await?;
+
+    Ok(())
+}
+
+/// Remove threads along with their contents and members, returning the authors
+/// of the removed messages like [`uncache_channel_contents`].
+pub(crate) async fn uncache_threads<S: CacheStrategy>(
+    cache: &RedisCache<S>,
+    conn: &mut Connection<'_>,
+    pipe: &mut Pipe<S>,
+    guild_id: Option<Id<GuildMarker>>,
+    parent_id: Option<Id<ChannelMarker>>,
+    thread_ids: &[Id<ChannelMarker>],
+) -> Result<Vec<Id<UserMarker>>, Error> {
+    if cache.wants(ResourceType::CHANNEL) {
+        for thread_id in thread_ids {
+            uncache_channel(pipe, guild_id, *thread_id);
+
+            if let Some(guild_id) = guild_id {
+                if let Some(parent_id) = parent_id {
+                    pipe.remove_channel_thread(guild_id, parent_id, *thread_id);
+                }
+                pipe.remove_guild_active_thread(guild_id, *thread_id);
+            }
+        }
+    }
+
+    uncache_channel_contents(cache, conn, pipe, thread_ids, true).await
+}
+
+/// Remove every thread of a channel, along with the set of its threads.
+///
+/// Private channels, which have no guild, have no threads either.
+pub(crate) async fn uncache_channel_threads<S: CacheStrategy>(
+    cache: &RedisCache<S>,
+    conn: &mut Connection<'_>,
+    pipe: &mut Pipe<S>,
+    guild_id: Option<Id<GuildMarker>>,
+    channel_id: Id<ChannelMarker>,
+) -> Result<(), Error> {
+    let Some(guild_id) = guild_id else {
+        return Ok(());
+    };
+    let thread_ids: Vec<Id<ChannelMarker>> = cache
+        .scan_ids(
+            conn,
+            RedisKey::ChannelThreads {
+                guild_id,
+                channel_id,
+            },
+        )
+        .await?;
+
+    uncache_threads(cache, conn, pipe, Some(guild_id), None, &thread_ids).await?;
+    pipe.delete_channel_threads(guild_id, channel_id);
+
+    Ok(())
+}
 
 impl<S: CacheStrategy> UpdateCache<S> for ThreadCreate {
     async fn update(&self, cache: &mut RedisCache<S>, pipe: &mut Pipe<S>) -> Result<(), Error> {
@@ -12,15 +191,27 @@ impl<S: CacheStrategy> UpdateCache<S> for ThreadCreate {
             cache_channel(pipe, self.0.clone())?;
         }
 
+        if cache.wants(ResourceType::THREAD_MEMBER) {
+            if let Some(member) = self.member.clone() {
+                cache_thread_member(pipe, Some(self.id), member)?;
+            }
+        }
+
         Ok(())
     }
 }
 
 impl<S: CacheStrategy> UpdateCache<S> for ThreadDelete {
     async fn update(&self, cache: &mut RedisCache<S>, pipe: &mut Pipe<S>) -> Result<(), Error> {
-        if cache.wants(ResourceType::CHANNEL) {
-            uncache_channel(pipe, Some(self.guild_id), self.id);
-        }
+        uncache_threads(
+            cache,
+            &mut cache.get_connection().await?,
+            pipe,
+            Some(self.guild_id),
+            Some(self.parent_id),
+            &[self.id],
+        )
+        .await?;
 
         Ok(())
     }
@@ -29,12 +220,49 @@ impl<S: CacheStrategy> UpdateCache<S> for ThreadDelete {
 impl<S: CacheStrategy> UpdateCache<S> for ThreadListSync {
     async fn update(&self, cache: &mut RedisCache<S>, pipe: &mut Pipe<S>) -> Result<(), Error> {
         if cache.wants(ResourceType::CHANNEL) {
+            prune_active_threads(cache, &mut cache.get_connection().await?, pipe, self).await?;
+
             let threads = self.threads.clone();
             for thread in threads {
                 cache_channel(pipe, thread)?;
             }
         }
 
+        if cache.wants(ResourceType::THREAD_MEMBER) {
+            for member in self.members.clone() {
+                cache_thread_member(pipe, None, member)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<S: CacheStrategy> UpdateCache<S> for ThreadMemberUpdate {
+    async fn update(&self, cache: &mut RedisCache<S>, pipe: &mut Pipe<S>) -> Result<(), Error> {
+        if cache.wants(ResourceType::THREAD_MEMBER) {
+            cache_thread_member(pipe, None, self.member.clone())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<S: CacheStrategy> UpdateCache<S> for ThreadMembersUpdate {
+    async fn update(&self, cache: &mut RedisCache<S>, pipe: &mut Pipe<S>) -> Result<(), Error> {
+        if !cache.wants(ResourceType::THREAD_MEMBER) {
+            return Ok(());
+        }
+
+        for member in self.added_members.clone() {
+            cache_thread_member(pipe, Some(self.id), member)?;
+        }
+
+        for user_id in &self.removed_member_ids {
+            pipe.remove_thread_member(self.id, *user_id)
+                .delete_thread_member(self.id, *user_id);
+        }
+
         Ok(())
     }
 }
@@ -46,9 +274,227 @@ impl<S: CacheStrategy> UpdateCache<S> for ThreadUpdate {
         pipe: &mut crate::cache::Pipe<S>,
     ) -> Result<(), Error> {
         if cache.wants(ResourceType::CHANNEL) {
-            pipe.set_channel(self.id, &S::Channel::from(self.0.clone()))?;
+            let thread = S::Channel::from(self.0.clone());
+            index_thread(pipe, &thread);
+            pipe.set_channel(self.guild_id, self.id, &thread)?;
+        }
+
+        if self.0.archived() {
+            let mut conn = cache.get_connection().await?;
+            uncache_channel_contents(cache, &mut conn, pipe, &[self.id], false).await?;
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use twilight_model::{
+        channel::{thread::ThreadMember, ChannelType},
+        gateway::payload::incoming::{
+            GuildCreate, ThreadCreate, ThreadDelete, ThreadListSync, ThreadMemberUpdate,
+            ThreadMembersUpdate, ThreadUpdate,
+        },
+        id::Id,
+        util::Timestamp,
+    };
+
+    use crate::{test, ConfigBuilder};
+
+    #[test]
+    fn test_thread_indexes() {
+        test::block_on(async {
+            let mut cache = test::redis_cache_with_config(
+                ConfigBuilder::default().namespace("thread_indexes").build(),
+            )
+            .await;
+            let guild_id = Id::new(1011);
+            let parent_id = Id::new(1012);
+            let synced_id = Id::new(1013);
+            let archived_id = Id::new(1014);
+            let pruned_id = Id::new(1015);
+
+            let mut guild = test::model::guild(guild_id);
+            guild.channels = vec![test::model::channel(parent_id, Some(guild_id))];
+            guild.threads = vec![
+                test::model::thread(synced_id, guild_id, parent_id, false),
+                test::model::thread(pruned_id, guild_id, parent_id, false),
+            ];
+            cache.update(GuildCreate(guild)).await.unwrap();
+            cache
+                .update(ThreadCreate(test::model::thread(
+                    archived_id,
+                    guild_id,
+                    parent_id,
+                    false,
+                )))
+                .await
+                .unwrap();
+            cache
+                .update(ThreadUpdate(test::model::thread(
+                    archived_id,
+                    guild_id,
+                    parent_id,
+                    true,
+                )))
+                .await
+                .unwrap();
+            cache
+                .update(ThreadListSync {
+                    channel_ids: vec![parent_id],
+                    guild_id,
+                    members: Vec::new(),
+                    threads: vec![test::model::thread(synced_id, guild_id, parent_id, false)],
+                })
+                .await
+                .unwrap();
+
+            {
+                let mut conn = cache.get_connection().await.unwrap();
+                assert_eq!(
+                    cache
+                        .len_channel_threads(&mut conn, guild_id, parent_id)
+                        .await
+                        .unwrap(),
+                    3
+                );
+                assert_eq!(
+                    cache
+                        .len_guild_active_threads(&mut conn, guild_id)
+                        .await
+                        .unwrap(),
+                    1
+                );
+                assert!(cache
+                    .guild_active_threads_contains(&mut conn, guild_id, synced_id)
+                    .await
+                    .unwrap());
+            }
+
+            cache
+                .update(ThreadDelete {
+                    guild_id,
+                    id: synced_id,
+                    kind: ChannelType::PublicThread,
+                    parent_id,
+                })
+                .await
+                .unwrap();
+
+            let mut conn = cache.get_connection().await.unwrap();
+            assert!(!cache
+                .channel_threads_contains(&mut conn, guild_id, parent_id, synced_id)
+                .await
+                .unwrap());
+            assert_eq!(
+                cache
+                    .len_guild_active_threads(&mut conn, guild_id)
+                    .await
+                    .unwrap(),
+                0
+            );
+        });
+    }
+
+    #[test]
+    fn test_thread_members() {
+        test::block_on(async {
+            let mut cache = test::redis_cache_with_config(
+                ConfigBuilder::default().namespace("thread_members").build(),
+            )
+            .await;
+            let guild_id = Id::new(1001);
+            let thread_id = Id::new(1002);
+            let user_ids = [Id::new(1003), Id::new(1004)];
+            let member = |user_id, flags| ThreadMember {
+                flags,
+                id: Some(thread_id),
+                join_timestamp: Timestamp::from_secs(1_700_000_000).unwrap(),
+                member: None,
+                presence: None,
+                user_id: Some(user_id),
+            };
+
+            cache
+                .update(ThreadMembersUpdate {
+                    added_members: user_ids.iter().map(|id| member(*id, 0)).collect(),
+                    guild_id,
+                    id: thread_id,
+                    member_count: 2,
+                    removed_member_ids: Vec::new(),
+                })
+                .await
+                .unwrap();
+            cache
+                .update(ThreadMemberUpdate {
+                    guild_id,
+                    member: member(user_ids[1], 1),
+                })
+                .await
+                .unwrap();
+            cache
+                .update(ThreadMembersUpdate {
+                    added_members: Vec::new(),
+                    guild_id,
+                    id: thread_id,
+                    member_count: 1,
+                    removed_member_ids: vec![user_ids[0]],
+                })
+                .await
+                .unwrap();
+
+            {
+                let mut conn = cache.get_connection().await.unwrap();
+                let mut iter = cache
+                    .scan_thread_members(&mut conn, thread_id)
+                    .await
+                    .unwrap();
+                let mut members = Vec::new();
+                while let Some(user_id) = iter.next_item().await.unwrap() {
+                    members.push(user_id);
+                }
+                assert_eq!(members, [user_ids[1]]);
+            }
+
+            let mut conn = cache.get_connection().await.unwrap();
+            assert!(cache
+                .get_thread_member(&mut conn, thread_id, user_ids[0])
+                .await
+                .unwrap()
+                .is_none());
+            let updated = cache
+                .get_thread_member(&mut conn, thread_id, user_ids[1])
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(updated.flags(), 1);
+            assert_eq!(updated.user_id(), user_ids[1]);
+            drop(conn);
+
+            cache
+                .update(ThreadDelete {
+                    guild_id,
+                    id: thread_id,
+                    kind: ChannelType::PublicThread,
+                    parent_id: Id::new(1005),
+                })
+                .await
+                .unwrap();
+
+            let mut conn = cache.get_connection().await.unwrap();
+            assert!(cache
+                .get_thread_member(&mut conn, thread_id, user_ids[1])
+                .await
+                .unwrap()
+                .is_none());
+            assert_eq!(
+                cache
+                    .len_thread_members(&mut conn, thread_id)
+                    .await
+                    .unwrap(),
+                0
+            );
+        });
+    }
+}
@@ -8,8 +8,8 @@ use twilight_model::{
 };
 
 use crate::{
-    cache::Pipe, config::ResourceType, traits::CacheableVoiceState, CacheStrategy, Connection,
-    Error, UpdateCache,
+    cache::Pipe, config::ResourceType, traits::CacheableVoiceState, CacheStrategy, Error,
+    RedisCache, UpdateCache,
 };
 
 pub(crate) fn set_voice_state_cache<S: CacheStrategy>(
@@ -27,7 +27,7 @@ pub(crate) fn set_voice_state_cache<S: CacheStrategy>(
 }
 
 pub(crate) async fn cache_voice_state<S: CacheStrategy>(
-    conn: &mut Connection<'_>,
+    cache: &RedisCache<S>,
     pipe: &mut Pipe<S>,
     guild_id: Id<GuildMarker>,
     voice_state: VoiceState,
@@ -36,9 +36,10 @@ pub(crate) async fn cache_voice_state<S: CacheStrategy>(
 
     // Check if the user is switching channels.
     // If they are, remove them from the old channel.
-    let (already_voice_state,): (Option<S::VoiceState>,) = Pipe::<S>::new()
+    let (already_voice_state,): (Option<S::VoiceState>,) = cache
+        .pipe()
         .get_voice_state(guild_id, user_id)
-        .query(conn)
+        .query(&mut cache.get_connection().await?)
         .await?;
 
     if let Some(already_voice_state) = already_voice_state {
@@ -65,18 +66,12 @@ pub(crate) async fn cache_voice_state<S: CacheStrategy>(
 impl<S: CacheStrategy> UpdateCache<S> for VoiceStateUpdate {
     async fn update(
         &self,
-        cache: &mut crate::RedisCache<S>,
+        cache: &mut RedisCache<S>,
         pipe: &mut crate::cache::Pipe<S>,
     ) -> Result<(), Error> {
         if cache.wants(ResourceType::VOICE_STATE) {
             if let Some(guild_id) = self.guild_id {
-                cache_voice_state(
-                    &mut cache.get_connection().await?,
-                    pipe,
-                    guild_id,
-                    self.0.clone(),
-                )
-                .await?;
+                cache_voice_state(cache, pipe, guild_id, self.0.clone()).await?;
             }
         }
 
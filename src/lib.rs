@@ -1,19 +1,32 @@
 #![cfg_attr(docsrs, feature(doc_auto_cfg))]
 
 pub mod cache;
+#[cfg(feature = "cluster")]
+mod cluster;
+pub mod codec;
 mod config;
 mod connection;
 pub mod event;
 mod model;
+#[cfg(feature = "permission-calculator")]
+pub mod permission;
+#[cfg(test)]
 mod test;
 mod traits;
 
-use std::marker::PhantomData;
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    marker::PhantomData,
+    sync::Arc,
+    time::Duration,
+};
 
-use self::config::ResourceType;
 pub use self::{
-    config::{Config, ConfigBuilder},
-    connection::{Connection, ConnectionDriver},
+    config::{Config, ConfigBuilder, ResourceType},
+    connection::{
+        Backoff, Connection, ConnectionDriver, ReconnectingClient, ReconnectingConnection,
+    },
     traits::CacheStrategy,
 };
 
@@ -26,6 +39,10 @@ pub enum Error {
     User(#[from] anyhow::Error),
     #[error("Failed to process data with serde.")]
     Serde(#[from] serde_json::Error),
+    #[error("Failed to read or write a snapshot: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to process data with the codec: {0}")]
+    Codec(Box<dyn std::error::Error + Send + Sync>),
     #[error("Redis has raise error: {0}")]
     Redis(#[from] redis::RedisError),
     #[cfg(feature = "bb8")]
@@ -33,53 +50,142 @@ pub enum Error {
     BB8(#[from] bb8_redis::bb8::RunError<redis::RedisError>),
     #[error("Failed to parse data. {msg} (Response was {response:?})")]
     Parse { msg: String, response: String },
+    #[error("Failed to update the cache because other clients kept changing the same values.")]
+    Conflict,
+    #[error("Shard {shard_id} is not one of the {shard_total} shards.")]
+    InvalidShard { shard_id: u64, shard_total: u64 },
+    #[cfg(feature = "permission-calculator")]
+    #[error("Failed to calculate permissions: {0}")]
+    Permission(#[from] permission::PermissionError),
 }
 
 mod private {
-    use twilight_model::gateway::payload::incoming::*;
-
-    pub trait Sealed {}
-
-    impl Sealed for ChannelCreate {}
-    impl Sealed for ChannelDelete {}
-    impl Sealed for ChannelPinsUpdate {}
-    impl Sealed for ChannelUpdate {}
-    impl Sealed for GuildCreate {}
-    impl Sealed for GuildEmojisUpdate {}
-    impl Sealed for GuildDelete {}
-    impl Sealed for GuildStickersUpdate {}
-    impl Sealed for GuildUpdate {}
-    impl Sealed for IntegrationCreate {}
-    impl Sealed for IntegrationDelete {}
-    impl Sealed for IntegrationUpdate {}
-    impl Sealed for InteractionCreate {}
-    impl Sealed for MemberAdd {}
-    impl Sealed for MemberChunk {}
-    impl Sealed for MemberRemove {}
-    impl Sealed for MemberUpdate {}
-    impl Sealed for MessageCreate {}
-    impl Sealed for MessageDelete {}
-    impl Sealed for MessageDeleteBulk {}
-    impl Sealed for MessageUpdate {}
-    impl Sealed for PresenceUpdate {}
-    impl Sealed for ReactionAdd {}
-    impl Sealed for ReactionRemove {}
-    impl Sealed for ReactionRemoveAll {}
-    impl Sealed for ReactionRemoveEmoji {}
-    impl Sealed for Ready {}
-    impl Sealed for RoleCreate {}
-    impl Sealed for RoleDelete {}
-    impl Sealed for RoleUpdate {}
-    impl Sealed for StageInstanceCreate {}
-    impl Sealed for StageInstanceDelete {}
-    impl Sealed for StageInstanceUpdate {}
-    impl Sealed for ThreadCreate {}
-    impl Sealed for ThreadDelete {}
-    impl Sealed for ThreadListSync {}
-    impl Sealed for ThreadUpdate {}
-    impl Sealed for UnavailableGuild {}
-    impl Sealed for UserUpdate {}
-    impl Sealed for VoiceStateUpdate {}
+    use twilight_model::gateway::{
+        event::{Event, EventType},
+        payload::incoming::*,
+    };
+
+    use crate::{codec::Codec, Error};
+
+    pub trait Sealed {
+        /// Type and serialized payload of the event to append to the event
+        /// log, `None` if the cache ignores the event.
+        fn log_entry<C: Codec>(&self) -> Result<Option<(EventType, Vec<u8>)>, Error>;
+    }
+
+    impl Sealed for Event {
+        fn log_entry<C: Codec>(&self) -> Result<Option<(EventType, Vec<u8>)>, Error> {
+            match self {
+                Event::ChannelCreate(v) => v.log_entry::<C>(),
+                Event::ChannelDelete(v) => v.log_entry::<C>(),
+                Event::ChannelPinsUpdate(v) => v.log_entry::<C>(),
+                Event::ChannelUpdate(v) => v.log_entry::<C>(),
+                Event::GuildCreate(v) => v.log_entry::<C>(),
+                Event::GuildDelete(v) => v.log_entry::<C>(),
+                Event::GuildEmojisUpdate(v) => v.log_entry::<C>(),
+                Event::GuildStickersUpdate(v) => v.log_entry::<C>(),
+                Event::GuildUpdate(v) => v.log_entry::<C>(),
+                Event::IntegrationCreate(v) => v.log_entry::<C>(),
+                Event::IntegrationDelete(v) => v.log_entry::<C>(),
+                Event::IntegrationUpdate(v) => v.log_entry::<C>(),
+                Event::InteractionCreate(v) => v.log_entry::<C>(),
+                Event::MemberAdd(v) => v.log_entry::<C>(),
+                Event::MemberChunk(v) => v.log_entry::<C>(),
+                Event::MemberRemove(v) => v.log_entry::<C>(),
+                Event::MemberUpdate(v) => v.log_entry::<C>(),
+                Event::MessageCreate(v) => v.log_entry::<C>(),
+                Event::MessageDelete(v) => v.log_entry::<C>(),
+                Event::MessageDeleteBulk(v) => v.log_entry::<C>(),
+                Event::MessageUpdate(v) => v.log_entry::<C>(),
+                Event::PresenceUpdate(v) => v.log_entry::<C>(),
+                Event::ReactionAdd(v) => v.log_entry::<C>(),
+                Event::ReactionRemove(v) => v.log_entry::<C>(),
+                Event::ReactionRemoveAll(v) => v.log_entry::<C>(),
+                Event::ReactionRemoveEmoji(v) => v.log_entry::<C>(),
+                Event::Ready(v) => v.log_entry::<C>(),
+                Event::RoleCreate(v) => v.log_entry::<C>(),
+                Event::RoleDelete(v) => v.log_entry::<C>(),
+                Event::RoleUpdate(v) => v.log_entry::<C>(),
+                Event::StageInstanceCreate(v) => v.log_entry::<C>(),
+                Event::StageInstanceDelete(v) => v.log_entry::<C>(),
+                Event::StageInstanceUpdate(v) => v.log_entry::<C>(),
+                Event::ThreadCreate(v) => v.log_entry::<C>(),
+                Event::ThreadDelete(v) => v.log_entry::<C>(),
+                Event::ThreadListSync(v) => v.log_entry::<C>(),
+                Event::ThreadMemberUpdate(v) => v.log_entry::<C>(),
+                Event::ThreadMembersUpdate(v) => v.log_entry::<C>(),
+                Event::ThreadUpdate(v) => v.log_entry::<C>(),
+                Event::UnavailableGuild(v) => v.log_entry::<C>(),
+                Event::UserUpdate(v) => v.log_entry::<C>(),
+                Event::VoiceStateUpdate(v) => v.log_entry::<C>(),
+                // Events ignored by `update`.
+                _ => Ok(None),
+            }
+        }
+    }
+
+    impl Sealed for &Event {
+        fn log_entry<C: Codec>(&self) -> Result<Option<(EventType, Vec<u8>)>, Error> {
+            (*self).log_entry::<C>()
+        }
+    }
+
+    macro_rules! impl_sealed {
+        ($($name:ident),* $(,)?) => {
+            $(
+                impl Sealed for $name {
+                    fn log_entry<C: Codec>(&self) -> Result<Option<(EventType, Vec<u8>)>, Error> {
+                        Ok(Some((EventType::$name, C::encode(self)?)))
+                    }
+                }
+            )*
+        };
+    }
+
+    impl_sealed!(
+        ChannelCreate,
+        ChannelDelete,
+        ChannelPinsUpdate,
+        ChannelUpdate,
+        GuildCreate,
+        GuildEmojisUpdate,
+        GuildDelete,
+        GuildStickersUpdate,
+        GuildUpdate,
+        IntegrationCreate,
+        IntegrationDelete,
+        IntegrationUpdate,
+        InteractionCreate,
+        MemberAdd,
+        MemberChunk,
+        MemberRemove,
+        MemberUpdate,
+        MessageCreate,
+        MessageDelete,
+        MessageDeleteBulk,
+        MessageUpdate,
+        PresenceUpdate,
+        ReactionAdd,
+        ReactionRemove,
+        ReactionRemoveAll,
+        ReactionRemoveEmoji,
+        Ready,
+        RoleCreate,
+        RoleDelete,
+        RoleUpdate,
+        StageInstanceCreate,
+        StageInstanceDelete,
+        StageInstanceUpdate,
+        ThreadCreate,
+        ThreadDelete,
+        ThreadListSync,
+        ThreadMemberUpdate,
+        ThreadMembersUpdate,
+        ThreadUpdate,
+        UnavailableGuild,
+        UserUpdate,
+        VoiceStateUpdate,
+    );
 }
 
 #[trait_variant::make(Send)]
@@ -94,7 +200,7 @@ pub trait UpdateCache<S: CacheStrategy>: private::Sealed {
 pub struct DefaultCacheStrategy;
 
 impl CacheStrategy for DefaultCacheStrategy {
-    type SerdeError = serde_json::Error;
+    type Codec = codec::Json;
 
     type Channel = twilight_model::channel::Channel;
     type ChannelVoiceState = model::CachedChannelVoiceState;
@@ -108,29 +214,101 @@ impl CacheStrategy for DefaultCacheStrategy {
     type Role = twilight_model::guild::Role;
     type StageInstance = twilight_model::channel::StageInstance;
     type Sticker = model::CachedSticker;
+    type ThreadMember = model::CachedThreadMember;
     type User = twilight_model::user::User;
     type VoiceState = model::CachedVoiceState;
 }
 
+/// Delay before the given attempt of an update which conflicted with another
+/// client, picked at random up to a millisecond doubled for each attempt, and
+/// up to 128 milliseconds.
+fn conflict_delay(attempt: usize) -> Duration {
+    let max = 1000_u64 << attempt.min(7);
+    let random = RandomState::new().build_hasher().finish();
+
+    Duration::from_micros(random % max)
+}
+
 pub struct RedisCache<S: CacheStrategy = DefaultCacheStrategy> {
     connection_driver: ConnectionDriver,
-    config: Config,
+    read_connection_driver: Option<ConnectionDriver>,
+    config: Arc<Config>,
     _strategy: PhantomData<S>,
 }
 
 impl<S: CacheStrategy> RedisCache<S> {
-    pub fn new(connection_driver: ConnectionDriver, config: Config) -> Self {
+    /// Create a cache writing through `connection_driver`.
+    ///
+    /// When `read_connection_driver` is given, for example for a replica, the
+    /// reads the cache runs on its own, such as streams, statistics and
+    /// exports, use it instead. Updates, including the values they read to
+    /// apply an event, always use `connection_driver`.
+    pub fn new(
+        connection_driver: ConnectionDriver,
+        read_connection_driver: Option<ConnectionDriver>,
+        config: Config,
+    ) -> Self {
         Self {
             connection_driver,
-            config,
+            read_connection_driver,
+            config: Arc::new(config),
             _strategy: PhantomData,
         }
     }
 
-    pub(crate) async fn get_connection(&self) -> Result<Connection<'_>, Error> {
+    /// Get a connection to the primary, which every write goes to.
+    ///
+    /// Use it for the getters when a value written just before must be read.
+    pub async fn get_connection(&self) -> Result<Connection<'_>, Error> {
         self.connection_driver.get().await
     }
 
+    /// Get a connection for reads, to the replica if a read connection driver
+    /// was given, to the primary otherwise.
+    ///
+    /// A replica may lag behind the primary, so values written just before
+    /// may not be read yet.
+    pub async fn get_read_connection(&self) -> Result<Connection<'_>, Error> {
+        match &self.read_connection_driver {
+            Some(driver) => driver.get().await,
+            None => self.connection_driver.get().await,
+        }
+    }
+
+    /// Send a `PING` to Redis, for example to check the health of the cache in
+    /// a readiness probe.
+    ///
+    /// See also [`ConnectionDriver::is_connected`], which tells whether the
+    /// last command reached Redis without sending one.
+    pub async fn ping(&self) -> Result<(), Error> {
+        let _: String = redis::cmd("PING")
+            .query_async(&mut self.get_connection().await?)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Returns the driver from which the connections to the primary are taken.
+    pub const fn connection_driver(&self) -> &ConnectionDriver {
+        &self.connection_driver
+    }
+
+    /// Returns the driver from which the connections for reads are taken, if
+    /// it differs from the primary.
+    pub const fn read_connection_driver(&self) -> Option<&ConnectionDriver> {
+        self.read_connection_driver.as_ref()
+    }
+
+    /// Create a pipe that follows the configuration of this cache, such as its
+    /// key namespace.
+    pub fn pipe(&self) -> cache::Pipe<S> {
+        cache::Pipe::with_config(Arc::clone(&self.config))
+    }
+
+    pub(crate) fn key(&self, key: impl Into<cache::RedisKey>) -> Vec<u8> {
+        key.into().to_namespaced_bytes(self.config.namespace())
+    }
+
     pub fn wants(&self, resource_type: ResourceType) -> bool {
         self.config.resource_type.contains(resource_type)
     }
@@ -139,18 +317,43 @@ impl<S: CacheStrategy> RedisCache<S> {
         self.config.resource_type.intersects(resource_type)
     }
 
+    /// Update the cache with an event.
+    ///
+    /// Events changing a value already in the cache, such as reactions and
+    /// member counts, are applied only if the value was not changed by another
+    /// client in the meantime, and retried otherwise. [`Error::Conflict`] is
+    /// returned after [`Config::max_update_attempts`] failed attempts.
+    ///
+    /// Retries wait for a random delay growing with each attempt, so that
+    /// clients changing the same value do not keep colliding. The delay is
+    /// slept with the Tokio timer.
     pub async fn update(&mut self, cache: impl UpdateCache<S>) -> Result<(), Error> {
-        let mut pipe = cache::Pipe::new();
-        if self.config.atomic {
-            pipe.atomic();
-        }
+        let log_entry = match self.config.event_log {
+            Some(_) => cache.log_entry::<S::Codec>()?,
+            None => None,
+        };
+
+        for attempt in 0..self.config.max_update_attempts.get() {
+            if attempt > 0 {
+                tokio::time::sleep(conflict_delay(attempt)).await;
+            }
 
-        cache.update(self, &mut pipe).await?;
+            let mut pipe = self.pipe();
+            if self.config.atomic {
+                pipe.atomic();
+            }
 
-        if !pipe.is_empty() {
-            let _: (redis::Value,) = pipe.query(&mut self.get_connection().await?).await?;
+            cache.update(self, &mut pipe).await?;
+            if let Some((kind, payload)) = &log_entry {
+                pipe.log_event(kind.name().unwrap_or_default(), payload);
+            }
+            pipe.publish_changes();
+
+            if pipe.is_empty() || pipe.commit(&mut self.get_connection().await?).await? {
+                return Ok(());
+            }
         }
 
-        Ok(())
+        Err(Error::Conflict)
     }
 }
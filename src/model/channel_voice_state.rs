@@ -21,4 +21,12 @@ impl From<(Id<GuildMarker>, Id<UserMarker>)> for CachedChannelVoiceState {
 crate::cache::value::impl_from_bytes_for_model!(CachedChannelVoiceState);
 crate::cache::value::impl_to_bytes_for_model!(CachedChannelVoiceState);
 
-impl CacheableChannelVoiceState for CachedChannelVoiceState {}
+impl CacheableChannelVoiceState for CachedChannelVoiceState {
+    fn guild_id(&self) -> Id<GuildMarker> {
+        self.guild_id
+    }
+
+    fn user_id(&self) -> Id<UserMarker> {
+        self.user_id
+    }
+}
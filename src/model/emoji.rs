@@ -110,7 +110,11 @@ impl PartialEq<Emoji> for CachedEmoji {
 crate::cache::value::impl_to_bytes_for_model!(CachedEmoji);
 crate::cache::value::impl_from_bytes_for_model!(CachedEmoji);
 
-impl CacheableEmoji for CachedEmoji {}
+impl CacheableEmoji for CachedEmoji {
+    fn user_id(&self) -> Option<Id<UserMarker>> {
+        self.user_id
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -387,7 +387,7 @@ impl PartialEq<Message> for CachedMessage {
                     other
                         .interaction
                         .as_ref()
-                        .map_or(false, |other_interaction| interaction == other_interaction)
+                        .is_some_and(|other_interaction| interaction == other_interaction)
                 })
             && self.kind == other.kind
             && self.member == other.member
@@ -458,6 +458,14 @@ impl CacheableMessage for CachedMessage {
         }
     }
 
+    fn author(&self) -> Id<UserMarker> {
+        self.author
+    }
+
+    fn pinned(&self) -> bool {
+        self.pinned
+    }
+
     fn reactions(&self) -> &[Reaction] {
         &self.reactions
     }
@@ -5,10 +5,11 @@ pub(crate) mod member;
 mod message;
 mod presence;
 mod sticker;
+mod thread_member;
 mod voice_state;
 
 pub use self::{
     channel_voice_state::CachedChannelVoiceState, emoji::CachedEmoji, guild::CachedGuild,
     member::CachedMember, message::CachedMessage, presence::CachedPresence, sticker::CachedSticker,
-    voice_state::CachedVoiceState,
+    thread_member::CachedThreadMember, voice_state::CachedVoiceState,
 };
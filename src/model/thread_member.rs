@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+use twilight_model::{
+    channel::thread::ThreadMember,
+    id::{
+        marker::{ChannelMarker, UserMarker},
+        Id,
+    },
+    util::Timestamp,
+};
+
+use crate::traits::CacheableThreadMember;
+
+/// Represents a cached [`ThreadMember`].
+///
+/// [`ThreadMember`]: twilight_model::channel::thread::ThreadMember
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CachedThreadMember {
+    pub(crate) flags: u64,
+    pub(crate) join_timestamp: Timestamp,
+    pub(crate) thread_id: Id<ChannelMarker>,
+    pub(crate) user_id: Id<UserMarker>,
+}
+
+impl CachedThreadMember {
+    /// Flags of the member, only used for notification settings.
+    pub const fn flags(&self) -> u64 {
+        self.flags
+    }
+
+    /// When the user last joined the thread.
+    pub const fn join_timestamp(&self) -> Timestamp {
+        self.join_timestamp
+    }
+
+    /// ID of the thread.
+    pub const fn thread_id(&self) -> Id<ChannelMarker> {
+        self.thread_id
+    }
+
+    /// ID of the user.
+    pub const fn user_id(&self) -> Id<UserMarker> {
+        self.user_id
+    }
+}
+
+impl From<(Id<ChannelMarker>, Id<UserMarker>, ThreadMember)> for CachedThreadMember {
+    fn from(
+        (thread_id, user_id, member): (Id<ChannelMarker>, Id<UserMarker>, ThreadMember),
+    ) -> Self {
+        Self {
+            flags: member.flags,
+            join_timestamp: member.join_timestamp,
+            thread_id,
+            user_id,
+        }
+    }
+}
+
+impl PartialEq<ThreadMember> for CachedThreadMember {
+    fn eq(&self, other: &ThreadMember) -> bool {
+        self.flags == other.flags
+            && self.join_timestamp == other.join_timestamp
+            && other.id.is_none_or(|id| id == self.thread_id)
+            && other.user_id.is_none_or(|id| id == self.user_id)
+    }
+}
+
+crate::cache::value::impl_from_bytes_for_model!(CachedThreadMember);
+crate::cache::value::impl_to_bytes_for_model!(CachedThreadMember);
+
+impl CacheableThreadMember for CachedThreadMember {}
@@ -0,0 +1,405 @@
+//! Calculate the permissions of members from the cached guild, member, role
+//! and channel entries.
+//!
+//! Created with [`RedisCache::permissions`]. The guild, the member and every
+//! role of the guild are fetched in a single pipeline, along with the channel.
+//! [`in_channel`] reads the parent of a thread afterwards.
+//!
+//! [`in_channel`]: RedisCachePermissions::in_channel
+
+use std::{
+    collections::HashMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use redis::Value;
+use twilight_model::{
+    channel::permission_overwrite::{PermissionOverwrite, PermissionOverwriteType},
+    guild::Permissions,
+    id::{
+        marker::{ChannelMarker, GuildMarker, RoleMarker, UserMarker},
+        Id,
+    },
+};
+
+use crate::{
+    cache::{FromCachedRedisValue, WithGuildId},
+    traits::{CacheableChannel, CacheableGuild, CacheableMember, CacheableRole},
+    CacheStrategy, Error, RedisCache,
+};
+
+/// Permissions a member keeps while their communication is disabled.
+const COMMUNICATION_DISABLED_ALLOWLIST: Permissions =
+    Permissions::VIEW_CHANNEL.union(Permissions::READ_MESSAGE_HISTORY);
+
+/// Permissions which are useless without being able to send messages.
+const MESSAGING_PERMISSIONS: Permissions = Permissions::ATTACH_FILES
+    .union(Permissions::EMBED_LINKS)
+    .union(Permissions::MENTION_EVERYONE)
+    .union(Permissions::SEND_TTS_MESSAGES);
+
+/// A resource needed to calculate permissions is missing.
+#[derive(thiserror::Error, Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PermissionError {
+    #[error("The channel {0} is not in the cache.")]
+    ChannelUnavailable(Id<ChannelMarker>),
+    #[error("The guild {0} is not in the cache.")]
+    GuildUnavailable(Id<GuildMarker>),
+    #[error("The member {user_id} of the guild {guild_id} is not in the cache.")]
+    MemberUnavailable {
+        guild_id: Id<GuildMarker>,
+        user_id: Id<UserMarker>,
+    },
+    #[error("The role {0} is not in the cache.")]
+    RoleUnavailable(Id<RoleMarker>),
+}
+
+/// Calculate the permissions of members in guilds and their channels.
+pub struct RedisCachePermissions<'a, S: CacheStrategy> {
+    cache: &'a RedisCache<S>,
+    check_member_communication_disabled: bool,
+}
+
+impl<'a, S: CacheStrategy> RedisCachePermissions<'a, S> {
+    pub(crate) const fn new(cache: &'a RedisCache<S>) -> Self {
+        Self {
+            cache,
+            check_member_communication_disabled: true,
+        }
+    }
+
+    /// Whether to take away the permissions of members whose communication
+    /// is disabled, except [`VIEW_CHANNEL`] and [`READ_MESSAGE_HISTORY`].
+    ///
+    /// Defaults to `true`.
+    ///
+    /// [`VIEW_CHANNEL`]: Permissions::VIEW_CHANNEL
+    /// [`READ_MESSAGE_HISTORY`]: Permissions::READ_MESSAGE_HISTORY
+    pub const fn check_member_communication_disabled(mut self, check: bool) -> Self {
+        self.check_member_communication_disabled = check;
+        self
+    }
+
+    /// Calculate the permissions of a member in a guild, without the
+    /// permission overwrites of any channel.
+    pub async fn root(
+        &self,
+        user_id: Id<UserMarker>,
+        guild_id: Id<GuildMarker>,
+    ) -> Result<Permissions, Error> {
+        let mut conn = self.cache.get_read_connection().await?;
+
+        let mut pipe = self.cache.pipe();
+        pipe.get_guild(guild_id)
+            .get_member(guild_id, user_id)
+            .get_guild_roles(guild_id);
+        let (guild, member, roles): (Option<S::Guild>, Option<S::Member>, Vec<Value>) =
+            pipe.query(&mut conn).await?;
+
+        let Some(member) = self.member(guild_id, user_id, guild, member)? else {
+            return Ok(Permissions::all());
+        };
+        let permissions = member_permissions::<S>(guild_id, &member, &roles)?;
+
+        if permissions.contains(Permissions::ADMINISTRATOR) {
+            return Ok(Permissions::all());
+        }
+
+        Ok(self.communication_disabled(&member, permissions))
+    }
+
+    /// Calculate the permissions of a member in a channel of a guild.
+    ///
+    /// The permission overwrites of the parent channel apply to threads.
+    pub async fn in_channel(
+        &self,
+        user_id: Id<UserMarker>,
+        guild_id: Id<GuildMarker>,
+        channel_id: Id<ChannelMarker>,
+    ) -> Result<Permissions, Error> {
+        let mut conn = self.cache.get_read_connection().await?;
+
+        let mut pipe = self.cache.pipe();
+        pipe.get_guild(guild_id)
+            .get_member(guild_id, user_id)
+            .get_guild_roles(guild_id)
+            .get_channel(Some(guild_id), channel_id);
+        let (guild, member, roles, channel): (_, _, Vec<Value>, Option<S::Channel>) =
+            pipe.query(&mut conn).await?;
+
+        let channel = channel.ok_or(PermissionError::ChannelUnavailable(channel_id))?;
+        let is_thread = channel.kind().is_thread();
+        let channel = match channel.parent_id().filter(|_| is_thread) {
+            Some(parent_id) => self
+                .cache
+                .get_channel(&mut conn, Some(guild_id), parent_id)
+                .await?
+                .ok_or(PermissionError::ChannelUnavailable(parent_id))?,
+            None => channel,
+        };
+
+        let Some(member) = self.member(guild_id, user_id, guild, member)? else {
+            return Ok(Permissions::all());
+        };
+        let mut permissions = member_permissions::<S>(guild_id, &member, &roles)?;
+
+        if permissions.contains(Permissions::ADMINISTRATOR) {
+            return Ok(Permissions::all());
+        }
+
+        if let Some(overwrites) = channel.permission_overwrites() {
+            permissions = apply_overwrites(permissions, overwrites, guild_id, user_id, &member);
+        }
+
+        let permissions = self.communication_disabled(&member, permissions);
+
+        if !permissions.contains(Permissions::VIEW_CHANNEL) {
+            return Ok(Permissions::empty());
+        }
+
+        let send_messages = if is_thread {
+            Permissions::SEND_MESSAGES_IN_THREADS
+        } else {
+            Permissions::SEND_MESSAGES
+        };
+
+        if permissions.contains(send_messages) {
+            Ok(permissions)
+        } else {
+            Ok(permissions - MESSAGING_PERMISSIONS)
+        }
+    }
+
+    /// Check that the guild and the member are cached, returning `None` if
+    /// the member owns the guild.
+    fn member(
+        &self,
+        guild_id: Id<GuildMarker>,
+        user_id: Id<UserMarker>,
+        guild: Option<S::Guild>,
+        member: Option<S::Member>,
+    ) -> Result<Option<S::Member>, PermissionError> {
+        let guild = guild.ok_or(PermissionError::GuildUnavailable(guild_id))?;
+
+        if guild.owner_id() == user_id {
+            return Ok(None);
+        }
+
+        member
+            .map(Some)
+            .ok_or(PermissionError::MemberUnavailable { guild_id, user_id })
+    }
+
+    /// Take away the permissions of a member whose communication is disabled.
+    fn communication_disabled(&self, member: &S::Member, permissions: Permissions) -> Permissions {
+        if !self.check_member_communication_disabled {
+            return permissions;
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |now| now.as_micros() as i64);
+
+        match member.communication_disabled_until() {
+            Some(until) if until.as_micros() > now => {
+                permissions & COMMUNICATION_DISABLED_ALLOWLIST
+            }
+            _ => permissions,
+        }
+    }
+}
+
+/// Combine the permissions of the `@everyone` role and the roles of the
+/// member, out of the roles of the guild read by `Pipe::get_guild_roles`.
+fn member_permissions<S: CacheStrategy>(
+    guild_id: Id<GuildMarker>,
+    member: &S::Member,
+    guild_roles: &[Value],
+) -> Result<Permissions, Error> {
+    let mut roles = HashMap::with_capacity(guild_roles.len() / 2);
+    for pair in guild_roles.chunks_exact(2) {
+        let role_id: u64 = redis::from_redis_value(&pair[0])?;
+        let role = Option::<WithGuildId<S::Role>>::from_cached_redis_value::<S::Codec>(&pair[1])?;
+
+        if let (Some(role_id), Some(role)) = (Id::<RoleMarker>::new_checked(role_id), role) {
+            roles.insert(role_id, role.resource);
+        }
+    }
+
+    std::iter::once(guild_id.cast())
+        .chain(member.roles().iter().copied())
+        .try_fold(Permissions::empty(), |permissions, role_id| {
+            let role = roles
+                .get(&role_id)
+                .ok_or(PermissionError::RoleUnavailable(role_id))?;
+            Ok(permissions | role.permissions())
+        })
+}
+
+/// Apply the overwrites of the `@everyone` role, then the roles of the
+/// member, then the member.
+fn apply_overwrites<M: CacheableMember>(
+    permissions: Permissions,
+    overwrites: &[PermissionOverwrite],
+    guild_id: Id<GuildMarker>,
+    user_id: Id<UserMarker>,
+    member: &M,
+) -> Permissions {
+    let mut everyone = (Permissions::empty(), Permissions::empty());
+    let mut roles = (Permissions::empty(), Permissions::empty());
+    let mut user = (Permissions::empty(), Permissions::empty());
+
+    for overwrite in overwrites {
+        let target = match overwrite.kind {
+            PermissionOverwriteType::Role if overwrite.id == guild_id.cast() => &mut everyone,
+            PermissionOverwriteType::Role if member.roles().contains(&overwrite.id.cast()) => {
+                &mut roles
+            }
+            PermissionOverwriteType::Member if overwrite.id == user_id.cast() => &mut user,
+            _ => continue,
+        };
+
+        target.0 |= overwrite.allow;
+        target.1 |= overwrite.deny;
+    }
+
+    [everyone, roles, user]
+        .into_iter()
+        .fold(permissions, |permissions, (allow, deny)| {
+            (permissions - deny) | allow
+        })
+}
+
+impl<S: CacheStrategy> RedisCache<S> {
+    /// Calculate the permissions of members from the cache.
+    pub const fn permissions(&self) -> RedisCachePermissions<'_, S> {
+        RedisCachePermissions::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use twilight_model::{
+        channel::{
+            permission_overwrite::{PermissionOverwrite, PermissionOverwriteType},
+            ChannelType,
+        },
+        gateway::payload::incoming::{ChannelCreate, GuildCreate},
+        guild::Permissions,
+        id::Id,
+        util::Timestamp,
+    };
+
+    use crate::test;
+
+    #[test]
+    fn test_permissions() {
+        test::block_on(async {
+            let mut cache = test::redis_cache().await;
+            let guild_id = Id::new(601);
+            let channel_id = Id::new(602);
+            let thread_id = Id::new(603);
+            let role_id = Id::new(604);
+            let user_id = Id::new(605);
+            let timed_out_id = Id::new(606);
+
+            let mut everyone = test::model::role(guild_id.cast());
+            everyone.permissions = Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES;
+            let mut role = test::model::role(role_id);
+            role.permissions = Permissions::EMBED_LINKS | Permissions::MANAGE_MESSAGES;
+
+            let mut member = test::model::member(user_id);
+            member.roles = vec![role_id];
+            let mut timed_out = test::model::member(timed_out_id);
+            timed_out.communication_disabled_until =
+                Some(Timestamp::from_secs(i32::MAX.into()).unwrap());
+
+            let mut channel = test::model::channel(channel_id, Some(guild_id));
+            channel.permission_overwrites = Some(vec![
+                PermissionOverwrite {
+                    allow: Permissions::empty(),
+                    deny: Permissions::SEND_MESSAGES,
+                    id: guild_id.cast(),
+                    kind: PermissionOverwriteType::Role,
+                },
+                PermissionOverwrite {
+                    allow: Permissions::ADD_REACTIONS,
+                    deny: Permissions::MANAGE_MESSAGES,
+                    id: role_id.cast(),
+                    kind: PermissionOverwriteType::Role,
+                },
+                PermissionOverwrite {
+                    allow: Permissions::SEND_MESSAGES,
+                    deny: Permissions::empty(),
+                    id: user_id.cast(),
+                    kind: PermissionOverwriteType::Member,
+                },
+            ]);
+
+            let mut guild = test::model::guild(guild_id);
+            guild.channels = vec![channel];
+            guild.members = vec![member, timed_out];
+            guild.roles = vec![everyone, role];
+            cache.update(GuildCreate(guild)).await.unwrap();
+
+            let mut thread = test::model::channel(thread_id, Some(guild_id));
+            thread.kind = ChannelType::PublicThread;
+            thread.parent_id = Some(channel_id);
+            cache.update(ChannelCreate(thread)).await.unwrap();
+
+            let permissions = cache.permissions();
+
+            assert_eq!(
+                permissions.root(user_id, guild_id).await.unwrap(),
+                Permissions::VIEW_CHANNEL
+                    | Permissions::SEND_MESSAGES
+                    | Permissions::EMBED_LINKS
+                    | Permissions::MANAGE_MESSAGES
+            );
+            assert_eq!(
+                permissions
+                    .in_channel(user_id, guild_id, channel_id)
+                    .await
+                    .unwrap(),
+                Permissions::VIEW_CHANNEL
+                    | Permissions::SEND_MESSAGES
+                    | Permissions::EMBED_LINKS
+                    | Permissions::ADD_REACTIONS
+            );
+            // Threads use the overwrites of their parent, but messaging in
+            // them needs its own permission.
+            assert_eq!(
+                permissions
+                    .in_channel(user_id, guild_id, thread_id)
+                    .await
+                    .unwrap(),
+                Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES | Permissions::ADD_REACTIONS
+            );
+            assert_eq!(
+                permissions
+                    .in_channel(timed_out_id, guild_id, channel_id)
+                    .await
+                    .unwrap(),
+                Permissions::VIEW_CHANNEL
+            );
+            assert_eq!(
+                permissions
+                    .check_member_communication_disabled(false)
+                    .root(timed_out_id, guild_id)
+                    .await
+                    .unwrap(),
+                Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES
+            );
+
+            // The fixture guild is owned by the user 1.
+            assert_eq!(
+                cache
+                    .permissions()
+                    .root(Id::new(1), guild_id)
+                    .await
+                    .unwrap(),
+                Permissions::all()
+            );
+        });
+    }
+}
@@ -11,6 +11,7 @@ pub(crate) fn runtime() -> &'static Runtime {
     RUNTIME.get_or_init(|| {
         tokio::runtime::Builder::new_current_thread()
             .enable_io()
+            .enable_time()
             .build()
             .unwrap()
     })
@@ -21,6 +22,10 @@ pub(crate) fn block_on(future: impl std::future::Future) {
 }
 
 pub(crate) async fn redis_cache() -> RedisCache<DefaultCacheStrategy> {
+    redis_cache_with_config(Config::default()).await
+}
+
+pub(crate) async fn redis_cache_with_config(config: Config) -> RedisCache<DefaultCacheStrategy> {
     static REDIS: OnceLock<Client> = OnceLock::new();
 
     let url = option_env!("TEST_REDIS_URL").unwrap_or("redis://127.0.0.1");
@@ -30,12 +35,38 @@ pub(crate) async fn redis_cache() -> RedisCache<DefaultCacheStrategy> {
         ConnectionDriver::MultiplexedClone(
             client.get_multiplexed_tokio_connection().await.unwrap(),
         ),
-        Config::default(),
+        None,
+        config,
     )
 }
 
 pub mod model {
-    use twilight_model::{id::Id, user::CurrentUser};
+    use twilight_model::{
+        channel::{
+            message::{
+                sticker::{StickerFormatType, StickerType},
+                MessageType, Sticker,
+            },
+            thread::{AutoArchiveDuration, ThreadMember, ThreadMetadata},
+            Channel, ChannelType, Message,
+        },
+        gateway::presence::{Activity, ActivityType, ClientStatus, Presence, Status, UserOrId},
+        guild::{
+            AfkTimeout, DefaultMessageNotificationLevel, Emoji, ExplicitContentFilter, Guild,
+            Member, MemberFlags, MfaLevel, NSFWLevel, Permissions, PremiumTier, Role, RoleFlags,
+            SystemChannelFlags, VerificationLevel,
+        },
+        id::{
+            marker::{
+                ChannelMarker, EmojiMarker, GuildMarker, MessageMarker, RoleMarker, StickerMarker,
+                UserMarker,
+            },
+            Id,
+        },
+        user::{CurrentUser, User},
+        util::Timestamp,
+        voice::VoiceState,
+    };
 
     pub fn current_user() -> CurrentUser {
         CurrentUser {
@@ -55,4 +86,306 @@ pub mod model {
             locale: None,
         }
     }
+
+    pub fn user(id: Id<UserMarker>) -> User {
+        User {
+            accent_color: None,
+            avatar: None,
+            avatar_decoration: None,
+            banner: None,
+            bot: false,
+            discriminator: 1,
+            email: None,
+            flags: None,
+            global_name: Some("test".to_owned()),
+            id,
+            locale: None,
+            mfa_enabled: None,
+            name: "user".to_owned(),
+            premium_type: None,
+            public_flags: None,
+            system: None,
+            verified: None,
+        }
+    }
+
+    pub fn member(user_id: Id<UserMarker>) -> Member {
+        Member {
+            avatar: None,
+            communication_disabled_until: None,
+            deaf: false,
+            flags: MemberFlags::empty(),
+            joined_at: Timestamp::from_secs(1_632_072_645).unwrap(),
+            mute: false,
+            nick: None,
+            pending: false,
+            premium_since: None,
+            roles: Vec::new(),
+            user: user(user_id),
+        }
+    }
+
+    pub fn role(id: Id<RoleMarker>) -> Role {
+        Role {
+            color: 0,
+            hoist: false,
+            icon: None,
+            id,
+            managed: false,
+            mentionable: false,
+            name: "role".to_owned(),
+            permissions: Permissions::empty(),
+            position: 0,
+            flags: RoleFlags::empty(),
+            tags: None,
+            unicode_emoji: None,
+        }
+    }
+
+    pub fn channel(id: Id<ChannelMarker>, guild_id: Option<Id<GuildMarker>>) -> Channel {
+        Channel {
+            application_id: None,
+            applied_tags: None,
+            available_tags: None,
+            bitrate: None,
+            default_auto_archive_duration: None,
+            default_forum_layout: None,
+            default_reaction_emoji: None,
+            default_sort_order: None,
+            default_thread_rate_limit_per_user: None,
+            flags: None,
+            guild_id,
+            icon: None,
+            id,
+            invitable: None,
+            kind: ChannelType::GuildText,
+            last_message_id: None,
+            last_pin_timestamp: None,
+            managed: None,
+            member: None,
+            member_count: None,
+            message_count: None,
+            name: Some("channel".to_owned()),
+            newly_created: None,
+            nsfw: None,
+            owner_id: None,
+            parent_id: None,
+            permission_overwrites: None,
+            position: None,
+            rate_limit_per_user: None,
+            recipients: None,
+            rtc_region: None,
+            thread_metadata: None,
+            topic: None,
+            user_limit: None,
+            video_quality_mode: None,
+        }
+    }
+
+    pub fn thread(
+        id: Id<ChannelMarker>,
+        guild_id: Id<GuildMarker>,
+        parent_id: Id<ChannelMarker>,
+        archived: bool,
+    ) -> Channel {
+        Channel {
+            kind: ChannelType::PublicThread,
+            parent_id: Some(parent_id),
+            thread_metadata: Some(ThreadMetadata {
+                archived,
+                auto_archive_duration: AutoArchiveDuration::Day,
+                archive_timestamp: Timestamp::from_secs(1_632_072_645).unwrap(),
+                create_timestamp: None,
+                invitable: None,
+                locked: false,
+            }),
+            ..channel(id, Some(guild_id))
+        }
+    }
+
+    pub fn thread_member(thread_id: Id<ChannelMarker>, user_id: Id<UserMarker>) -> ThreadMember {
+        ThreadMember {
+            flags: 0,
+            id: Some(thread_id),
+            join_timestamp: Timestamp::from_secs(1_632_072_645).unwrap(),
+            member: None,
+            presence: None,
+            user_id: Some(user_id),
+        }
+    }
+
+    pub fn guild(id: Id<GuildMarker>) -> Guild {
+        Guild {
+            afk_channel_id: None,
+            afk_timeout: AfkTimeout::FIVE_MINUTES,
+            application_id: None,
+            approximate_member_count: None,
+            approximate_presence_count: None,
+            banner: None,
+            channels: Vec::new(),
+            default_message_notifications: DefaultMessageNotificationLevel::Mentions,
+            description: None,
+            discovery_splash: None,
+            emojis: Vec::new(),
+            explicit_content_filter: ExplicitContentFilter::None,
+            features: Vec::new(),
+            icon: None,
+            id,
+            joined_at: None,
+            large: false,
+            max_members: None,
+            max_presences: None,
+            max_video_channel_users: None,
+            member_count: Some(0),
+            members: Vec::new(),
+            mfa_level: MfaLevel::None,
+            name: "guild".to_owned(),
+            nsfw_level: NSFWLevel::Default,
+            owner_id: Id::new(1),
+            owner: None,
+            permissions: None,
+            preferred_locale: "en-US".to_owned(),
+            premium_progress_bar_enabled: false,
+            premium_subscription_count: None,
+            premium_tier: PremiumTier::None,
+            presences: Vec::new(),
+            public_updates_channel_id: None,
+            roles: Vec::new(),
+            rules_channel_id: None,
+            safety_alerts_channel_id: None,
+            splash: None,
+            stage_instances: Vec::new(),
+            stickers: Vec::new(),
+            system_channel_flags: SystemChannelFlags::empty(),
+            system_channel_id: None,
+            threads: Vec::new(),
+            unavailable: false,
+            vanity_url_code: None,
+            verification_level: VerificationLevel::None,
+            voice_states: Vec::new(),
+            widget_channel_id: None,
+            widget_enabled: None,
+        }
+    }
+
+    pub fn message(
+        id: Id<MessageMarker>,
+        channel_id: Id<ChannelMarker>,
+        author_id: Id<UserMarker>,
+    ) -> Message {
+        Message {
+            activity: None,
+            application: None,
+            application_id: None,
+            attachments: Vec::new(),
+            author: user(author_id),
+            channel_id,
+            components: Vec::new(),
+            content: "content".to_owned(),
+            edited_timestamp: None,
+            embeds: Vec::new(),
+            flags: None,
+            guild_id: None,
+            id,
+            interaction: None,
+            kind: MessageType::Regular,
+            member: None,
+            mention_channels: Vec::new(),
+            mention_everyone: false,
+            mention_roles: Vec::new(),
+            mentions: Vec::new(),
+            pinned: false,
+            reactions: Vec::new(),
+            reference: None,
+            referenced_message: None,
+            role_subscription_data: None,
+            sticker_items: Vec::new(),
+            timestamp: Timestamp::from_secs(1_632_072_645).unwrap(),
+            thread: None,
+            tts: false,
+            webhook_id: None,
+        }
+    }
+
+    pub fn emoji(id: Id<EmojiMarker>, user_id: Option<Id<UserMarker>>) -> Emoji {
+        Emoji {
+            animated: false,
+            available: true,
+            id,
+            managed: false,
+            name: "emoji".to_owned(),
+            require_colons: true,
+            roles: Vec::new(),
+            user: user_id.map(user),
+        }
+    }
+
+    pub fn sticker(id: Id<StickerMarker>, guild_id: Id<GuildMarker>) -> Sticker {
+        Sticker {
+            available: true,
+            description: Some("description".to_owned()),
+            format_type: StickerFormatType::Png,
+            guild_id: Some(guild_id),
+            id,
+            kind: StickerType::Guild,
+            name: "sticker".to_owned(),
+            pack_id: None,
+            sort_value: None,
+            tags: "tags".to_owned(),
+            user: None,
+        }
+    }
+
+    pub fn presence(guild_id: Id<GuildMarker>, user_id: Id<UserMarker>) -> Presence {
+        Presence {
+            activities: vec![Activity {
+                application_id: None,
+                assets: None,
+                buttons: Vec::new(),
+                created_at: Some(1_632_072_645_000),
+                details: None,
+                emoji: None,
+                flags: None,
+                id: None,
+                instance: None,
+                kind: ActivityType::Playing,
+                name: "game".to_owned(),
+                party: None,
+                secrets: None,
+                state: None,
+                timestamps: None,
+                url: None,
+            }],
+            client_status: ClientStatus {
+                desktop: Some(Status::Online),
+                mobile: None,
+                web: None,
+            },
+            guild_id,
+            status: Status::Online,
+            user: UserOrId::UserId { id: user_id },
+        }
+    }
+
+    pub fn voice_state(
+        guild_id: Id<GuildMarker>,
+        channel_id: Id<ChannelMarker>,
+        user_id: Id<UserMarker>,
+    ) -> VoiceState {
+        VoiceState {
+            channel_id: Some(channel_id),
+            deaf: false,
+            guild_id: Some(guild_id),
+            member: None,
+            mute: false,
+            self_deaf: false,
+            self_mute: true,
+            self_stream: false,
+            self_video: false,
+            session_id: "session".to_owned(),
+            suppress: false,
+            user_id,
+            request_to_speak_timestamp: None,
+        }
+    }
 }
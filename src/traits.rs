@@ -30,6 +30,7 @@ use twilight_model::{channel::permission_overwrite::PermissionOverwrite, guild::
 use twilight_model::{
     channel::{
         message::{Reaction, Sticker},
+        thread::ThreadMember,
         Channel, ChannelType, Message, StageInstance,
     },
     gateway::{
@@ -46,14 +47,18 @@ use twilight_model::{
     voice::VoiceState,
 };
 
-use crate::cache::{
-    value::{impl_from_bytes_for_model, impl_to_bytes_for_model},
-    FromBytes, FromCachedRedisValue, ToBytes,
+use crate::{
+    cache::{
+        value::{impl_from_bytes_for_model, impl_to_bytes_for_model},
+        FromBytes, FromCachedRedisValue, ToBytes,
+    },
+    codec::Codec,
 };
 
 /// Super-trait for the generic cached representations of Discord API models.
 pub trait CacheStrategy: Send + Sync {
-    type SerdeError: std::error::Error;
+    /// The format the cached models are stored in.
+    type Codec: Codec;
 
     /// The cached [`Member`] model representation.
     type Member: CacheableMember;
@@ -83,6 +88,8 @@ pub trait CacheStrategy: Send + Sync {
     type StageInstance: CacheableStageInstance;
     /// The cached [`User`] model representation.
     type User: CacheableUser;
+    /// The cached [`ThreadMember`] model representation.
+    type ThreadMember: CacheableThreadMember;
 }
 
 /// Trait for a generic cached representation of a [`Member`].
@@ -165,7 +172,6 @@ impl CacheableRole for Role {
 }
 
 /// Trait for a generic cached representation of a [`Channel`].
-
 pub trait CacheableChannel:
     From<Channel>
     + PartialEq<Channel>
@@ -186,9 +192,11 @@ pub trait CacheableChannel:
     fn kind(&self) -> ChannelType;
 
     /// ID of the parent channel if this is a thread.
-    #[cfg(feature = "permission-calculator")]
     fn parent_id(&self) -> Option<Id<ChannelMarker>>;
 
+    /// Whether this is an archived thread.
+    fn archived(&self) -> bool;
+
     /// ID of the channel.
     fn id(&self) -> Id<ChannelMarker>;
 
@@ -212,11 +220,16 @@ impl CacheableChannel for Channel {
         self.kind
     }
 
-    #[cfg(feature = "permission-calculator")]
     fn parent_id(&self) -> Option<Id<ChannelMarker>> {
         self.parent_id
     }
 
+    fn archived(&self) -> bool {
+        self.thread_metadata
+            .as_ref()
+            .is_some_and(|metadata| metadata.archived)
+    }
+
     fn id(&self) -> Id<ChannelMarker> {
         self.id
     }
@@ -296,6 +309,28 @@ pub trait CacheableChannelVoiceState:
     + DeserializeOwned
     + FromBytes
     + ToBytes
+{
+    /// ID of the guild of the voice channel.
+    fn guild_id(&self) -> Id<GuildMarker>;
+
+    /// ID of the user in the voice channel.
+    fn user_id(&self) -> Id<UserMarker>;
+}
+
+/// Trait for a generic cached representation of a [`ThreadMember`], along with
+/// the ids of its thread and user.
+pub trait CacheableThreadMember:
+    From<(Id<ChannelMarker>, Id<UserMarker>, ThreadMember)>
+    + PartialEq<ThreadMember>
+    + PartialEq<Self>
+    + Clone
+    + Debug
+    + Send
+    + Sync
+    + Serialize
+    + DeserializeOwned
+    + FromBytes
+    + ToBytes
 {
 }
 
@@ -316,6 +351,12 @@ pub trait CacheableMessage:
     /// Update the cached data with a [`MessageUpdate`] event.
     fn update_with_message_update(&mut self, message_update: &MessageUpdate);
 
+    /// ID of the author of the message.
+    fn author(&self) -> Id<UserMarker>;
+
+    /// Whether the message is pinned.
+    fn pinned(&self) -> bool;
+
     /// Reactions added to this message.
     fn reactions(&self) -> &[Reaction];
 
@@ -394,6 +435,8 @@ pub trait CacheableEmoji:
     + FromBytes
     + ToBytes
 {
+    /// ID of the user who created the emoji, if known.
+    fn user_id(&self) -> Option<Id<UserMarker>>;
 }
 
 /// Trait for a generic cached representation of a [`GuildIntegration`].